@@ -0,0 +1,125 @@
+//! Translation of virtual addresses to the file contents backing them.
+//!
+//! Addresses are run-time addresses: the `bias` the file is loaded at is
+//! subtracted before they are compared with the link-time addresses in the
+//! program and section headers. For files that are not position independent, or
+//! when working with link-time addresses, the bias is zero.
+
+use std::io;
+
+use super::{File, Section};
+use super::types;
+
+impl File {
+    /// Returns the `PT_LOAD` segment whose file contents include the
+    /// link-time address `addr`.
+    fn load_segment_for_vaddr(&self, addr: u64) -> Option<&types::ProgramHeader> {
+        self.phdrs.iter()
+            .filter(|p| p.progtype == types::PT_LOAD)
+            .find(|p| p.vaddr <= addr && addr - p.vaddr < p.filesz)
+    }
+
+    /// Returns the allocated section whose memory image contains `addr`,
+    /// with the file loaded at `bias`.
+    ///
+    /// Thread-local `SHT_NOBITS` sections (`.tbss`) occupy no space in the
+    /// address space of the loaded file and are never returned.
+    pub fn section_for_vaddr(&self, addr: u64, bias: u64) -> Option<&Section> {
+        let addr = match addr.checked_sub(bias) {
+            Some(addr) => addr,
+            None => return None,
+        };
+        self.sections.iter()
+            .filter(|s| (s.shdr.flags.0 & types::SHF_ALLOC.0) != 0)
+            .filter(|s| !(s.shdr.shtype == types::SHT_NOBITS &&
+                          (s.shdr.flags.0 & types::SHF_TLS.0) != 0))
+            .find(|s| s.shdr.addr <= addr && addr - s.shdr.addr < s.shdr.size)
+    }
+
+    /// Translates `addr`, with the file loaded at `bias`, to the offset in
+    /// the file holding its initial contents.
+    ///
+    /// Uses the `PT_LOAD` segments, or the section headers for files
+    /// without program headers. Returns `None` for addresses that are not
+    /// mapped, or that fall into zero-initialized memory (`.bss`).
+    pub fn vaddr_to_offset(&self, addr: u64, bias: u64) -> Option<u64> {
+        let addr = match addr.checked_sub(bias) {
+            Some(addr) => addr,
+            None => return None,
+        };
+        if self.phdrs.is_empty() {
+            return self.section_for_vaddr(addr, 0).and_then(|s| {
+                if s.shdr.shtype == types::SHT_NOBITS {
+                    None
+                } else {
+                    s.shdr.offset.checked_add(addr - s.shdr.addr)
+                }
+            });
+        }
+        self.load_segment_for_vaddr(addr)
+            .and_then(|p| p.offset.checked_add(addr - p.vaddr))
+    }
+
+    /// Returns the `len` bytes of initial contents starting at `addr`, with
+    /// the file loaded at `bias`.
+    ///
+    /// The range must lie within a single segment, and its contents within
+    /// a single section; bytes outside any section, such as the file and
+    /// program headers, cannot be read. Files without sections, such as
+    /// core dumps, are read from the segment contents instead.
+    pub fn read_at_vaddr(&self, addr: u64, bias: u64, len: u64) -> Option<&[u8]> {
+        let addr = match addr.checked_sub(bias) {
+            Some(addr) => addr,
+            None => return None,
+        };
+        if self.phdrs.is_empty() {
+            return self.section_for_vaddr(addr, 0)
+                .and_then(|s| {
+                    if s.shdr.shtype == types::SHT_NOBITS {
+                        return None;
+                    }
+                    let start = addr - s.shdr.addr;
+                    match start.checked_add(len) {
+                        Some(end) if end <= s.shdr.size => {
+                            Some(&s.data[start as usize..end as usize])
+                        }
+                        _ => None,
+                    }
+                });
+        }
+
+        let phdr = match self.load_segment_for_vaddr(addr) {
+            Some(phdr) => phdr,
+            None => return None,
+        };
+        let offset = match phdr.offset.checked_add(addr - phdr.vaddr) {
+            Some(offset) => offset,
+            None => return None,
+        };
+        match (offset.checked_add(len), phdr.offset.checked_add(phdr.filesz)) {
+            (Some(end), Some(segment_end)) if end <= segment_end => self.file_range(offset, len),
+            _ => None,
+        }
+    }
+
+    /// Reads the address-sized word stored at `addr`, e.g. a pointer in a
+    /// data section.
+    ///
+    /// The value is returned as stored in the file, i.e. as a link-time
+    /// address; add `bias` to obtain the run-time address. Pointers in
+    /// position-independent files may instead be filled in by dynamic
+    /// relocations at load time.
+    pub fn read_word_at_vaddr(&self, addr: u64, bias: u64) -> Option<u64> {
+        let size = if self.ehdr.class == types::ELFCLASS32 { 4 } else { 8 };
+        let bytes = match self.read_at_vaddr(addr, bias, size) {
+            Some(bytes) => bytes,
+            None => return None,
+        };
+        let mut rdr = io::Cursor::new(bytes);
+        if size == 4 {
+            read_u32!(self, rdr).ok().map(|w| w as u64)
+        } else {
+            read_u64!(self, rdr).ok()
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! The names binutils readelf prints for ELF header fields and note
+//! contents, which differ from the descriptions `elf::types` displays.
+
+use elf;
+use elf::types;
+
+/// Machine names, as printed by readelf 2.40
+const MACHINES: &'static [(u16, &'static str)] = &[
+    (0, "None"),
+    (1, "WE32100"),
+    (2, "Sparc"),
+    (3, "Intel 80386"),
+    (4, "MC68000"),
+    (5, "MC88000"),
+    (6, "Intel MCU"),
+    (7, "Intel 80860"),
+    (8, "MIPS R3000"),
+    (9, "IBM System/370"),
+    (10, "MIPS R4000 big-endian"),
+    (11, "Sparc v9 (old)"),
+    (15, "HPPA"),
+    (17, "Fujitsu VPP500"),
+    (18, "Sparc v8+"),
+    (19, "Intel 80960"),
+    (20, "PowerPC"),
+    (21, "PowerPC64"),
+    (22, "IBM S/390"),
+    (23, "SPU"),
+    (36, "Renesas V850 (using RH850 ABI)"),
+    (37, "Fujitsu FR20"),
+    (38, "TRW RH32"),
+    (39, "MCORE"),
+    (40, "ARM"),
+    (41, "Digital Alpha (old)"),
+    (42, "Renesas / SuperH SH"),
+    (43, "Sparc v9"),
+    (44, "Siemens Tricore"),
+    (45, "ARC"),
+    (46, "Renesas H8/300"),
+    (47, "Renesas H8/300H"),
+    (48, "Renesas H8S"),
+    (49, "Renesas H8/500"),
+    (50, "Intel IA-64"),
+    (51, "Stanford MIPS-X"),
+    (52, "Motorola Coldfire"),
+    (53, "Motorola MC68HC12 Microcontroller"),
+    (54, "Fujitsu Multimedia Accelerator"),
+    (55, "Siemens PCP"),
+    (56, "Sony nCPU embedded RISC processor"),
+    (57, "Denso NDR1 microprocesspr"),
+    (58, "Motorola Star*Core processor"),
+    (59, "Toyota ME16 processor"),
+    (60, "STMicroelectronics ST100 processor"),
+    (61, "Advanced Logic Corp. TinyJ embedded processor"),
+    (62, "Advanced Micro Devices X86-64"),
+    (63, "Sony DSP processor"),
+    (64, "Digital Equipment Corp. PDP-10"),
+    (65, "Digital Equipment Corp. PDP-11"),
+    (66, "Siemens FX66 microcontroller"),
+    (67, "STMicroelectronics ST9+ 8/16 bit microcontroller"),
+    (68, "STMicroelectronics ST7 8-bit microcontroller"),
+    (69, "Motorola MC68HC16 Microcontroller"),
+    (70, "Motorola MC68HC11 Microcontroller"),
+    (71, "Motorola MC68HC08 Microcontroller"),
+    (72, "Motorola MC68HC05 Microcontroller"),
+    (73, "Silicon Graphics SVx"),
+    (74, "STMicroelectronics ST19 8-bit microcontroller"),
+    (75, "Digital VAX"),
+    (76, "Axis Communications 32-bit embedded processor"),
+    (77, "Infineon Technologies 32-bit embedded cpu"),
+    (78, "Element 14 64-bit DSP processor"),
+    (79, "LSI Logic's 16-bit DSP processor"),
+    (80, "Donald Knuth's educational 64-bit processor"),
+    (81, "Harvard Universitys's machine-independent object format"),
+    (82, "Vitesse Prism"),
+    (83, "Atmel AVR 8-bit microcontroller"),
+    (84, "Fujitsu FR30"),
+    (85, "d10v"),
+    (86, "d30v"),
+    (87, "Renesas V850"),
+    (88, "Renesas M32R (formerly Mitsubishi M32r)"),
+    (89, "mn10300"),
+    (90, "mn10200"),
+    (91, "picoJava"),
+    (92, "OpenRISC 1000"),
+    (93, "ARCompact"),
+    (94, "Tensilica Xtensa Processor"),
+    (95, "Alphamosaic VideoCore processor"),
+    (96, "Thompson Multimedia General Purpose Processor"),
+    (97, "National Semiconductor 32000 series"),
+    (98, "Tenor Network TPC processor"),
+    (99, "Trebia SNP 1000 processor"),
+    (100, "STMicroelectronics ST200 microcontroller"),
+    (101, "Ubicom IP2xxx 8-bit microcontrollers"),
+    (102, "MAX Processor"),
+    (103, "National Semiconductor CompactRISC"),
+    (104, "Fujitsu F2MC16"),
+    (105, "Texas Instruments msp430 microcontroller"),
+    (106, "Analog Devices Blackfin"),
+    (107, "S1C33 Family of Seiko Epson processors"),
+    (108, "Sharp embedded microprocessor"),
+    (109, "Arca RISC microprocessor"),
+    (110, "Unicore"),
+    (111, "eXcess 16/32/64-bit configurable embedded CPU"),
+    (112, "Icera Semiconductor Inc. Deep Execution Processor"),
+    (113, "Altera Nios II"),
+    (114, "National Semiconductor CRX microprocessor"),
+    (115, "Motorola XGATE embedded processor"),
+    (116, "Infineon Technologies xc16x"),
+    (117, "Renesas M16C series microprocessors"),
+    (118, "Microchip Technology dsPIC30F Digital Signal Controller"),
+    (119, "Freescale Communication Engine RISC core"),
+    (120, "Renesas M32c"),
+    (131, "Altium TSK3000 core"),
+    (132, "Freescale RS08 embedded processor"),
+    (134, "Cyan Technology eCOG2 microprocessor"),
+    (135, "SUNPLUS S+Core"),
+    (136, "New Japan Radio (NJR) 24-bit DSP Processor"),
+    (137, "Broadcom VideoCore III processor"),
+    (138, "Lattice Mico32"),
+    (139, "Seiko Epson C17 family"),
+    (140, "Texas Instruments TMS320C6000 DSP family"),
+    (141, "Texas Instruments TMS320C2000 DSP family"),
+    (142, "Texas Instruments TMS320C55x DSP family"),
+    (144, "TI PRU I/O processor"),
+    (160, "STMicroelectronics 64bit VLIW Data Signal Processor"),
+    (161, "Cypress M8C microprocessor"),
+    (162, "Renesas R32C series microprocessors"),
+    (163, "NXP Semiconductors TriMedia architecture family"),
+    (164, "QUALCOMM DSP6 Processor"),
+    (165, "Intel 8051 and variants"),
+    (166, "STMicroelectronics STxP7x family"),
+    (167, "Andes Technology compact code size embedded RISC processor family"),
+    (168, "Cyan Technology eCOG1X family"),
+    (169, "Dallas Semiconductor MAXQ30 Core microcontrollers"),
+    (170, "New Japan Radio (NJR) 16-bit DSP Processor"),
+    (171, "M2000 Reconfigurable RISC Microprocessor"),
+    (172, "Cray Inc. NV2 vector architecture"),
+    (173, "Renesas RX"),
+    (174, "Imagination Technologies Meta processor architecture"),
+    (175, "MCST Elbrus general purpose hardware architecture"),
+    (176, "Cyan Technology eCOG16 family"),
+    (177, "Xilinx MicroBlaze"),
+    (178, "Freescale Extended Time Processing Unit"),
+    (179, "Infineon Technologies SLE9X core"),
+    (180, "Intel L1OM"),
+    (181, "Intel K1OM"),
+    (182, "Intel (reserved)"),
+    (183, "AArch64"),
+    (184, "ARM (reserved)"),
+    (185, "Atmel Corporation 32-bit microprocessor"),
+    (186, "STMicroeletronics STM8 8-bit microcontroller"),
+    (187, "Tilera TILE64 multicore architecture family"),
+    (188, "Tilera TILEPro multicore architecture family"),
+    (189, "Xilinx MicroBlaze"),
+    (190, "NVIDIA CUDA architecture"),
+    (191, "Tilera TILE-Gx multicore architecture family"),
+    (192, "CloudShield architecture family"),
+    (193, "KIPO-KAIST Core-A 1st generation processor family"),
+    (194, "KIPO-KAIST Core-A 2nd generation processor family"),
+    (195, "ARCv2"),
+    (196, "Open8 8-bit RISC soft processor core"),
+    (197, "Renesas RL78"),
+    (198, "Broadcom VideoCore V processor"),
+    (199, "Renesas 78K0R"),
+    (200, "Freescale 56800EX Digital Signal Controller (DSC)"),
+    (201, "Beyond BA1 CPU architecture"),
+    (202, "Beyond BA2 CPU architecture"),
+    (203, "XMOS xCORE processor family"),
+    (204, "Microchip 8-bit PIC(r) family"),
+    (205, "Intel Graphics Technology"),
+    (210, "KM211 KM32 32-bit processor"),
+    (211, "KM211 KMX32 32-bit processor"),
+    (212, "KM211 KMX16 16-bit processor"),
+    (213, "KM211 KMX8 8-bit processor"),
+    (214, "KM211 KVARC processor"),
+    (215, "Paneve CDP architecture family"),
+    (216, "Cognitive Smart Memory Processor"),
+    (217, "Bluechip Systems CoolEngine"),
+    (218, "Nanoradio Optimized RISC"),
+    (219, "CSR Kalimba architecture family"),
+    (220, "Zilog Z80"),
+    (221, "CDS VISIUMcore processor"),
+    (222, "FTDI Chip FT32"),
+    (223, "Moxie"),
+    (224, "AMD GPU"),
+    (243, "RISC-V"),
+    (244, "Lanai 32-bit processor"),
+    (245, "CEVA Processor Architecture Family"),
+    (246, "CEVA X2 Processor Family"),
+    (247, "Linux BPF"),
+    (248, "Graphcore Intelligent Processing Unit"),
+    (249, "Imagination Technologies"),
+    (250, "Netronome Flow Processor"),
+    (251, "NEC Vector Engine"),
+    (252, "C-SKY"),
+    (253, "Synopsys ARCv2.3 64-bit"),
+    (254, "MOS Technology MCS 6502 processor"),
+    (255, "Synopsys ARCv2.3 32-bit"),
+    (256, "Kalray VLIW core of the MPPA processor family"),
+    (257, "WDC 65816/65C816"),
+    (258, "LoongArch"),
+    (259, "ChipON KungFu32"),
+    (0x1057, "Atmel AVR 8-bit microcontroller"),
+    (0x2530, "Morpho Techologies MT processor"),
+    (0x3330, "Fujitsu FR30"),
+    (0x4157, "Web Assembly"),
+    (0x4688, "Infineon Technologies xc16x"),
+    (0x5441, "Fujitsu FR-V"),
+    (0x5aa5, "OpenDLX"),
+    (0x7650, "d10v"),
+    (0x7676, "d30v"),
+    (0x8217, "Ubicom IP2xxx 8-bit microcontrollers"),
+    (0x9026, "Alpha"),
+    (0x9041, "Renesas M32R (formerly Mitsubishi M32r)"),
+    (0x9080, "Renesas V850"),
+    (0xa390, "IBM S/390"),
+    (0xabc7, "Tensilica Xtensa Processor"),
+    (0xad45, "Sanyo XStormy16 CPU core"),
+    (0xbaab, "Xilinx MicroBlaze"),
+    (0xbeef, "mn10300"),
+    (0xdead, "mn10200"),
+    (0xf00d, "Toshiba MeP Media Engine"),
+    (0xfeb0, "Altera Nios"),
+    (0xfeba, "Vitesse IQ2000"),
+    (0xfebb, "Altera Nios"),
+];
+
+/// OS ABI names, as printed by readelf
+const OSABIS: &'static [&'static str] = &[
+    "UNIX - System V", "UNIX - HP-UX", "UNIX - NetBSD", "UNIX - GNU", "", "",
+    "UNIX - Solaris", "UNIX - AIX", "UNIX - IRIX", "UNIX - FreeBSD", "UNIX - TRU64",
+    "Novell - Modesto", "UNIX - OpenBSD", "VMS - OpenVMS", "HP - Non-Stop Kernel", "AROS",
+    "FenixOS", "Nuxi CloudABI", "Stratus Technologies OpenVOS",
+];
+
+/// `DF_1_PIE` of `DT_FLAGS_1`: the file is a position-independent executable
+const DF_1_PIE: u64 = 0x08000000;
+
+pub fn class(class: types::Class) -> String {
+    match class {
+        types::ELFCLASSNONE => "none".to_string(),
+        types::ELFCLASS32 => "ELF32".to_string(),
+        types::ELFCLASS64 => "ELF64".to_string(),
+        _ => format!("<unknown: {:x}>", class.0),
+    }
+}
+
+pub fn data(data: types::Data) -> String {
+    match data {
+        types::ELFDATANONE => "none".to_string(),
+        types::ELFDATA2LSB => "2's complement, little endian".to_string(),
+        types::ELFDATA2MSB => "2's complement, big endian".to_string(),
+        _ => format!("<unknown: {:x}>", data.0),
+    }
+}
+
+/// The version in `e_ident`
+pub fn ident_version(version: u8) -> String {
+    match version {
+        0 => "0".to_string(),
+        1 => "1 (current)".to_string(),
+        v => format!("{} <unknown>", v),
+    }
+}
+
+pub fn osabi(osabi: types::OSABI) -> String {
+    match OSABIS.get(osabi.0 as usize) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("<unknown: {:x}>", osabi.0),
+    }
+}
+
+/// The file type; shared objects flagged `DF_1_PIE` are executables
+pub fn file_type(file: &elf::File) -> String {
+    let t = file.ehdr.elftype.0;
+    match t {
+        0 => "NONE (None)".to_string(),
+        1 => "REL (Relocatable file)".to_string(),
+        2 => "EXEC (Executable file)".to_string(),
+        3 if is_pie(file) => "DYN (Position-Independent Executable file)".to_string(),
+        3 => "DYN (Shared object file)".to_string(),
+        4 => "CORE (Core file)".to_string(),
+        0xfe00...0xfeff => format!("OS Specific: ({:x})", t),
+        0xff00...0xffff => format!("Processor Specific: ({:x})", t),
+        _ => format!("<unknown>: {:x}", t),
+    }
+}
+
+fn is_pie(file: &elf::File) -> bool {
+    match file.dynamic() {
+        Ok(entries) => entries.iter().any(|d| match *d {
+            elf::Dynamic::Flags1(flags) => (flags & DF_1_PIE) != 0,
+            _ => false,
+        }),
+        Err(_) => false,
+    }
+}
+
+pub fn machine(machine: types::Machine) -> String {
+    match MACHINES.iter().find(|&&(m, _)| m == machine.0) {
+        Some(&(_, name)) => name.to_string(),
+        None => format!("<unknown>: {:#x}", machine.0),
+    }
+}
+
+/// Processor-specific section types, by machine
+const SECTION_TYPES: &'static [(types::Machine, u32, &'static str)] = &[
+    (types::EM_ARM, 0x70000001, "ARM_EXIDX"),
+    (types::EM_ARM, 0x70000002, "ARM_PREEMPTMAP"),
+    (types::EM_ARM, 0x70000003, "ARM_ATTRIBUTES"),
+    (types::EM_ARM, 0x70000004, "ARM_DEBUGOVERLAY"),
+    (types::EM_ARM, 0x70000005, "ARM_OVERLAYSECTION"),
+    (types::EM_X86_64, 0x70000001, "X86_64_UNWIND"),
+    (types::EM_RISCV, 0x70000003, "RISCV_ATTRIBUTES"),
+];
+
+pub fn section_type(file: &elf::File, shtype: types::SectionType) -> String {
+    let machine = file.ehdr.machine;
+    match SECTION_TYPES.iter().find(|&&(m, t, _)| m == machine && t == shtype.0) {
+        Some(&(_, _, name)) => name.to_string(),
+        None => format!("{}", shtype),
+    }
+}
+
+/// `e_flags`, followed by what they mean for the machines readelf and
+/// this tool both decode
+pub fn header_flags(file: &elf::File) -> String {
+    let flags = file.ehdr.flags;
+    let mut s = format!("{:#x}", flags);
+    match file.ehdr.machine {
+        types::EM_ARM => {
+            let mut rest = flags & 0x00ffffff;
+            match flags & 0xff000000 {
+                0x04000000 => s.push_str(", Version4 EABI"),
+                0x05000000 => {
+                    s.push_str(", Version5 EABI");
+                    for &(bit, name) in [(0x200, ", soft-float ABI"),
+                                         (0x400, ", hard-float ABI")].iter() {
+                        if (rest & bit) != 0 {
+                            s.push_str(name);
+                            rest &= !bit;
+                        }
+                    }
+                }
+                _ => return s,
+            }
+            for &(bit, name) in [(0x800000, ", BE8"), (0x400000, ", LE8")].iter() {
+                if (rest & bit) != 0 {
+                    s.push_str(name);
+                    rest &= !bit;
+                }
+            }
+            if rest != 0 {
+                s.push_str(", <unknown>");
+            }
+        }
+        types::EM_RISCV => {
+            if (flags & 0x1) != 0 {
+                s.push_str(", RVC");
+            }
+            if (flags & 0x8) != 0 {
+                s.push_str(", RVE");
+            }
+            if (flags & 0x10) != 0 {
+                s.push_str(", TSO");
+            }
+            s.push_str(match flags & 0x6 {
+                0x0 => ", soft-float ABI",
+                0x2 => ", single-float ABI",
+                0x4 => ", double-float ABI",
+                _ => ", quad-float ABI",
+            });
+        }
+        _ => {}
+    }
+    s
+}
+
+/// Reads the `size`-byte word at `offset` in `data`, in the file's byte
+/// order.
+pub fn word(file: &elf::File, data: &[u8], offset: usize, size: usize) -> Option<u64> {
+    if offset + size > data.len() {
+        return None;
+    }
+    let bytes = &data[offset..offset + size];
+    let mut value = 0u64;
+    for i in 0..size {
+        let b = if file.ehdr.data == types::ELFDATA2MSB { bytes[i] } else { bytes[size - 1 - i] };
+        value = (value << 8) | b as u64;
+    }
+    Some(value)
+}
+
+/// Operating system named by a `NT_GNU_ABI_TAG` note
+pub fn abi_tag_os(os: u64) -> &'static str {
+    match os {
+        0 => "Linux",
+        1 => "Hurd",
+        2 => "Solaris",
+        3 => "FreeBSD",
+        4 => "NetBSD",
+        5 => "Syllable",
+        6 => "NaCl",
+        _ => "Unknown",
+    }
+}
+
+const GNU_PROPERTY_STACK_SIZE: u64 = 1;
+const GNU_PROPERTY_NO_COPY_ON_PROTECTED: u64 = 2;
+const GNU_PROPERTY_LOPROC: u64 = 0xc0000000;
+const GNU_PROPERTY_HIPROC: u64 = 0xdfffffff;
+const GNU_PROPERTY_LOUSER: u64 = 0xe0000000;
+const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u64 = 0xc0000000;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u64 = 0xc0000002;
+const GNU_PROPERTY_X86_FEATURE_2_NEEDED: u64 = 0xc0008001;
+const GNU_PROPERTY_X86_ISA_1_NEEDED: u64 = 0xc0008002;
+const GNU_PROPERTY_X86_FEATURE_2_USED: u64 = 0xc0010001;
+const GNU_PROPERTY_X86_ISA_1_USED: u64 = 0xc0010002;
+
+const X86_ISA_1: &'static [&'static str] = &[
+    "x86-64-baseline", "x86-64-v2", "x86-64-v3", "x86-64-v4",
+];
+const X86_FEATURE_1: &'static [&'static str] = &["IBT", "SHSTK", "LAM_U48", "LAM_U57"];
+const X86_FEATURE_2: &'static [&'static str] = &[
+    "x86", "x87", "MMX", "XMM", "YMM", "ZMM", "FXSR", "XSAVE", "XSAVEOPT",
+    "XSAVEC", "TMM", "MASK",
+];
+const AARCH64_FEATURE_1: &'static [&'static str] = &["BTI", "PAC"];
+
+/// Lists the bits set in `bitmask` by name, `names[n]` naming bit `n`.
+fn bit_names(bitmask: u64, names: &[&str]) -> String {
+    if bitmask == 0 {
+        return "<None>".to_string();
+    }
+    let mut bits = Vec::new();
+    for n in 0..64 {
+        let bit = 1u64 << n;
+        if (bitmask & bit) != 0 {
+            bits.push(match names.get(n) {
+                Some(name) => name.to_string(),
+                None => format!("<unknown: {:x}>", bit),
+            });
+        }
+    }
+    bits.join(", ")
+}
+
+/// Decodes the properties of a `NT_GNU_PROPERTY_TYPE_0` note, separated
+/// by `sep`.
+pub fn gnu_properties(file: &elf::File, desc: &[u8], sep: &str) -> String {
+    let size = if file.ehdr.class == types::ELFCLASS32 { 4 } else { 8 };
+    if desc.len() < 8 || desc.len() % size != 0 {
+        return format!("<corrupt GNU_PROPERTY_TYPE, size = {:#x}>", desc.len());
+    }
+    let x86 = match file.ehdr.machine {
+        types::EM_X86_64 | types::EM_386 | types::EM_IAMCU => true,
+        _ => false,
+    };
+
+    let mut s = String::new();
+    let mut offset = 0;
+    while offset + 8 <= desc.len() {
+        let ptype = word(file, desc, offset, 4).unwrap();
+        let datasz = word(file, desc, offset + 4, 4).unwrap() as usize;
+        offset += 8;
+        if datasz > desc.len() - offset {
+            s.push_str(&format!("<corrupt type ({:#x}) datasz: {:#x}>", ptype, datasz));
+            break;
+        }
+        let data = &desc[offset..offset + datasz];
+        let bitmask = if datasz == 4 { word(file, data, 0, 4).unwrap() } else { 0 };
+        let decoded = match ptype {
+            GNU_PROPERTY_X86_ISA_1_USED if x86 => Some(("x86 ISA used: ", X86_ISA_1)),
+            GNU_PROPERTY_X86_ISA_1_NEEDED if x86 => Some(("x86 ISA needed: ", X86_ISA_1)),
+            GNU_PROPERTY_X86_FEATURE_1_AND if x86 => Some(("x86 feature: ", X86_FEATURE_1)),
+            GNU_PROPERTY_X86_FEATURE_2_USED if x86 => Some(("x86 feature used: ", X86_FEATURE_2)),
+            GNU_PROPERTY_X86_FEATURE_2_NEEDED if x86 => Some(("x86 feature needed: ", X86_FEATURE_2)),
+            GNU_PROPERTY_AARCH64_FEATURE_1_AND if file.ehdr.machine == types::EM_AARCH64 => {
+                Some(("AArch64 feature: ", AARCH64_FEATURE_1))
+            }
+            _ => None,
+        };
+        match decoded {
+            Some((label, names)) => {
+                s.push_str(label);
+                if datasz == 4 {
+                    s.push_str(&bit_names(bitmask, names));
+                } else {
+                    s.push_str(&format!("<corrupt length: {:#x}> ", datasz));
+                }
+            }
+            None if ptype == GNU_PROPERTY_STACK_SIZE => {
+                s.push_str("stack size: ");
+                match word(file, data, 0, size) {
+                    Some(value) if datasz == size => s.push_str(&format!("{:#x}", value)),
+                    _ => s.push_str(&format!("<corrupt length: {:#x}> ", datasz)),
+                }
+            }
+            None if ptype == GNU_PROPERTY_NO_COPY_ON_PROTECTED => {
+                s.push_str("no copy on protected ");
+                if datasz != 0 {
+                    s.push_str(&format!("<corrupt length: {:#x}> ", datasz));
+                }
+            }
+            None => {
+                let kind = if ptype < GNU_PROPERTY_LOPROC {
+                    "unknown"
+                } else if ptype <= GNU_PROPERTY_HIPROC || ptype < GNU_PROPERTY_LOUSER {
+                    "processor-specific"
+                } else {
+                    "application-specific"
+                };
+                s.push_str(&format!("<{} type {:#x} data: ", kind, ptype));
+                for b in data.iter() {
+                    s.push_str(&format!("{:02x} ", b));
+                }
+                s.push('>');
+            }
+        }
+        offset += (datasz + size - 1) & !(size - 1);
+        if offset >= desc.len() {
+            break;
+        }
+        s.push_str(sep);
+    }
+    s
+}
@@ -1,34 +1,788 @@
 extern crate elf;
 
+mod readelf_names;
+
 use std::env;
-use std::path::PathBuf;
+use std::io;
+use std::io::Write;
+use std::process;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let paths: Vec<PathBuf> = if args.len() == 1 {
-        vec!(From::from("stress"))
+use elf::types;
+
+#[derive(Default)]
+struct Options {
+    file_header: bool,
+    section_headers: bool,
+    program_headers: bool,
+    relocs: bool,
+    symbols: bool,
+    notes: bool,
+    wide: bool,
+    hex_dumps: Vec<String>,
+    paths: Vec<String>,
+}
+
+const USAGE: &'static str = "\
+Usage: rust-readelf <option(s)> elf-file(s)
+ Display information about the contents of ELF format files
+ Options are:
+  -a --all               Equivalent to: -h -l -S -s -r -n
+  -h --file-header       Display the ELF file header
+  -l --program-headers   Display the program headers
+     --segments          An alias for --program-headers
+  -S --section-headers   Display the sections' header
+     --sections          An alias for --section-headers
+  -s --syms              Display the symbol table
+     --symbols           An alias for --syms
+  -n --notes             Display the core notes (if present)
+  -r --relocs            Display the relocations (if present)
+  -x --hex-dump=<name>   Dump the contents of section <name> as bytes
+  -W --wide              Allow output width to exceed 80 characters
+     --help              Display this information";
+
+fn parse_args<I: Iterator<Item=String>>(mut args: I) -> Result<Options, String> {
+    let mut opts = Options::default();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            match &arg[..] {
+                "--all" => {
+                    opts.file_header = true;
+                    opts.program_headers = true;
+                    opts.section_headers = true;
+                    opts.symbols = true;
+                    opts.relocs = true;
+                    opts.notes = true;
+                }
+                "--file-header" => opts.file_header = true,
+                "--program-headers" | "--segments" => opts.program_headers = true,
+                "--section-headers" | "--sections" => opts.section_headers = true,
+                "--syms" | "--symbols" => opts.symbols = true,
+                "--notes" => opts.notes = true,
+                "--relocs" => opts.relocs = true,
+                "--wide" => opts.wide = true,
+                "--help" => return Err(String::new()),
+                _ if arg.starts_with("--hex-dump=") => {
+                    opts.hex_dumps.push(arg["--hex-dump=".len()..].to_string());
+                }
+                _ => return Err(format!("unrecognized option '{}'", arg)),
+            }
+        } else if arg.starts_with("-") && arg.len() > 1 {
+            let flags = &arg[1..];
+            for (i, c) in flags.char_indices() {
+                match c {
+                    'a' => {
+                        opts.file_header = true;
+                        opts.program_headers = true;
+                        opts.section_headers = true;
+                        opts.symbols = true;
+                        opts.relocs = true;
+                        opts.notes = true;
+                    }
+                    'h' => opts.file_header = true,
+                    'l' => opts.program_headers = true,
+                    'S' => opts.section_headers = true,
+                    's' => opts.symbols = true,
+                    'n' => opts.notes = true,
+                    'r' => opts.relocs = true,
+                    'W' => opts.wide = true,
+                    'x' => {
+                        // The section name is either the rest of this
+                        // argument or the next one.
+                        let rest = &flags[i + 1..];
+                        let name = if rest.is_empty() {
+                            match args.next() {
+                                Some(name) => name,
+                                None => return Err("option requires an argument -- 'x'".to_string()),
+                            }
+                        } else {
+                            rest.to_string()
+                        };
+                        opts.hex_dumps.push(name);
+                        break;
+                    }
+                    _ => return Err(format!("invalid option -- '{}'", c)),
+                }
+            }
+        } else {
+            opts.paths.push(arg);
+        }
+    }
+    let any = opts.file_header || opts.section_headers || opts.program_headers
+        || opts.relocs || opts.symbols || opts.notes || !opts.hex_dumps.is_empty();
+    if !any || opts.paths.is_empty() {
+        return Err(String::new());
+    }
+    Ok(opts)
+}
+
+fn parse_error(e: elf::ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))
+}
+
+fn is_elf32(file: &elf::File) -> bool {
+    file.ehdr.class == types::ELFCLASS32
+}
+
+fn print_file_header(w: &mut Write, file: &elf::File) -> io::Result<()> {
+    let ehdr = &file.ehdr;
+    try!(writeln!(w, "ELF Header:"));
+    try!(write!(w, "  Magic:  "));
+    let ident = [types::ELFMAG0, types::ELFMAG1, types::ELFMAG2, types::ELFMAG3,
+                 ehdr.class.0, ehdr.data.0, ehdr.version.0 as u8, ehdr.osabi.0,
+                 ehdr.abiversion, 0, 0, 0, 0, 0, 0, 0];
+    for b in ident.iter() {
+        try!(write!(w, " {:02x}", b));
+    }
+    try!(writeln!(w, " "));
+    try!(writeln!(w, "  Class:                             {}", readelf_names::class(ehdr.class)));
+    try!(writeln!(w, "  Data:                              {}", readelf_names::data(ehdr.data)));
+    try!(writeln!(w, "  Version:                           {}",
+                  readelf_names::ident_version(ehdr.version.0 as u8)));
+    try!(writeln!(w, "  OS/ABI:                            {}", readelf_names::osabi(ehdr.osabi)));
+    try!(writeln!(w, "  ABI Version:                       {}", ehdr.abiversion));
+    try!(writeln!(w, "  Type:                              {}", readelf_names::file_type(file)));
+    try!(writeln!(w, "  Machine:                           {}", readelf_names::machine(ehdr.machine)));
+    try!(writeln!(w, "  Version:                           {:#x}", ehdr.version.0));
+    try!(writeln!(w, "  Entry point address:               {:#x}", ehdr.entry));
+    try!(writeln!(w, "  Start of program headers:          {} (bytes into file)",
+                  if file.phdrs.is_empty() { 0 } else { ehdr.phoff }));
+    try!(writeln!(w, "  Start of section headers:          {} (bytes into file)",
+                  if file.sections.is_empty() { 0 } else { ehdr.shoff }));
+    try!(writeln!(w, "  Flags:                             {}", readelf_names::header_flags(file)));
+    try!(writeln!(w, "  Size of this header:               {} (bytes)", file.ehsize()));
+    try!(writeln!(w, "  Size of program headers:           {} (bytes)", file.phentsize()));
+    try!(writeln!(w, "  Number of program headers:         {}", file.phdrs.len()));
+    try!(writeln!(w, "  Size of section headers:           {} (bytes)", file.shentsize()));
+    try!(writeln!(w, "  Number of section headers:         {}", file.sections.len()));
+    writeln!(w, "  Section header string table index: {}", ehdr.shstrndx)
+}
+
+/// Whether `section` lies within the memory image of `phdr`
+fn section_in_segment(section: &elf::Section, phdr: &types::ProgramHeader) -> bool {
+    let shdr = &section.shdr;
+    if (shdr.flags.0 & types::SHF_ALLOC.0) == 0 {
+        return false;
+    }
+    // .tbss only exists in the TLS template, not in other segments
+    if (shdr.flags.0 & types::SHF_TLS.0) != 0 && shdr.shtype == types::SHT_NOBITS
+        && phdr.progtype != types::PT_TLS {
+        return false;
+    }
+    let end = phdr.vaddr + phdr.memsz;
+    shdr.addr >= phdr.vaddr && shdr.addr + shdr.size <= end
+        && (shdr.size != 0 || shdr.addr < end)
+}
+
+/// The path of the program interpreter a `PT_INTERP` segment names
+fn interpreter(file: &elf::File, phdr: &types::ProgramHeader) -> Option<String> {
+    file.segment_data(phdr).map(|data| {
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        String::from_utf8_lossy(&data[..end]).into_owned()
+    })
+}
+
+fn print_program_headers(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    if file.phdrs.is_empty() {
+        return writeln!(w, "\nThere are no program headers in this file.");
+    }
+    if !opts.file_header {
+        try!(writeln!(w, "\nElf file type is {}", readelf_names::file_type(file)));
+        try!(writeln!(w, "Entry point {:#x}", file.ehdr.entry));
+        try!(writeln!(w, "There are {} program headers, starting at offset {}",
+                      file.phdrs.len(), file.ehdr.phoff));
+    }
+    try!(writeln!(w, "\nProgram Headers:"));
+    if is_elf32(file) {
+        try!(writeln!(w, "  Type           Offset   VirtAddr   PhysAddr   FileSiz MemSiz  Flg Align"));
+    } else if opts.wide {
+        try!(writeln!(w, "  Type           Offset   VirtAddr           PhysAddr           FileSiz  MemSiz   Flg Align"));
+    } else {
+        try!(writeln!(w, "  Type           Offset             VirtAddr           PhysAddr"));
+        try!(writeln!(w, "                 FileSiz            MemSiz              Flags  Align"));
+    }
+    for phdr in file.phdrs.iter() {
+        let progtype = format!("{}", phdr.progtype);
+        if is_elf32(file) {
+            try!(writeln!(w, "  {:<14} 0x{:06x} 0x{:08x} 0x{:08x} 0x{:05x} 0x{:05x} {} {:#x}",
+                          progtype, phdr.offset, phdr.vaddr, phdr.paddr,
+                          phdr.filesz, phdr.memsz, phdr.flags, phdr.align));
+        } else if opts.wide {
+            try!(writeln!(w, "  {:<14} 0x{:06x} 0x{:016x} 0x{:016x} 0x{:06x} 0x{:06x} {} {:#x}",
+                          progtype, phdr.offset, phdr.vaddr, phdr.paddr,
+                          phdr.filesz, phdr.memsz, phdr.flags, phdr.align));
+        } else {
+            try!(writeln!(w, "  {:<14} 0x{:016x} 0x{:016x} 0x{:016x}",
+                          progtype, phdr.offset, phdr.vaddr, phdr.paddr));
+            try!(writeln!(w, "                 0x{:016x} 0x{:016x}  {}    {:#x}",
+                          phdr.filesz, phdr.memsz, phdr.flags, phdr.align));
+        }
+        if phdr.progtype == types::PT_INTERP {
+            if let Some(path) = interpreter(file, phdr) {
+                try!(writeln!(w, "      [Requesting program interpreter: {}]", path));
+            }
+        }
+    }
+
+    if file.sections.is_empty() {
+        return Ok(());
+    }
+    try!(writeln!(w, "\n Section to Segment mapping:"));
+    try!(writeln!(w, "  Segment Sections..."));
+    for (i, phdr) in file.phdrs.iter().enumerate() {
+        try!(write!(w, "   {:02}     ", i));
+        for section in file.sections.iter() {
+            if section_in_segment(section, phdr) {
+                try!(write!(w, "{} ", section.shdr.name));
+            }
+        }
+        try!(writeln!(w, ""));
+    }
+    Ok(())
+}
+
+/// `name` cut to `width` characters the way readelf does outside wide
+/// mode, ending in "[...]" if it was shortened
+fn truncated(name: &str, width: usize, wide: bool) -> String {
+    if wide || name.chars().count() <= width {
+        return name.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut short: String = name.chars().take(width.saturating_sub(5)).collect();
+    short.push_str("[...]");
+    short
+}
+
+fn print_section_headers(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    if file.sections.is_empty() {
+        return writeln!(w, "\nThere are no sections in this file.");
+    }
+    if !opts.file_header {
+        try!(writeln!(w, "There are {} section headers, starting at offset {:#x}:",
+                      file.sections.len(), file.ehdr.shoff));
+    }
+    try!(writeln!(w, "\nSection Headers:"));
+    if is_elf32(file) {
+        try!(writeln!(w, "  [Nr] Name              Type            Addr     Off    Size   ES Flg Lk Inf Al"));
+    } else if opts.wide {
+        try!(writeln!(w, "  [Nr] Name              Type            Address          Off    Size   ES Flg Lk Inf Al"));
     } else {
-        let mut i = args.into_iter();
-        i.next();
-        i.map(|arg| From::from(arg) )
-            .collect()
+        try!(writeln!(w, "  [Nr] Name              Type             Address           Offset"));
+        try!(writeln!(w, "       Size              EntSize          Flags  Link  Info  Align"));
+    }
+    for (i, section) in file.sections.iter().enumerate() {
+        let shdr = &section.shdr;
+        let name = truncated(&shdr.name, 17, opts.wide);
+        let mut shtype = readelf_names::section_type(file, shdr.shtype);
+        if !opts.wide {
+            shtype = shtype.chars().take(15).collect();
+        }
+        if is_elf32(file) || opts.wide {
+            let addr = if is_elf32(file) {
+                format!("{:08x}", shdr.addr)
+            } else {
+                format!("{:016x}", shdr.addr)
+            };
+            try!(writeln!(w, "  [{:2}] {:<17} {:<15} {} {:06x} {:06x} {:02x} {:>3} {:2} {:3} {:2}",
+                          i, name, shtype, addr,
+                          shdr.offset, shdr.size, shdr.entsize,
+                          shdr.flags, shdr.link, shdr.info,
+                          shdr.addralign));
+        } else {
+            try!(writeln!(w, "  [{:2}] {:<17} {:<15}  {:016x}  {:08x}",
+                          i, name, shtype, shdr.addr, shdr.offset));
+            try!(writeln!(w, "       {:016x}  {:016x} {:>3}      {:2}   {:3}     {}",
+                          shdr.size, shdr.entsize, shdr.flags,
+                          shdr.link, shdr.info, shdr.addralign));
+        }
+    }
+    try!(writeln!(w, "Key to Flags:"));
+    try!(writeln!(w, "  W (write), A (alloc), X (execute), M (merge), S (strings), I (info),"));
+    try!(writeln!(w, "  L (link order), O (extra OS processing required), G (group), T (TLS),"));
+    try!(writeln!(w, "  C (compressed), x (unknown), o (OS specific), E (exclude),"));
+    try!(write!(w, "  "));
+    let osabi = file.ehdr.osabi;
+    if osabi == types::ELFOSABI_LINUX || osabi == types::ELFOSABI_FREEBSD {
+        try!(write!(w, "R (retain), "));
+    }
+    try!(write!(w, "D (mbind), "));
+    match file.ehdr.machine {
+        types::EM_X86_64 | types::EM_L10M | types::EM_K10M => try!(write!(w, "l (large), ")),
+        types::EM_ARM => try!(write!(w, "y (purecode), ")),
+        types::EM_PPC => try!(write!(w, "v (VLE), ")),
+        _ => {}
+    }
+    writeln!(w, "p (processor specific)")
+}
+
+fn symbol_type_name(t: types::SymbolType) -> String {
+    t.name().map(|n| n.to_string()).unwrap_or_else(|| format!("<{}>", t.0))
+}
+
+fn symbol_bind_name(b: types::SymbolBind) -> String {
+    b.name().map(|n| n.to_string()).unwrap_or_else(|| format!("<{}>", b.0))
+}
+
+fn symbol_shndx_name(shndx: u16) -> String {
+    match shndx {
+        types::SHN_UNDEF => "UND".to_string(),
+        types::SHN_ABS => "ABS".to_string(),
+        types::SHN_COMMON => "COM".to_string(),
+        n => format!("{}", n),
+    }
+}
+
+/// Name to show for a symbol; section symbols are named after their section
+fn symbol_name(file: &elf::File, sym: &types::Symbol) -> String {
+    if sym.sym_type() == types::STT_SECTION && sym.name.is_empty() {
+        if let Some(section) = file.sections.get(sym.shndx as usize) {
+            return section.shdr.name.clone();
+        }
+    }
+    sym.name.clone()
+}
+
+/// The `@VER (n)`, `@@VER` or `@VER` suffix readelf appends to versioned
+/// dynamic symbols
+fn version_suffix(sym: &types::Symbol, needs: &[elf::version::VersionNeed]) -> Option<String> {
+    let version = match sym.version {
+        Some(ref v) => v,
+        None => return None,
     };
-    for path in paths.into_iter() {
-        let file = match elf::File::open_path(&path) {
+    match version.file {
+        Some(ref file) => {
+            let index = needs.iter()
+                .filter(|need| need.file == *file)
+                .flat_map(|need| need.versions.iter())
+                .find(|v| v.name == version.name)
+                .map_or(0, |v| v.index);
+            Some(format!("@{} ({})", version.name, index))
+        }
+        // The symbol naming a version definition is shown without a suffix
+        None if sym.name == version.name => None,
+        None if version.hidden => Some(format!("@{}", version.name)),
+        None => Some(format!("@@{}", version.name)),
+    }
+}
+
+fn print_symbols(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    if file.sections.is_empty() {
+        return writeln!(w, "\nDynamic symbol information is not available for displaying symbols.");
+    }
+    let needs = try!(file.version_needs().map_err(parse_error));
+    for section in file.sections.iter() {
+        let shtype = section.shdr.shtype;
+        if shtype != types::SHT_SYMTAB && shtype != types::SHT_DYNSYM {
+            continue;
+        }
+        let symbols = try!(file.get_symbols(section).map_err(parse_error));
+        try!(writeln!(w, "\nSymbol table '{}' contains {} {}:", section.shdr.name,
+                      symbols.len(), if symbols.len() == 1 { "entry" } else { "entries" }));
+        if is_elf32(file) {
+            try!(writeln!(w, "   Num:    Value  Size Type    Bind   Vis      Ndx Name"));
+        } else {
+            try!(writeln!(w, "   Num:    Value          Size Type    Bind   Vis      Ndx Name"));
+        }
+        for (i, sym) in symbols.iter().enumerate() {
+            let value = if is_elf32(file) {
+                format!("{:08x}", sym.value)
+            } else {
+                format!("{:016x}", sym.value)
+            };
+            // Narrow output fits the name and its version suffix in 21 columns
+            let suffix = version_suffix(sym, &needs).unwrap_or(String::new());
+            let width = 21usize.saturating_sub(suffix.len());
+            try!(writeln!(w, "{:6}: {} {:5} {:<7} {:<6} {:<8} {:>3} {}{}",
+                          i, value, sym.size,
+                          symbol_type_name(sym.sym_type()),
+                          symbol_bind_name(sym.sym_bind()),
+                          sym.sym_vis().name().unwrap_or("PROTECTED"),
+                          symbol_shndx_name(sym.shndx),
+                          truncated(&symbol_name(file, sym), width, opts.wide),
+                          suffix));
+        }
+    }
+    Ok(())
+}
+
+fn print_relocs(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    let mut any = false;
+    for section in file.sections.iter() {
+        let shtype = section.shdr.shtype;
+        if shtype != types::SHT_REL && shtype != types::SHT_RELA {
+            continue;
+        }
+        any = true;
+        let relocs = try!(file.get_relocations(section).map_err(parse_error));
+        let symbols = match file.sections.get(section.shdr.link as usize) {
+            Some(symtab) if symtab.shdr.shtype != types::SHT_NULL => {
+                try!(file.get_symbols(symtab).map_err(parse_error))
+            }
+            _ => Vec::new(),
+        };
+
+        try!(writeln!(w, "\nRelocation section '{}' at offset {:#x} contains {} {}:",
+                      section.shdr.name, section.shdr.offset, relocs.len(),
+                      if relocs.len() == 1 { "entry" } else { "entries" }));
+        let rela = shtype == types::SHT_RELA;
+        let addend = if rela { " + Addend" } else { "" };
+        match (is_elf32(file), opts.wide) {
+            (true, true) => try!(writeln!(w, " Offset     Info    Type                Sym. Value  Symbol's Name{}", addend)),
+            (true, false) => try!(writeln!(w, " Offset     Info    Type            Sym.Value  Sym. Name{}", addend)),
+            (false, true) => try!(writeln!(w, "    Offset             Info             Type               Symbol's Value  Symbol's Name{}", addend)),
+            (false, false) => try!(writeln!(w, "  Offset          Info           Type           Sym. Value    Sym. Name{}", addend)),
+        }
+        for reloc in relocs.iter() {
+            if is_elf32(file) {
+                let info = (reloc.sym << 8) | (reloc.rtype & 0xff);
+                try!(write!(w, "{:08x}  {:08x} ", reloc.offset, info));
+            } else {
+                let info = ((reloc.sym as u64) << 32) | reloc.rtype as u64;
+                if opts.wide {
+                    try!(write!(w, "{:016x}  {:016x} ", reloc.offset, info));
+                } else {
+                    try!(write!(w, "{:012x}  {:012x} ", reloc.offset, info));
+                }
+            }
+            match types::reloc_type_name(file.ehdr.machine, reloc.rtype) {
+                Some(name) if opts.wide => try!(write!(w, "{:<22}", name)),
+                Some(name) => try!(write!(w, "{:<17}", name.chars().take(17).collect::<String>())),
+                None => try!(write!(w, "unrecognized: {:<7x}", reloc.rtype)),
+            }
+            match symbols.get(reloc.sym as usize) {
+                Some(sym) if reloc.sym != 0 => {
+                    if is_elf32(file) {
+                        try!(write!(w, " {:08x}   ", sym.value));
+                    } else {
+                        try!(write!(w, " {:016x} ", sym.value));
+                    }
+                    try!(write!(w, "{}", truncated(&symbol_name(file, sym), 22, opts.wide)));
+                    if let Some(ref version) = sym.version {
+                        if version.file.is_some() || version.hidden {
+                            try!(write!(w, "@{}", version.name));
+                        } else if sym.name != version.name {
+                            try!(write!(w, "@@{}", version.name));
+                        }
+                    }
+                    if let Some(addend) = reloc.addend {
+                        if addend < 0 {
+                            try!(write!(w, " - {:x}", -addend));
+                        } else {
+                            try!(write!(w, " + {:x}", addend));
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(addend) = reloc.addend {
+                        let pad = if is_elf32(file) { 12 } else { 20 };
+                        if addend < 0 {
+                            try!(write!(w, "{:1$}-{2:x}", "", pad, -addend));
+                        } else {
+                            try!(write!(w, "{:1$}{2:x}", "", pad, addend));
+                        }
+                    }
+                }
+            }
+            try!(writeln!(w, ""));
+        }
+    }
+    if !any {
+        try!(writeln!(w, "\nThere are no relocations in this file."));
+    }
+    Ok(())
+}
+
+fn print_mapped_files(w: &mut Write, file: &elf::File, desc: &[u8]) -> io::Result<()> {
+    let size = if is_elf32(file) { 4 } else { 8 };
+    let (count, page_size) = match (readelf_names::word(file, desc, 0, size),
+                                    readelf_names::word(file, desc, size, size)) {
+        (Some(count), Some(page_size)) => (count as usize, page_size),
+        _ => return writeln!(w, "    Malformed note - too short for header"),
+    };
+    let names_start = match count.checked_mul(3 * size) {
+        Some(len) if 2 * size + len < desc.len() => 2 * size + len,
+        _ => return writeln!(w, "    Malformed note - filenames end too early"),
+    };
+    let mut names = desc[names_start..].split(|&b| b == 0);
+    try!(writeln!(w, "    Page size: {}", page_size));
+    try!(writeln!(w, "    {1:>0$}{2:>0$}{3:>0$}", 2 * size + 2, "Start", "End", "Page Offset"));
+    for i in 0..count {
+        let entry = 2 * size + 3 * size * i;
+        let name = match names.next() {
+            Some(name) if entry < names_start => String::from_utf8_lossy(name).into_owned(),
+            _ => return writeln!(w, "    Malformed note - filenames end too early"),
+        };
+        let value = |j: usize| readelf_names::word(file, desc, entry + j * size, size).unwrap();
+        try!(writeln!(w, "    0x{1:00$x}  0x{2:00$x}  0x{3:00$x}\n        {4}",
+                      2 * size, value(0), value(1), value(2), name));
+    }
+    Ok(())
+}
+
+fn print_note_list(w: &mut Write, file: &elf::File, notes: &[elf::Note],
+                   opts: &Options) -> io::Result<()> {
+    try!(writeln!(w, "  Owner                Data size \tDescription"));
+    for note in notes.iter() {
+        let description = note.description().unwrap_or_else(
+            || format!("Unknown note type: ({:#010x})", note.ntype.0));
+        // Wide output continues the line with the decoded contents
+        try!(write!(w, "  {:<20} {:#010x}\t{}{}", note.name, note.desc.len(), description,
+                    if opts.wide { "\t" } else { "\n" }));
+        match (&note.name[..], note.ntype) {
+            ("GNU", types::NT_GNU_BUILD_ID) => {
+                try!(write!(w, "    Build ID: "));
+                for b in note.desc.iter() {
+                    try!(write!(w, "{:02x}", b));
+                }
+                try!(writeln!(w, ""));
+            }
+            ("GNU", types::NT_GNU_ABI_TAG) => {
+                let word = |i: usize| readelf_names::word(file, &note.desc, 4 * i, 4).unwrap_or(0);
+                if note.desc.len() < 16 {
+                    try!(writeln!(w, "    <corrupt GNU_ABI_TAG>"));
+                } else {
+                    try!(writeln!(w, "    OS: {}, ABI: {}.{}.{}",
+                                  readelf_names::abi_tag_os(word(0)), word(1), word(2), word(3)));
+                }
+            }
+            ("GNU", types::NT_GNU_GOLD_VERSION) => {
+                let version = note.desc.split(|&b| b == 0).next().unwrap_or(&[]);
+                try!(writeln!(w, "    Version: {}", String::from_utf8_lossy(version)));
+            }
+            ("GNU", types::NT_GNU_PROPERTY_TYPE_0) => {
+                let sep = if opts.wide { ", " } else { "\n\t" };
+                try!(writeln!(w, "      Properties: {}",
+                              readelf_names::gnu_properties(file, &note.desc, sep)));
+            }
+            ("GNU", _) => {
+                try!(write!(w, "    Description data: "));
+                for b in note.desc.iter() {
+                    try!(write!(w, "{:02x} ", b));
+                }
+                try!(writeln!(w, ""));
+            }
+            ("CORE", types::NT_FILE) => try!(print_mapped_files(w, file, &note.desc)),
+            // readelf decodes no other core notes
+            ("CORE", _) if opts.wide => try!(writeln!(w, "")),
+            ("CORE", _) => {}
+            _ if !note.desc.is_empty() => {
+                try!(write!(w, "   description data: "));
+                for b in note.desc.iter() {
+                    try!(write!(w, "{:02x} ", b));
+                }
+                try!(writeln!(w, ""));
+            }
+            _ if opts.wide => try!(writeln!(w, "")),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn print_notes(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    let mut any = false;
+    for section in file.sections.iter().filter(|s| s.shdr.shtype == types::SHT_NOTE) {
+        any = true;
+        let notes = try!(file.parse_notes(&section.data, section.shdr.addralign)
+                             .map_err(parse_error));
+        try!(writeln!(w, "\nDisplaying notes found in: {}", section.shdr.name));
+        try!(print_note_list(w, file, &notes, opts));
+    }
+    if !any {
+        for phdr in file.phdrs.iter().filter(|p| p.progtype == types::PT_NOTE) {
+            if let Some(data) = file.segment_data(phdr) {
+                let notes = try!(file.parse_notes(data, phdr.align).map_err(parse_error));
+                try!(writeln!(w, "\nDisplaying notes found at file offset {:#010x} with length {:#010x}:",
+                              phdr.offset, phdr.filesz));
+                try!(print_note_list(w, file, &notes, opts));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_hex_dump(w: &mut Write, file: &elf::File, index: usize) -> io::Result<()> {
+    let section = &file.sections[index];
+    if section.data.is_empty() || section.shdr.shtype == types::SHT_NOBITS {
+        return writeln!(w, "Section '{}' has no data to dump.", section.shdr.name);
+    }
+    try!(writeln!(w, "\nHex dump of section '{}':", section.shdr.name));
+    let relocated = file.sections.iter().any(|s| {
+        (s.shdr.shtype == types::SHT_REL || s.shdr.shtype == types::SHT_RELA)
+            && s.shdr.info as usize == index && s.shdr.size != 0
+    });
+    if relocated {
+        try!(writeln!(w, " NOTE: This section has relocations against it, but these have NOT been applied to this dump."));
+    }
+    for (i, line) in section.data.chunks(16).enumerate() {
+        try!(write!(w, "  0x{:08x} ", section.shdr.addr + 16 * i as u64));
+        for j in 0..16 {
+            if j < line.len() {
+                try!(write!(w, "{:02x}", line[j]));
+            } else {
+                try!(write!(w, "  "));
+            }
+            if j % 4 == 3 {
+                try!(write!(w, " "));
+            }
+        }
+        for &b in line.iter() {
+            let c = if b >= 0x20 && b < 0x7f { b as char } else { '.' };
+            try!(write!(w, "{}", c));
+        }
+        try!(writeln!(w, ""));
+    }
+    writeln!(w, "")
+}
+
+fn dump(w: &mut Write, file: &elf::File, opts: &Options) -> io::Result<()> {
+    if opts.file_header {
+        try!(print_file_header(w, file));
+    }
+    if opts.section_headers {
+        try!(print_section_headers(w, file, opts));
+    }
+    if opts.program_headers {
+        try!(print_program_headers(w, file, opts));
+    }
+    if opts.relocs {
+        try!(print_relocs(w, file, opts));
+    }
+    if opts.symbols {
+        try!(print_symbols(w, file, opts));
+    }
+    // Sections are dumped in file order, whichever order they were asked for in
+    let requested = |index: usize, name: &str| {
+        opts.hex_dumps.iter().any(|r| *r == name || r.parse() == Ok(index))
+    };
+    for (i, section) in file.sections.iter().enumerate() {
+        if requested(i, &section.shdr.name) {
+            try!(print_hex_dump(w, file, i));
+        }
+    }
+    for request in opts.hex_dumps.iter().rev() {
+        let found = file.sections.iter().enumerate()
+            .any(|(i, s)| *request == s.shdr.name || request.parse() == Ok(i));
+        if found {
+            continue;
+        }
+        if request.parse::<usize>().is_ok() {
+            let _ = writeln!(io::stderr(),
+                             "rust-readelf: Warning: Section {} was not dumped because it does not exist!",
+                             request);
+        } else {
+            let _ = writeln!(io::stderr(),
+                             "rust-readelf: Warning: Section '{}' was not dumped because it does not exist",
+                             request);
+        }
+    }
+    if opts.notes {
+        try!(print_notes(w, file, opts));
+    }
+    Ok(())
+}
+
+fn main() {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            if !msg.is_empty() {
+                let _ = writeln!(io::stderr(), "rust-readelf: {}", msg);
+            }
+            let _ = writeln!(io::stderr(), "{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut status = 0;
+    for path in opts.paths.iter() {
+        let file = match elf::File::open_path(path) {
             Ok(f) => f,
-            Err(e) => panic!("Error: {:?}", e),
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "rust-readelf: Error: {}: {:?}", path, e);
+                status = 1;
+                continue;
+            }
         };
-        println!("Debug-print ELF file:");
-        println!("{:?}", file);
-        println!("");
-        println!("Pretty-print ELF file:");
-        println!("{}", file);
-
-        println!("Getting the .text section");
-        let text = file.get_section(".text");
-        match text {
-            Some(s) => println!("shdr: {}", s),
-            None => println!("Failed to look up .text section!"),
+        if opts.paths.len() > 1 {
+            let _ = writeln!(out, "\nFile: {}", path);
         }
+        if let Err(e) = dump(&mut out, &file, &opts) {
+            let _ = writeln!(io::stderr(), "rust-readelf: Error: {}: {}", path, e);
+            status = 1;
+        }
+    }
+    process::exit(status);
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{dump, parse_args, Options};
+    use elf;
+
+    fn golden(fixture: &[u8], args: &str, expected: &str) {
+        let file = elf::File::open_stream(&mut io::Cursor::new(fixture)).unwrap();
+        let mut args: Vec<String> = args.split(' ').map(|s| s.to_string()).collect();
+        args.push("fixture".to_string());
+        let opts: Options = parse_args(args.into_iter()).ok().unwrap();
+        let mut out = Vec::new();
+        dump(&mut out, &file, &opts).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        if out != expected {
+            panic!("output of `rust-readelf {}` differs from golden file:\n{}", out, expected);
+        }
+    }
+
+    #[test]
+    fn file_header() {
+        golden(include_bytes!("../test/dyn.so"), "-h",
+               include_str!("../test/readelf/dyn.so-h.txt"));
+    }
+
+    #[test]
+    fn program_headers() {
+        golden(include_bytes!("../test/dyn.so"), "-l",
+               include_str!("../test/readelf/dyn.so-l.txt"));
+    }
+
+    #[test]
+    fn section_headers() {
+        golden(include_bytes!("../test/dyn.so"), "-S",
+               include_str!("../test/readelf/dyn.so-S.txt"));
+        golden(include_bytes!("../../unravel/src/test/x86.so"), "-S",
+               include_str!("../test/readelf/x86.so-S.txt"));
+    }
+
+    #[test]
+    fn symbols() {
+        golden(include_bytes!("../test/rel.o"), "-s",
+               include_str!("../test/readelf/rel.o-s.txt"));
+        golden(include_bytes!("../test/ver.so"), "-s",
+               include_str!("../test/readelf/ver.so-s.txt"));
+    }
+
+    #[test]
+    fn notes() {
+        golden(include_bytes!("../test/dyn.so"), "-n",
+               include_str!("../test/readelf/dyn.so-n.txt"));
+    }
+
+    #[test]
+    fn relocs() {
+        golden(include_bytes!("../test/rel.o"), "-r",
+               include_str!("../test/readelf/rel.o-r.txt"));
+        golden(include_bytes!("../test/ver.so"), "-W -r",
+               include_str!("../test/readelf/ver.so-Wr.txt"));
+    }
+
+    #[test]
+    fn hex_dump() {
+        golden(include_bytes!("../test/rel.o"), "-x .data --hex-dump=.text",
+               include_str!("../test/readelf/rel.o-x.txt"));
     }
 }
@@ -0,0 +1,150 @@
+//! Decompression of section contents.
+//!
+//! Two formats are supported: `SHF_COMPRESSED` sections, whose data starts
+//! with an `Elf32_Chdr`/`Elf64_Chdr` header naming the algorithm, and the
+//! legacy GNU `.zdebug_*` sections, whose data starts with "ZLIB" and the
+//! uncompressed size as a big-endian 64-bit integer.
+//!
+//! zlib is always linked. zstd is only linked when built with
+//! `--cfg elf_zstd`; without it, zstd-compressed sections are reported as
+//! `ParseError::NotImplemented`.
+
+use std::borrow::Cow;
+use std::io;
+use std::os::raw::{c_int, c_ulong};
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use super::{File, ParseError, Section};
+use super::types;
+
+const Z_OK: c_int = 0;
+
+/// Largest ratio of uncompressed to compressed size deflate can achieve
+const MAX_ZLIB_RATIO: u64 = 1032;
+/// Largest ratio zstd can achieve: a 3-byte block header and a single byte
+/// repeated over a 128 KiB block
+#[cfg(elf_zstd)]
+const MAX_ZSTD_RATIO: u64 = 32768;
+
+#[link(name="z")]
+extern "C" {
+    fn uncompress(dest: *mut u8, dest_len: *mut c_ulong, source: *const u8, source_len: c_ulong) -> c_int;
+}
+
+#[cfg(elf_zstd)]
+#[link(name="zstd")]
+extern "C" {
+    fn ZSTD_decompress(dst: *mut u8, dst_capacity: usize, src: *const u8, src_size: usize) -> usize;
+    fn ZSTD_isError(code: usize) -> ::std::os::raw::c_uint;
+}
+
+/// Checks the uncompressed `size` a header claims against what `data` could
+/// possibly expand to, before allocating for it.
+fn check_size(data: &[u8], size: u64, max_ratio: u64) -> Result<(), ParseError> {
+    match (data.len() as u64).checked_mul(max_ratio) {
+        Some(max) if size > max => Err(ParseError::InvalidFormat(None)),
+        _ => Ok(()),
+    }
+}
+
+fn inflate(data: &[u8], size: u64) -> Result<Vec<u8>, ParseError> {
+    try!(check_size(data, size, MAX_ZLIB_RATIO));
+    let mut out = vec![0u8; size as usize];
+    let mut out_len = size as c_ulong;
+    let ret = unsafe {
+        uncompress(out.as_mut_ptr(), &mut out_len, data.as_ptr(), data.len() as c_ulong)
+    };
+    if ret != Z_OK || out_len as u64 != size {
+        return Err(ParseError::InvalidFormat(None));
+    }
+    Ok(out)
+}
+
+#[cfg(elf_zstd)]
+fn unzstd(data: &[u8], size: u64) -> Result<Vec<u8>, ParseError> {
+    try!(check_size(data, size, MAX_ZSTD_RATIO));
+    let mut out = vec![0u8; size as usize];
+    let ret = unsafe {
+        ZSTD_decompress(out.as_mut_ptr(), out.len(), data.as_ptr(), data.len())
+    };
+    if unsafe { ZSTD_isError(ret) } != 0 || ret as u64 != size {
+        return Err(ParseError::InvalidFormat(None));
+    }
+    Ok(out)
+}
+
+#[cfg(not(elf_zstd))]
+fn unzstd(_data: &[u8], _size: u64) -> Result<Vec<u8>, ParseError> {
+    Err(ParseError::NotImplemented)
+}
+
+/// Decoded compression header of an `SHF_COMPRESSED` section
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CompressionHeader {
+    /// Compression algorithm
+    pub ctype: types::CompressionType,
+    /// Size of the uncompressed data
+    pub size: u64,
+    /// Alignment of the uncompressed data
+    pub addralign: u64,
+}
+
+impl Section {
+    /// Whether this is a legacy GNU `.zdebug_*` section holding compressed
+    /// data.
+    ///
+    /// Tools only compress such sections when that makes them
+    /// smaller, so the contents must be checked as well as the name.
+    pub fn is_zdebug(&self) -> bool {
+        self.shdr.name.starts_with(".zdebug") && self.data.starts_with(b"ZLIB")
+            && self.data.len() >= 12
+    }
+}
+
+impl File {
+    /// Decodes the compression header of an `SHF_COMPRESSED` section.
+    ///
+    /// Returns `None` for sections without the flag.
+    pub fn compression_header(&self, section: &Section) -> Result<Option<CompressionHeader>, ParseError> {
+        if (section.shdr.flags.0 & types::SHF_COMPRESSED.0) == 0 {
+            return Ok(None);
+        }
+        let mut rdr = io::Cursor::new(&section.data[..]);
+        let ctype = types::CompressionType(try!(read_u32!(self, rdr)));
+        let size: u64;
+        let addralign: u64;
+        if self.ehdr.class == types::ELFCLASS32 {
+            size = try!(read_u32!(self, rdr)) as u64;
+            addralign = try!(read_u32!(self, rdr)) as u64;
+        } else {
+            let _reserved = try!(read_u32!(self, rdr));
+            size = try!(read_u64!(self, rdr));
+            addralign = try!(read_u64!(self, rdr));
+        }
+        Ok(Some(CompressionHeader { ctype: ctype, size: size, addralign: addralign }))
+    }
+
+    /// Returns the contents of `section`, decompressing them if the section
+    /// is compressed. Uncompressed sections are returned without copying.
+    pub fn decompressed_data<'a>(&self, section: &'a Section) -> Result<Cow<'a, [u8]>, ParseError> {
+        if let Some(chdr) = try!(self.compression_header(section)) {
+            let start = if self.ehdr.class == types::ELFCLASS32 { 12 } else { 24 };
+            let data = &section.data[start..];
+            let uncompressed = match chdr.ctype {
+                types::ELFCOMPRESS_ZLIB => try!(inflate(data, chdr.size)),
+                types::ELFCOMPRESS_ZSTD => try!(unzstd(data, chdr.size)),
+                _ => return Err(ParseError::NotImplemented),
+            };
+            return Ok(Cow::Owned(uncompressed));
+        }
+
+        if section.is_zdebug() {
+            let mut rdr = io::Cursor::new(&section.data[4..12]);
+            let size = try!(rdr.read_u64::<BigEndian>());
+            return Ok(Cow::Owned(try!(inflate(&section.data[12..], size))));
+        }
+
+        Ok(Cow::Borrowed(&section.data[..]))
+    }
+}
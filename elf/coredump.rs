@@ -0,0 +1,266 @@
+//! Decoding of the process state recorded in core dumps (`ET_CORE` files).
+//!
+//! A core dump has no sections. The `PT_NOTE` segment describes the
+//! threads, their registers, the auxiliary vector and the mapped files,
+//! and the `PT_LOAD` segments hold the dumped memory, which can be read
+//! back with `File::read_at_vaddr`.
+
+use std::io;
+
+use super::{File, ParseError};
+use super::types;
+use super::utils;
+
+const X86_64_REGISTERS: &'static [&'static str] = &[
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8",
+    "rax", "rcx", "rdx", "rsi", "rdi", "orig_rax", "rip", "cs", "eflags",
+    "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs", "gs",
+];
+
+const I386_REGISTERS: &'static [&'static str] = &[
+    "ebx", "ecx", "edx", "esi", "edi", "ebp", "eax", "ds", "es", "fs", "gs",
+    "orig_eax", "eip", "cs", "eflags", "esp", "ss",
+];
+
+const AARCH64_REGISTERS: &'static [&'static str] = &[
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10",
+    "x11", "x12", "x13", "x14", "x15", "x16", "x17", "x18", "x19", "x20",
+    "x21", "x22", "x23", "x24", "x25", "x26", "x27", "x28", "x29", "x30",
+    "sp", "pc", "pstate",
+];
+
+const ARM_REGISTERS: &'static [&'static str] = &[
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10",
+    "r11", "r12", "sp", "lr", "pc", "cpsr", "orig_r0",
+];
+
+/// Names of the general-purpose registers in the order the kernel dumps
+/// them (`elf_gregset_t`), and the names of the pc and sp among them.
+fn register_names(machine: types::Machine) -> Option<(&'static [&'static str], &'static str, &'static str)> {
+    match machine {
+        types::EM_X86_64 => Some((X86_64_REGISTERS, "rip", "rsp")),
+        types::EM_386 => Some((I386_REGISTERS, "eip", "esp")),
+        types::EM_AARCH64 => Some((AARCH64_REGISTERS, "pc", "sp")),
+        types::EM_ARM => Some((ARM_REGISTERS, "pc", "sp")),
+        _ => None,
+    }
+}
+
+/// A thread of the dumped process, from its `NT_PRSTATUS` note
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoreThread {
+    /// Thread ID
+    pub tid: i32,
+    /// Current signal (`pr_cursig`); Linux records the fatal signal in
+    /// every thread
+    pub signal: u16,
+    /// General-purpose registers, in the order of the machine's
+    /// `elf_gregset_t`
+    pub registers: Vec<u64>,
+    /// Raw floating-point register set from the following `NT_FPREGSET`
+    /// note, if any
+    pub fpregs: Option<Vec<u8>>,
+    machine: types::Machine,
+}
+
+impl CoreThread {
+    /// Returns the value of the register called `name`, e.g. "rbx".
+    ///
+    /// Registers can only be looked up by name on x86, x86-64, ARM and
+    /// AArch64; use `registers` directly on other machines.
+    pub fn register(&self, name: &str) -> Option<u64> {
+        register_names(self.machine)
+            .and_then(|(names, _, _)| names.iter().position(|n| *n == name))
+            .and_then(|i| self.registers.get(i).cloned())
+    }
+
+    /// The program counter
+    pub fn pc(&self) -> Option<u64> {
+        register_names(self.machine).and_then(|(_, pc, _)| self.register(pc))
+    }
+
+    /// The stack pointer
+    pub fn sp(&self) -> Option<u64> {
+        register_names(self.machine).and_then(|(_, _, sp)| self.register(sp))
+    }
+}
+
+/// A file mapped into the address space, from the `NT_FILE` note
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MappedFile {
+    pub start: u64,
+    pub end: u64,
+    /// Offset in the file of the mapping's first byte
+    pub offset: u64,
+    pub path: String,
+}
+
+/// A range of the address space described by a `PT_LOAD` segment
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub end: u64,
+    /// Access permissions of the mapping
+    pub flags: types::ProgFlag,
+    /// Number of bytes from `start` that were dumped; the rest of the
+    /// region cannot be read
+    pub dumped: u64,
+    /// The file mapped at `start`, if any
+    pub path: Option<String>,
+}
+
+/// The process state recorded in a core dump
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Core {
+    /// Threads, starting with the one that received the fatal signal
+    pub threads: Vec<CoreThread>,
+    /// Auxiliary vector entries (`AT_*` type and value), without the
+    /// terminating `AT_NULL`
+    pub auxv: Vec<(u64, u64)>,
+    pub files: Vec<MappedFile>,
+    pub regions: Vec<MemoryRegion>,
+}
+
+impl Core {
+    /// The value of the auxiliary vector entry of type `key`.
+    pub fn auxv_value(&self, key: u64) -> Option<u64> {
+        self.auxv.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v)
+    }
+
+    /// The memory region containing `addr`.
+    pub fn region_for_vaddr(&self, addr: u64) -> Option<&MemoryRegion> {
+        self.regions.iter().find(|r| r.start <= addr && addr < r.end)
+    }
+}
+
+impl File {
+    fn read_word(&self, rdr: &mut io::Cursor<&[u8]>) -> io::Result<u64> {
+        if self.ehdr.class == types::ELFCLASS32 {
+            read_u32!(self, rdr).map(|w| w as u64)
+        } else {
+            read_u64!(self, rdr)
+        }
+    }
+
+    fn parse_prstatus(&self, desc: &[u8]) -> Result<CoreThread, ParseError> {
+        // Offsets of pr_pid and pr_reg in struct elf_prstatus, which ends
+        // with the pr_fpvalid int (padded to a word on 64-bit)
+        let (pid_off, reg_off, word) = if self.ehdr.class == types::ELFCLASS32 {
+            (24, 72, 4)
+        } else {
+            (32, 112, 8)
+        };
+        let len = desc.len() as u64;
+        if len < reg_off + word {
+            return Err(ParseError::InvalidFormat(None));
+        }
+        let mut rdr = io::Cursor::new(desc);
+        rdr.set_position(12);
+        let signal = try!(read_u16!(self, rdr));
+        rdr.set_position(pid_off);
+        let tid = try!(read_u32!(self, rdr)) as i32;
+        rdr.set_position(reg_off);
+        let mut registers = Vec::new();
+        for _ in 0..(len - reg_off - word) / word {
+            registers.push(try!(self.read_word(&mut rdr)));
+        }
+        Ok(CoreThread {
+            tid:       tid,
+            signal:    signal,
+            registers: registers,
+            fpregs:    None,
+            machine:   self.ehdr.machine,
+        })
+    }
+
+    fn parse_mapped_files(&self, desc: &[u8]) -> Result<Vec<MappedFile>, ParseError> {
+        let mut rdr = io::Cursor::new(desc);
+        let count = try!(self.read_word(&mut rdr));
+        let page_size = try!(self.read_word(&mut rdr));
+        let mut files = Vec::new();
+        for _ in 0..count {
+            let start = try!(self.read_word(&mut rdr));
+            let end = try!(self.read_word(&mut rdr));
+            let pgoff = try!(self.read_word(&mut rdr));
+            let offset = match pgoff.checked_mul(page_size) {
+                Some(offset) => offset,
+                None => return Err(ParseError::InvalidFormat(None)),
+            };
+            files.push(MappedFile {
+                start:  start,
+                end:    end,
+                offset: offset,
+                path:   String::new(),
+            });
+        }
+
+        // The paths follow the table, in the same order
+        let mut name_off = rdr.position() as usize;
+        for file in files.iter_mut() {
+            if name_off >= desc.len() {
+                return Err(ParseError::InvalidFormat(None));
+            }
+            file.path = try!(utils::get_string(desc, name_off));
+            name_off += file.path.len() + 1;
+        }
+        Ok(files)
+    }
+
+    fn parse_auxv(&self, desc: &[u8]) -> Result<Vec<(u64, u64)>, ParseError> {
+        let word = if self.ehdr.class == types::ELFCLASS32 { 4 } else { 8 };
+        let mut rdr = io::Cursor::new(desc);
+        let mut auxv = Vec::new();
+        while rdr.position() + 2 * word <= desc.len() as u64 {
+            let key = try!(self.read_word(&mut rdr));
+            let value = try!(self.read_word(&mut rdr));
+            if key == types::AT_NULL { break; }
+            auxv.push((key, value));
+        }
+        Ok(auxv)
+    }
+
+    /// Decodes the threads, auxiliary vector and memory map of a core dump.
+    pub fn core(&self) -> Result<Core, ParseError> {
+        if self.ehdr.elftype != types::ET_CORE {
+            return Err(ParseError::InvalidFormat(None));
+        }
+
+        let mut core = Core {
+            threads: Vec::new(),
+            auxv:    Vec::new(),
+            files:   Vec::new(),
+            regions: Vec::new(),
+        };
+        for note in try!(self.notes()) {
+            if note.name != "CORE" { continue; }
+            match note.ntype {
+                types::NT_PRSTATUS => core.threads.push(try!(self.parse_prstatus(&note.desc))),
+                // Each thread's floating-point registers follow its status
+                types::NT_FPREGSET => match core.threads.last_mut() {
+                    Some(thread) => thread.fpregs = Some(note.desc),
+                    None => return Err(ParseError::InvalidFormat(None)),
+                },
+                types::NT_AUXV => core.auxv = try!(self.parse_auxv(&note.desc)),
+                types::NT_FILE => core.files = try!(self.parse_mapped_files(&note.desc)),
+                _ => {}
+            }
+        }
+
+        for phdr in self.phdrs.iter().filter(|p| p.progtype == types::PT_LOAD) {
+            let end = match phdr.vaddr.checked_add(phdr.memsz) {
+                Some(end) => end,
+                None => return Err(ParseError::InvalidFormat(None)),
+            };
+            core.regions.push(MemoryRegion {
+                start:  phdr.vaddr,
+                end:    end,
+                flags:  phdr.flags,
+                dumped: phdr.filesz,
+                path:   core.files.iter()
+                    .find(|f| f.start == phdr.vaddr)
+                    .map(|f| f.path.clone()),
+            });
+        }
+        Ok(core)
+    }
+}
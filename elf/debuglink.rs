@@ -0,0 +1,158 @@
+//! Lookup of the separate debug-info file of a stripped binary.
+//!
+//! Debuggers find the file holding the debug info stripped from a binary
+//! by its build ID, as `<debug dir>/.build-id/xx/yyyy.debug`, or by the
+//! name recorded in its `.gnu_debuglink` section, which also carries the
+//! CRC32 of the debug file. The same search order is used here:
+//!
+//! 1. `<debug dir>/.build-id/xx/yyyy.debug` for each debug directory
+//! 2. `<name>` in the binary's directory
+//! 3. `.debug/<name>` in the binary's directory
+//! 4. `<debug dir>/<binary's directory>/<name>` for each debug directory
+
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::os::raw::{c_uint, c_ulong};
+use std::path::{Component, Path, PathBuf};
+
+use super::{File, ParseError};
+use super::types;
+use super::utils;
+
+/// The directory distributions install debug files into
+pub const DEFAULT_DEBUG_DIR: &'static str = "/usr/lib/debug";
+
+// zlib is linked by the `compress` module
+extern "C" {
+    fn crc32(crc: c_ulong, buf: *const u8, len: c_uint) -> c_ulong;
+}
+
+/// Computes the CRC32 used by `.gnu_debuglink` sections, which is the
+/// one used by zlib.
+pub fn debuglink_crc32(data: &[u8]) -> u32 {
+    let mut crc: c_ulong = 0;
+    for chunk in data.chunks(c_uint::max_value() as usize) {
+        crc = unsafe { crc32(crc, chunk.as_ptr(), chunk.len() as c_uint) };
+    }
+    crc as u32
+}
+
+/// Contents of a `.gnu_debuglink` section
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DebugLink {
+    /// File name of the debug file, without a directory
+    pub file: String,
+    /// CRC32 of the debug file's contents
+    pub crc: u32,
+}
+
+fn read_file(path: &Path) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    match fs::File::open(path).and_then(|mut f| f.read_to_end(&mut data)) {
+        Ok(_) => Some(data),
+        Err(_) => None,
+    }
+}
+
+/// Appends `path` to `dir`, even if `path` is absolute.
+fn join_under(dir: &Path, path: &Path) -> PathBuf {
+    let mut joined = dir.to_path_buf();
+    for component in path.components() {
+        if let Component::Normal(c) = component {
+            joined.push(c);
+        }
+    }
+    joined
+}
+
+impl File {
+    /// Decodes the `.gnu_debuglink` section, if the file has one.
+    pub fn debuglink(&self) -> Result<Option<DebugLink>, ParseError> {
+        let section = match self.get_section(".gnu_debuglink") {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        let file = try!(utils::get_string(&section.data, 0));
+
+        // The name is padded to a multiple of four bytes, the CRC follows
+        let crc_off = (file.len() as u64 + 1 + 3) & !3;
+        if crc_off + 4 > section.data.len() as u64 {
+            return Err(ParseError::InvalidFormat(None));
+        }
+        let mut rdr = io::Cursor::new(&section.data[..]);
+        rdr.set_position(crc_off);
+        let crc = try!(read_u32!(self, rdr));
+        Ok(Some(DebugLink { file: file, crc: crc }))
+    }
+
+    /// Paths searched for the debug file, each with whether it was derived
+    /// from the build ID rather than the debug link.
+    fn candidate_paths(&self, path: &Path, debug_dirs: &[PathBuf])
+                       -> Result<Vec<(PathBuf, bool)>, ParseError> {
+        let mut candidates = Vec::new();
+        if let Some(id) = try!(self.build_id()) {
+            if id.len() >= 2 {
+                let hex: Vec<String> = id.iter().map(|b| format!("{:02x}", b)).collect();
+                let name = format!("{}.debug", hex[1..].concat());
+                for dir in debug_dirs.iter() {
+                    candidates.push((dir.join(".build-id").join(&hex[0]).join(&name), true));
+                }
+            }
+        }
+        if let Some(link) = try!(self.debuglink()) {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            candidates.push((dir.join(&link.file), false));
+            candidates.push((dir.join(".debug").join(&link.file), false));
+            for debug_dir in debug_dirs.iter() {
+                candidates.push((join_under(debug_dir, dir).join(&link.file), false));
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Returns the paths searched for the debug file of this file, which
+    /// was read from `path`, in the order they are tried.
+    pub fn debug_file_candidates<P: AsRef<Path>>(&self, path: P, debug_dirs: &[PathBuf])
+                                                 -> Result<Vec<PathBuf>, ParseError> {
+        let candidates = try!(self.candidate_paths(path.as_ref(), debug_dirs));
+        Ok(candidates.into_iter().map(|(p, _)| p).collect())
+    }
+
+    /// Finds and opens the separate debug file of this file, which was read
+    /// from `path`, searching `debug_dirs` (usually `DEFAULT_DEBUG_DIR`)
+    /// and the file's own directory.
+    ///
+    /// A candidate found through the build ID must have the same build
+    /// ID, and one found through `.gnu_debuglink` the recorded CRC32;
+    /// others are skipped. Returns `None` if no matching file exists.
+    pub fn find_debug_file<P: AsRef<Path>>(&self, path: P, debug_dirs: &[PathBuf])
+                                           -> Result<Option<File>, ParseError> {
+        let path = path.as_ref();
+        let build_id = try!(self.build_id());
+        let link = try!(self.debuglink());
+
+        for (candidate, from_build_id) in try!(self.candidate_paths(path, debug_dirs)) {
+            if candidate == path { continue; }
+            let data = match read_file(&candidate) {
+                Some(data) => data,
+                None => continue,
+            };
+            if !from_build_id {
+                match link {
+                    Some(ref link) if debuglink_crc32(&data) == link.crc => {}
+                    _ => continue,
+                }
+            }
+            let debug_file = match File::open_stream(&mut io::Cursor::new(&data[..])) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            if from_build_id && debug_file.build_id().ok() != Some(build_id.clone()) {
+                continue;
+            }
+            return Ok(Some(debug_file));
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,128 @@
+//! Decoding of the dynamic linking information found in the `SHT_DYNAMIC`
+//! section (equivalently, the `PT_DYNAMIC` segment).
+
+use std::io;
+
+use super::{File, ParseError, Section};
+use super::types;
+use super::utils;
+
+/// A decoded entry of the dynamic array
+///
+/// Entries whose value is an offset into the dynamic string table are
+/// resolved to the string itself; everything else keeps its raw
+/// `d_val`/`d_ptr` value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Dynamic {
+    /// Name of a needed library
+    Needed(String),
+    /// Name of this shared object
+    SoName(String),
+    /// Library search path (deprecated in favour of `RunPath`)
+    RPath(String),
+    /// Library search path
+    RunPath(String),
+    /// `DT_FLAGS` value
+    Flags(types::DynFlag),
+    /// `DT_FLAGS_1` value
+    Flags1(u64),
+    /// Address of the GNU-style hash table
+    GnuHash(u64),
+    /// Any other entry, with its raw value
+    Other(types::DynTag, u64),
+}
+
+impl Dynamic {
+    pub fn tag(&self) -> types::DynTag {
+        match *self {
+            Dynamic::Needed(_) => types::DT_NEEDED,
+            Dynamic::SoName(_) => types::DT_SONAME,
+            Dynamic::RPath(_) => types::DT_RPATH,
+            Dynamic::RunPath(_) => types::DT_RUNPATH,
+            Dynamic::Flags(_) => types::DT_FLAGS,
+            Dynamic::Flags1(_) => types::DT_FLAGS_1,
+            Dynamic::GnuHash(_) => types::DT_GNU_HASH,
+            Dynamic::Other(tag, _) => tag,
+        }
+    }
+}
+
+impl File {
+    /// Returns the section holding the dynamic array, if any.
+    ///
+    /// Prefers the `SHT_DYNAMIC` section and otherwise falls back to the
+    /// section whose contents back the `PT_DYNAMIC` segment.
+    pub fn dynamic_section(&self) -> Option<&Section> {
+        if let Some(s) = self.sections.iter().find(|s| s.shdr.shtype == types::SHT_DYNAMIC) {
+            return Some(s);
+        }
+        self.phdrs.iter()
+            .find(|p| p.progtype == types::PT_DYNAMIC)
+            .and_then(|p| self.sections.iter().find(|s| {
+                s.shdr.shtype != types::SHT_NOBITS && s.shdr.offset == p.offset
+            }))
+    }
+
+    /// Decodes the dynamic array, stopping at the terminating `DT_NULL`.
+    ///
+    /// Returns an empty vector for statically linked files.
+    pub fn dynamic(&self) -> Result<Vec<Dynamic>, ParseError> {
+        let section = match self.dynamic_section() {
+            Some(s) => s,
+            None => return Ok(Vec::new()),
+        };
+        let strtab = self.sections.get(section.shdr.link as usize);
+
+        let mut entries = Vec::new();
+        let mut rdr = io::Cursor::new(&section.data[..]);
+        loop {
+            if rdr.position() >= section.data.len() as u64 { break; }
+
+            let tag: types::DynTag;
+            let val: u64;
+            if self.ehdr.class == types::ELFCLASS32 {
+                tag = types::DynTag(try!(read_u32!(self, rdr)) as i32 as i64);
+                val = try!(read_u32!(self, rdr)) as u64;
+            } else {
+                tag = types::DynTag(try!(read_u64!(self, rdr)) as i64);
+                val = try!(read_u64!(self, rdr));
+            }
+
+            let string = |val: u64| -> Result<String, ParseError> {
+                match strtab {
+                    Some(strtab) => Ok(try!(utils::get_string(&strtab.data, val as usize))),
+                    None => Err(ParseError::InvalidFormat(None)),
+                }
+            };
+
+            entries.push(match tag {
+                types::DT_NULL => break,
+                types::DT_NEEDED => Dynamic::Needed(try!(string(val))),
+                types::DT_SONAME => Dynamic::SoName(try!(string(val))),
+                types::DT_RPATH => Dynamic::RPath(try!(string(val))),
+                types::DT_RUNPATH => Dynamic::RunPath(try!(string(val))),
+                types::DT_FLAGS => Dynamic::Flags(types::DynFlag(val)),
+                types::DT_FLAGS_1 => Dynamic::Flags1(val),
+                types::DT_GNU_HASH => Dynamic::GnuHash(val),
+                tag => Dynamic::Other(tag, val),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Names of the libraries this file depends on, in `DT_NEEDED` order.
+    pub fn needed(&self) -> Result<Vec<String>, ParseError> {
+        Ok(try!(self.dynamic()).into_iter().filter_map(|d| match d {
+            Dynamic::Needed(name) => Some(name),
+            _ => None,
+        }).collect())
+    }
+
+    /// The `DT_SONAME` of a shared object, if it has one.
+    pub fn soname(&self) -> Result<Option<String>, ParseError> {
+        Ok(try!(self.dynamic()).into_iter().filter_map(|d| match d {
+            Dynamic::SoName(name) => Some(name),
+            _ => None,
+        }).next())
+    }
+}
@@ -11,10 +11,33 @@ pub mod types;
 #[macro_use]
 pub mod utils;
 
+pub mod address;
+pub mod compress;
+pub mod coredump;
+pub mod debuglink;
+pub mod dynamic;
+pub mod note;
+pub mod symbols;
+pub mod version;
+pub mod writer;
+
+pub use self::coredump::{Core, CoreThread, MappedFile, MemoryRegion};
+pub use self::debuglink::DebugLink;
+pub use self::dynamic::Dynamic;
+pub use self::note::Note;
+pub use self::version::{NeededVersion, VersionDefinition, VersionNeed};
+pub use self::writer::WriteError;
+
+#[cfg(test)]
+mod test;
+
 pub struct File {
     pub ehdr: types::FileHeader,
     pub phdrs: Vec<types::ProgramHeader>,
     pub sections: Vec<Section>,
+    /// Contents of each program header's segment, kept only for files
+    /// without section headers such as core dumps
+    pub segments: Vec<Vec<u8>>,
 }
 
 pub trait ReadExact {
@@ -28,6 +51,9 @@ impl<T> ReadExact for T
         let mut buf = Vec::with_capacity(len as usize);
         let mut chunk = self.take(len);
         try!(chunk.read_to_end(&mut buf));
+        if buf.len() as u64 != len {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "short read"));
+        }
         return Ok(buf);
     }
 }
@@ -120,13 +146,16 @@ impl File {
             shoff = try!(read_u64!(elf_f, io_file));
         }
 
-        let flags = try!(read_u32!(elf_f, io_file));
+        elf_f.ehdr.flags = try!(read_u32!(elf_f, io_file));
         let ehsize = try!(read_u16!(elf_f, io_file));
         let phentsize = try!(read_u16!(elf_f, io_file));
         let phnum = try!(read_u16!(elf_f, io_file));
         let shentsize = try!(read_u16!(elf_f, io_file));
         let shnum = try!(read_u16!(elf_f, io_file));
         let shstrndx = try!(read_u16!(elf_f, io_file));
+        elf_f.ehdr.phoff = phoff;
+        elf_f.ehdr.shoff = shoff;
+        elf_f.ehdr.shstrndx = shstrndx;
 
         // Parse the program headers
         try!(io_file.seek(io::SeekFrom::Start(phoff)));
@@ -211,6 +240,7 @@ impl File {
             elf_f.sections.push(Section {
                     shdr: types::SectionHeader {
                         name:      name,
+                        name_idx:  *name_idxs.last().unwrap(),
                         shtype:    shtype,
                         flags:     flags,
                         addr:      addr,
@@ -230,10 +260,13 @@ impl File {
         loop {
             if s_i == shnum as usize { break; }
 
-            let off = elf_f.sections[s_i].shdr.offset;
-            let size = elf_f.sections[s_i].shdr.size;
-            try!(io_file.seek(io::SeekFrom::Start(off)));
-            elf_f.sections[s_i].data = try!(io_file.read_exactly(size));
+            // SHT_NOBITS sections occupy no space in the file
+            if elf_f.sections[s_i].shdr.shtype != types::SHT_NOBITS {
+                let off = elf_f.sections[s_i].shdr.offset;
+                let size = elf_f.sections[s_i].shdr.size;
+                try!(io_file.seek(io::SeekFrom::Start(off)));
+                elf_f.sections[s_i].data = try!(io_file.read_exactly(size));
+            }
 
             s_i += 1;
         }
@@ -250,6 +283,20 @@ impl File {
             s_i += 1;
         }
 
+        // Without sections nothing else holds the segment contents
+        if shnum == 0 {
+            let file_len = try!(io_file.seek(io::SeekFrom::End(0)));
+            for phdr in elf_f.phdrs.iter() {
+                // Check the size against the file before allocating for it
+                match phdr.offset.checked_add(phdr.filesz) {
+                    Some(end) if end <= file_len => {}
+                    _ => return Err(ParseError::InvalidFormat(None)),
+                }
+                try!(io_file.seek(io::SeekFrom::Start(phdr.offset)));
+                elf_f.segments.push(try!(io_file.read_exactly(phdr.filesz)));
+            }
+        }
+
         Ok(elf_f)
     }
 
@@ -259,11 +306,65 @@ impl File {
             .find(|section| section.shdr.name == name.as_ref() )
     }
 
+    /// Size in bytes of the file header
+    pub fn ehsize(&self) -> u64 {
+        if self.ehdr.class == types::ELFCLASS32 { 52 } else { 64 }
+    }
+
+    /// Size in bytes of a program header table entry
+    pub fn phentsize(&self) -> u64 {
+        if self.phdrs.is_empty() { 0 }
+        else if self.ehdr.class == types::ELFCLASS32 { 32 } else { 56 }
+    }
+
+    /// Size in bytes of a section header table entry
+    pub fn shentsize(&self) -> u64 {
+        if self.sections.is_empty() { 0 }
+        else if self.ehdr.class == types::ELFCLASS32 { 40 } else { 64 }
+    }
+
+    /// Returns the `len` bytes of the file starting at `offset`, provided
+    /// they lie within a single section, or within a single segment for
+    /// files without sections.
+    fn file_range(&self, offset: u64, len: u64) -> Option<&[u8]> {
+        let end = match offset.checked_add(len) {
+            Some(end) => end,
+            None => return None,
+        };
+        let contains = |start: u64, size: u64| {
+            start <= offset && start.checked_add(size).map_or(false, |e| end <= e)
+        };
+        if self.sections.is_empty() {
+            return self.phdrs.iter().zip(self.segments.iter())
+                .find(|&(p, _)| contains(p.offset, p.filesz))
+                .map(|(p, data)| {
+                    let start = (offset - p.offset) as usize;
+                    &data[start..start + len as usize]
+                });
+        }
+        self.sections
+            .iter()
+            .filter(|s| s.shdr.shtype != types::SHT_NOBITS)
+            .find(|s| contains(s.shdr.offset, s.shdr.size))
+            .map(|s| {
+                let start = (offset - s.shdr.offset) as usize;
+                &s.data[start..start + len as usize]
+            })
+    }
+
+    /// Returns the file contents of the segment described by `phdr`,
+    /// provided they lie within a single section or the file has no
+    /// sections.
+    pub fn segment_data(&self, phdr: &types::ProgramHeader) -> Option<&[u8]> {
+        self.file_range(phdr.offset, phdr.filesz)
+    }
+
     pub fn new() -> File {
         File {
             ehdr: types::FileHeader::new(),
             phdrs: Vec::new(),
             sections: Vec::new(),
+            segments: Vec::new(),
         }
     }
 }
@@ -0,0 +1,120 @@
+//! Decoding of ELF notes (`SHT_NOTE` sections and `PT_NOTE` segments).
+
+use std::io;
+
+use super::{File, ParseError};
+use super::types;
+use super::utils;
+
+/// A single note entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Note {
+    /// Owner of the note, e.g. "GNU" or "CORE"
+    pub name: String,
+    /// Note type, interpreted relative to `name`
+    pub ntype: types::NoteType,
+    /// Note descriptor
+    pub desc: Vec<u8>,
+}
+
+impl Note {
+    /// Returns the build ID if this is a GNU build-id note.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        if self.name == "GNU" && self.ntype == types::NT_GNU_BUILD_ID {
+            Some(&self.desc[..])
+        } else {
+            None
+        }
+    }
+
+    /// Describes the note type in the style of readelf, or returns `None`
+    /// for types unknown for the owner.
+    pub fn description(&self) -> Option<String> {
+        let str = match (&self.name[..], self.ntype) {
+            ("GNU", ntype) => {
+                let str = format!("{}", ntype);
+                return if str.starts_with("<unknown>") { None } else { Some(str) };
+            }
+            ("CORE", types::NT_PRSTATUS) => "NT_PRSTATUS (prstatus structure)",
+            ("CORE", types::NT_FPREGSET) => "NT_FPREGSET (floating point registers)",
+            ("CORE", types::NT_PRPSINFO) => "NT_PRPSINFO (prpsinfo structure)",
+            ("CORE", types::NT_AUXV) => "NT_AUXV (auxiliary vector)",
+            ("CORE", types::NT_SIGINFO) => "NT_SIGINFO (siginfo_t data)",
+            ("CORE", types::NT_FILE) => "NT_FILE (mapped files)",
+            ("LINUX", types::NT_X86_XSTATE) => "NT_X86_XSTATE (x86 XSAVE extended state)",
+            _ => return None,
+        };
+        Some(str.to_string())
+    }
+}
+
+fn align_up(x: u64, align: u64) -> u64 {
+    (x + align - 1) & !(align - 1)
+}
+
+impl File {
+    /// Parses the notes in `data`, a sequence of note entries whose
+    /// name and descriptor are padded to `align` bytes.
+    pub fn parse_notes(&self, data: &[u8], align: u64) -> Result<Vec<Note>, ParseError> {
+        let align = if align == 8 { 8 } else { 4 };
+        let len = data.len() as u64;
+        let mut notes = Vec::new();
+        let mut rdr = io::Cursor::new(data);
+        loop {
+            // The 12-byte header is the same for both ELF classes
+            if rdr.position() + 12 > len { break; }
+
+            let namesz = try!(read_u32!(self, rdr)) as u64;
+            let descsz = try!(read_u32!(self, rdr)) as u64;
+            let ntype = types::NoteType(try!(read_u32!(self, rdr)));
+
+            let name_off = rdr.position();
+            let desc_off = align_up(name_off + namesz, align);
+            let next_off = align_up(desc_off + descsz, align);
+            if desc_off + descsz > len {
+                return Err(ParseError::InvalidFormat(None));
+            }
+
+            let name = if namesz == 0 {
+                String::new()
+            } else {
+                try!(utils::get_string(&data[..(name_off + namesz) as usize],
+                                       name_off as usize))
+            };
+            let desc = data[desc_off as usize..(desc_off + descsz) as usize].to_vec();
+            notes.push(Note { name: name, ntype: ntype, desc: desc });
+
+            rdr.set_position(next_off);
+        }
+        Ok(notes)
+    }
+
+    /// Returns every note in the file.
+    ///
+    /// Notes are read from the `SHT_NOTE` sections when the file has any,
+    /// and from the `PT_NOTE` segments otherwise.
+    pub fn notes(&self) -> Result<Vec<Note>, ParseError> {
+        let mut notes = Vec::new();
+        let mut saw_section = false;
+        for s in self.sections.iter().filter(|s| s.shdr.shtype == types::SHT_NOTE) {
+            notes.extend(try!(self.parse_notes(&s.data, s.shdr.addralign)));
+            saw_section = true;
+        }
+        if !saw_section {
+            for p in self.phdrs.iter().filter(|p| p.progtype == types::PT_NOTE) {
+                if let Some(data) = self.segment_data(p) {
+                    notes.extend(try!(self.parse_notes(data, p.align)));
+                }
+            }
+        }
+        Ok(notes)
+    }
+
+    /// The contents of the GNU build-id note, if present.
+    pub fn build_id(&self) -> Result<Option<Vec<u8>>, ParseError> {
+        Ok(try!(self.notes()).iter()
+           .filter_map(|n| n.build_id())
+           .next()
+           .map(|id| id.to_vec()))
+    }
+}
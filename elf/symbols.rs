@@ -0,0 +1,127 @@
+//! Decoding of symbol tables (`SHT_SYMTAB` / `SHT_DYNSYM`) and of
+//! relocation sections (`SHT_REL` / `SHT_RELA`).
+
+use std::io;
+
+use super::{File, ParseError, Section};
+use super::types;
+use super::utils;
+
+impl File {
+    /// Decodes the symbol table held in `section`, whose `sh_link` names
+    /// the string table for the symbol names.
+    ///
+    /// Dynamic symbols carry their GNU version when the file has a
+    /// `.gnu.version` section.
+    pub fn get_symbols(&self, section: &Section) -> Result<Vec<types::Symbol>, ParseError> {
+        if section.shdr.shtype != types::SHT_SYMTAB && section.shdr.shtype != types::SHT_DYNSYM {
+            return Err(ParseError::InvalidFormat(None));
+        }
+        let strtab = match self.sections.get(section.shdr.link as usize) {
+            Some(s) => s,
+            None => return Err(ParseError::InvalidFormat(None)),
+        };
+
+        let mut symbols = Vec::new();
+        let mut rdr = io::Cursor::new(&section.data[..]);
+        loop {
+            if rdr.position() >= section.data.len() as u64 { break; }
+
+            let name: u32;
+            let value: u64;
+            let size: u64;
+            let shndx: u16;
+            let info: u8;
+            let other: u8;
+
+            if self.ehdr.class == types::ELFCLASS32 {
+                name = try!(read_u32!(self, rdr));
+                value = try!(read_u32!(self, rdr)) as u64;
+                size = try!(read_u32!(self, rdr)) as u64;
+                info = try!(read_u8!(self, rdr));
+                other = try!(read_u8!(self, rdr));
+                shndx = try!(read_u16!(self, rdr));
+            } else {
+                name = try!(read_u32!(self, rdr));
+                info = try!(read_u8!(self, rdr));
+                other = try!(read_u8!(self, rdr));
+                shndx = try!(read_u16!(self, rdr));
+                value = try!(read_u64!(self, rdr));
+                size = try!(read_u64!(self, rdr));
+            }
+
+            let name = try!(utils::get_string(&strtab.data, name as usize));
+            symbols.push(types::Symbol::new(name, value, size, shndx, info, other));
+        }
+
+        if section.shdr.shtype == types::SHT_DYNSYM {
+            if let Some(versions) = try!(self.symbol_versions(section)) {
+                for (sym, version) in symbols.iter_mut().zip(versions) {
+                    sym.version = version;
+                }
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Decodes the static symbol table (`.symtab`), if the file has one.
+    pub fn symbols(&self) -> Result<Vec<types::Symbol>, ParseError> {
+        match self.sections.iter().find(|s| s.shdr.shtype == types::SHT_SYMTAB) {
+            Some(s) => self.get_symbols(s),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Decodes the dynamic symbol table (`.dynsym`), if the file has one.
+    pub fn dynamic_symbols(&self) -> Result<Vec<types::Symbol>, ParseError> {
+        match self.sections.iter().find(|s| s.shdr.shtype == types::SHT_DYNSYM) {
+            Some(s) => self.get_symbols(s),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Decodes the relocation entries held in `section`.
+    pub fn get_relocations(&self, section: &Section) -> Result<Vec<types::Relocation>, ParseError> {
+        let rela = section.shdr.shtype == types::SHT_RELA;
+        if !rela && section.shdr.shtype != types::SHT_REL {
+            return Err(ParseError::InvalidFormat(None));
+        }
+
+        let mut relocs = Vec::new();
+        let mut rdr = io::Cursor::new(&section.data[..]);
+        loop {
+            if rdr.position() >= section.data.len() as u64 { break; }
+
+            let offset: u64;
+            let sym: u32;
+            let rtype: u32;
+            let mut addend: Option<i64> = None;
+
+            if self.ehdr.class == types::ELFCLASS32 {
+                offset = try!(read_u32!(self, rdr)) as u64;
+                let info = try!(read_u32!(self, rdr));
+                sym = info >> 8;
+                rtype = info & 0xff;
+                if rela {
+                    addend = Some(try!(read_u32!(self, rdr)) as i32 as i64);
+                }
+            } else {
+                offset = try!(read_u64!(self, rdr));
+                let info = try!(read_u64!(self, rdr));
+                sym = (info >> 32) as u32;
+                rtype = (info & 0xffffffff) as u32;
+                if rela {
+                    addend = Some(try!(read_u64!(self, rdr)) as i64);
+                }
+            }
+
+            relocs.push(types::Relocation {
+                offset: offset,
+                sym:    sym,
+                rtype:  rtype,
+                addend: addend,
+            });
+        }
+        Ok(relocs)
+    }
+}
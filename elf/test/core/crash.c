@@ -0,0 +1,60 @@
+/* A two-thread process that kills itself with SIGABRT, used to produce
+ * the core dump fixture:
+ *
+ *   gcc -static -nostdlib -O1 -fno-stack-protector -o crash crash.c
+ *   ulimit -c unlimited; env -i ./crash
+ *
+ * The kernel always dumps the vDSO and vvar pages; their contents were
+ * zeroed in the fixture since they only describe the machine that
+ * produced it.
+ *
+ * The second thread runs on a small static stack and blocks in pause().
+ * Before aborting, the main thread keeps a pointer to a marker global in
+ * %rbx.
+ */
+#define SYS_madvise 28
+#define SYS_pause 34
+#define SYS_nanosleep 35
+#define SYS_clone 56
+#define SYS_getpid 39
+#define SYS_tgkill 234
+
+#define CLONE_FLAGS 0x50f00 /* VM | FS | FILES | SIGHAND | THREAD | SYSVSEM */
+
+static char thread_stack[4096] __attribute__((aligned(16)));
+unsigned long marker = 0x5354414b4d415053; /* "SPAMKATS" */
+
+static long syscall3(long n, long a, long b, long c) {
+    long ret;
+    __asm__ volatile ("syscall" : "=a"(ret) : "a"(n), "D"(a), "S"(b), "d"(c)
+                      : "rcx", "r11", "memory");
+    return ret;
+}
+
+void _start(void) {
+    long ret;
+    /* The child never returns from the asm block: it loops in pause() */
+    __asm__ volatile ("syscall\n\t"
+                      "test %%rax, %%rax\n\t"
+                      "jnz 2f\n"
+                      "1:\tmov $34, %%eax\n\t"
+                      "syscall\n\t"
+                      "jmp 1b\n"
+                      "2:"
+                      : "=a"(ret)
+                      : "a"(SYS_clone), "D"(CLONE_FLAGS), "S"(thread_stack + sizeof thread_stack),
+                        "d"(0)
+                      : "rcx", "r11", "memory");
+    long ts[2] = { 0, 10000000 };
+    syscall3(SYS_nanosleep, (long)ts, 0, 0);
+    /* Keep the fixture small: leave all but the current stack page out of
+     * the dump (MADV_DONTDUMP) */
+    long page = (long)ts & ~0xfffL;
+    syscall3(SYS_madvise, page - 0x40000, 0x40000, 16);
+    long pid = syscall3(SYS_getpid, 0, 0, 0);
+    __asm__ volatile ("mov %0, %%rbx\n\t"
+                      "syscall"
+                      : : "r"(&marker), "a"(SYS_tgkill), "D"(pid), "S"(pid), "d"(6)
+                      : "rbx", "rcx", "r11", "memory");
+    for (;;) ;
+}
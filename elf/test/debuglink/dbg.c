@@ -0,0 +1,9 @@
+/* Fixture for the separate debug file lookup tests:
+ *
+ *   gcc -shared -nostdlib -g -O1 -Wl,--build-id=sha1 -o dbg.full.so dbg.c
+ *   objcopy --only-keep-debug dbg.full.so dbg.so.debug
+ *   objcopy --strip-debug --add-gnu-debuglink=dbg.so.debug dbg.full.so dbg.so
+ */
+int add(int a, int b) {
+    return a + b;
+}
@@ -0,0 +1,6 @@
+/* gcc -shared -fPIC -O2 -nostdlib -Wl,-soname,libfixture.so.1 \
+ *     -Wl,--build-id=0x0123456789abcdef0123456789abcdef01234567 \
+ *     -Wl,-rpath,/opt/fixture/lib -Wl,--disable-new-dtags -Wl,-z,now \
+ *     -Wl,--no-as-needed -lm -o dyn.so dyn.c
+ */
+int fixture_answer(void) { return 42; }
@@ -0,0 +1,411 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::{Dynamic, File, ParseError, Section, WriteError};
+use super::types;
+
+fn open(bytes: &[u8]) -> File {
+    File::open_stream(&mut io::Cursor::new(bytes)).unwrap()
+}
+
+fn dyn_so() -> File {
+    open(include_bytes!("dyn.so"))
+}
+
+#[test]
+fn dynamic_entries() {
+    let file = dyn_so();
+    let dynamic = file.dynamic().unwrap();
+    assert_eq!(dynamic[0], Dynamic::Needed("libm.so.6".to_string()));
+    assert_eq!(dynamic[1], Dynamic::SoName("libfixture.so.1".to_string()));
+    assert_eq!(dynamic[2], Dynamic::RPath("/opt/fixture/lib".to_string()));
+    assert!(dynamic.iter().any(|d| d.tag() == types::DT_GNU_HASH));
+    assert!(dynamic.iter().any(|d| *d == Dynamic::Other(types::DT_BIND_NOW, 0)));
+    assert!(dynamic.iter().all(|d| d.tag() != types::DT_NULL));
+
+    assert_eq!(file.needed().unwrap(), vec!["libm.so.6".to_string()]);
+    assert_eq!(file.soname().unwrap(), Some("libfixture.so.1".to_string()));
+}
+
+#[test]
+fn dynamic_string_out_of_range() {
+    let mut bytes = include_bytes!("dyn.so").to_vec();
+    // The first entry is DT_NEEDED; point its name past the string table
+    let offset = dyn_so().get_section(".dynamic").unwrap().shdr.offset as usize;
+    assert_eq!(bytes[offset], types::DT_NEEDED.0 as u8);
+    for b in bytes[offset + 8..offset + 16].iter_mut() {
+        *b = 0xff;
+    }
+    match open(&bytes).dynamic() {
+        Err(ParseError::InvalidFormat(None)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn build_id_note() {
+    let file = dyn_so();
+    let notes = file.notes().unwrap();
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].name, "GNU");
+    assert_eq!(notes[0].ntype, types::NT_GNU_BUILD_ID);
+    assert_eq!(file.build_id().unwrap().unwrap(),
+               vec![0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23,
+                    0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67]);
+}
+
+fn assert_round_trips(bytes: &[u8]) {
+    let out = open(bytes).to_bytes().unwrap();
+    assert_eq!(out.len(), bytes.len());
+    assert!(out == bytes);
+}
+
+#[test]
+fn write_unmodified() {
+    assert_round_trips(include_bytes!("dyn.so"));
+    assert_round_trips(include_bytes!("../../unravel/src/test/x86.so"));
+    assert_round_trips(include_bytes!("../../unravel/src/test/arm.so"));
+    assert_round_trips(include_bytes!("../../unravel/src/test/aarch64.so"));
+    assert_round_trips(include_bytes!("core/crash.core"));
+}
+
+#[test]
+fn write_added_and_replaced_sections() {
+    let mut file = dyn_so();
+    let table = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    file.add_section(".fn_id_data", types::SHT_PROGBITS, types::SHF_NONE, 8, table.clone())
+        .unwrap();
+    let comment = b"a comment that is much longer than the one the linker wrote\0".to_vec();
+    file.replace_section(".comment", comment.clone()).unwrap();
+
+    let reread = open(&file.to_bytes().unwrap());
+    assert_eq!(reread.get_section(".fn_id_data").unwrap().data, table);
+    assert_eq!(reread.get_section(".comment").unwrap().data, comment);
+    assert_eq!(reread.sections.len(), file.sections.len());
+    for (a, b) in reread.sections.iter().zip(file.sections.iter()) {
+        assert_eq!(a.shdr, b.shdr);
+        assert_eq!(a.data, b.data);
+    }
+    assert_eq!(reread.phdrs, file.phdrs);
+}
+
+#[test]
+fn write_rejects_growing_allocated_section() {
+    let mut file = dyn_so();
+    let size = file.get_section(".text").unwrap().shdr.size as usize;
+    match file.replace_section(".text", vec![0x90; size + 1]) {
+        Err(WriteError::AllocatedSection(ref name)) if name == ".text" => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn display_names() {
+    assert_eq!(format!("{}", types::EM_RISCV), "RISC-V");
+    assert_eq!(format!("{}", types::Machine(0x1234)), "<unknown>: 0x1234");
+    assert_eq!(format!("{}", types::SHT_GNU_VERSYM), "VERSYM");
+    assert_eq!(format!("{}", types::SectionType(0x70000001)), "LOPROC+0x1");
+    assert_eq!(format!("{:<10}|", types::SHT_INIT_ARRAY), "INIT_ARRAY|");
+    assert_eq!(format!("{:<10}|", types::SHT_NOTE), "NOTE      |");
+    assert_eq!(format!("{}", types::PT_GNU_PROPERTY), "GNU_PROPERTY");
+    assert_eq!(format!("{}", types::ProgType(0x60000010)), "LOOS+0x10");
+
+    let flags = types::SectionFlag(types::SHF_WRITE.0 | types::SHF_ALLOC.0 | types::SHF_EXECINSTR.0);
+    assert_eq!(format!("{}", flags), "WAX");
+    assert_eq!(format!("{}", types::SectionFlag(types::SHF_ALLOC.0 | 0x08000000)), "Ao");
+    assert_eq!(format!("{}", types::SectionFlag(0x40000000)), "p");
+    assert_eq!(format!("{}", types::SectionFlag(0x1000)), "x");
+}
+
+#[test]
+fn parse_names() {
+    assert_eq!("EM_X86_64".parse(), Ok(types::EM_X86_64));
+    assert_eq!("aarch64".parse(), Ok(types::EM_AARCH64));
+    assert_eq!("243".parse(), Ok(types::EM_RISCV));
+    assert!("EM_NOPE".parse::<types::Machine>().is_err());
+
+    assert_eq!("progbits".parse(), Ok(types::SHT_PROGBITS));
+    assert_eq!("SHT_GNU_HASH".parse(), Ok(types::SHT_GNU_HASH));
+    assert_eq!("0x6ffffffe".parse(), Ok(types::SHT_GNU_VERNEED));
+    assert_eq!("PT_GNU_STACK".parse(), Ok(types::PT_GNU_STACK));
+    assert_eq!("func".parse(), Ok(types::STT_FUNC));
+    assert_eq!("STB_WEAK".parse(), Ok(types::STB_WEAK));
+    assert_eq!("hidden".parse(), Ok(types::STV_HIDDEN));
+
+    assert_eq!("AX".parse(), Ok(types::SectionFlag(types::SHF_ALLOC.0 | types::SHF_EXECINSTR.0)));
+    assert!("AQ".parse::<types::SectionFlag>().is_err());
+    assert_eq!("R E".parse(), Ok(types::ProgFlag(types::PF_R.0 | types::PF_X.0)));
+
+    // Every name printed for a known value parses back to that value
+    for t in 0..20 {
+        let t = types::SectionType(t);
+        let name = format!("{}", t);
+        if !name.starts_with("<unknown>") {
+            assert_eq!(name.parse(), Ok(t));
+        }
+    }
+}
+
+#[test]
+fn vaddr_lookup() {
+    let file = dyn_so();
+    let dynamic = file.get_section(".dynamic").unwrap();
+    let (addr, offset) = (dynamic.shdr.addr, dynamic.shdr.offset);
+    let first_word = dynamic.data[..8].to_vec();
+    assert_eq!(file.section_for_vaddr(addr + 8, 0).unwrap().shdr.name, ".dynamic");
+    assert_eq!(file.vaddr_to_offset(addr + 8, 0), Some(offset + 8));
+    assert_eq!(file.read_at_vaddr(addr, 0, 8), Some(&first_word[..]));
+    // DT_NEEDED is the first entry of the dynamic array
+    assert_eq!(file.read_word_at_vaddr(addr, 0), Some(types::DT_NEEDED.0 as u64));
+
+    // The range may not run past the end of the segment
+    assert_eq!(file.read_at_vaddr(addr + 0xf8, 0, 16), None);
+    assert_eq!(file.read_at_vaddr(addr, 0, u64::max_value()), None);
+    // The file header is mapped but not part of any section
+    assert_eq!(file.vaddr_to_offset(0, 0), Some(0));
+    assert_eq!(file.read_at_vaddr(0, 0, 4), None);
+    // Between the text and rodata segments
+    assert!(file.section_for_vaddr(0x1800, 0).is_none());
+    assert_eq!(file.vaddr_to_offset(0x1800, 0), None);
+
+    let bias = 0x7f0000000000;
+    assert_eq!(file.vaddr_to_offset(addr, bias), None);
+    assert_eq!(file.vaddr_to_offset(bias + addr, bias), Some(offset));
+    assert_eq!(file.read_at_vaddr(bias + addr, bias, 8), Some(&first_word[..]));
+
+    // A segment whose file offset overflows when the address is added
+    let mut file = file;
+    for p in file.phdrs.iter_mut().filter(|p| p.progtype == types::PT_LOAD) {
+        p.offset = u64::max_value();
+    }
+    assert_eq!(file.vaddr_to_offset(addr + 8, 0), None);
+    assert_eq!(file.read_at_vaddr(addr + 8, 0, 8), None);
+}
+
+#[test]
+fn vaddr_lookup_without_segments() {
+    let file = open(include_bytes!("rel.o"));
+    let text = file.get_section(".text").unwrap();
+    assert_eq!(file.section_for_vaddr(0, 0).unwrap().shdr.name, ".text");
+    assert_eq!(file.vaddr_to_offset(2, 0), Some(text.shdr.offset + 2));
+    assert_eq!(file.read_at_vaddr(0, 0, text.shdr.size), Some(&text.data[..]));
+    assert_eq!(file.read_at_vaddr(2, 0, u64::max_value()), None);
+}
+
+#[test]
+fn symbol_versions() {
+    let file = open(include_bytes!("ver.so"));
+
+    let defs = file.version_definitions().unwrap();
+    assert_eq!(defs.len(), 3);
+    assert_eq!((defs[0].flags, &defs[0].name[..]), (types::VER_FLG_BASE, "ver.so"));
+    assert_eq!((defs[2].index, &defs[2].name[..]), (3, "VER_2"));
+    assert_eq!(defs[2].parents, vec!["VER_1".to_string()]);
+
+    let needs = file.version_needs().unwrap();
+    assert_eq!(needs.len(), 1);
+    assert_eq!(needs[0].file, "libm.so.6");
+    assert_eq!(needs[0].versions[0].name, "GLIBC_2.2.5");
+
+    // Same names as `nm -D ver.so`
+    let mut names: Vec<String> = file.dynamic_symbols().unwrap().iter()
+        .filter(|s| !s.name.is_empty())
+        .map(|s| s.versioned_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["VER_1", "VER_2", "answer@@VER_2", "answer@VER_1",
+                           "cos@GLIBC_2.2.5", "unversioned_cos@@VER_1"]);
+
+    // Files without versioning information keep plain names
+    let file = dyn_so();
+    assert!(file.dynamic_symbols().unwrap().iter().all(|s| s.version.is_none()));
+}
+
+#[test]
+fn decompress_sections() {
+    let plain = open(include_bytes!("compressed/cmp.o"));
+    let debug_info = &plain.get_section(".debug_info").unwrap().data;
+    assert_eq!(&plain.decompressed_data(plain.get_section(".debug_info").unwrap()).unwrap()[..],
+               &debug_info[..]);
+
+    for &(bytes, ctype) in [(&include_bytes!("compressed/cmp-zlib.o")[..], types::ELFCOMPRESS_ZLIB),
+                            (&include_bytes!("compressed/cmp-zstd.o")[..], types::ELFCOMPRESS_ZSTD)].iter() {
+        let file = open(bytes);
+        let section = file.get_section(".debug_info").unwrap();
+        let chdr = file.compression_header(section).unwrap().unwrap();
+        assert_eq!(chdr.ctype, ctype);
+        assert_eq!(chdr.size, debug_info.len() as u64);
+        if ctype == types::ELFCOMPRESS_ZSTD && !cfg!(elf_zstd) {
+            match file.decompressed_data(section) {
+                Err(ParseError::NotImplemented) => {}
+                other => panic!("{:?}", other),
+            }
+            continue;
+        }
+        assert_eq!(&file.decompressed_data(section).unwrap()[..], &debug_info[..]);
+    }
+
+    // A header claiming more than the data could expand to is rejected
+    // before allocating for it
+    let file = open(include_bytes!("compressed/cmp-zlib.o"));
+    let section = file.get_section(".debug_info").unwrap();
+    let mut data = section.data.clone();
+    for b in data[8..16].iter_mut() {
+        *b = 0xff;
+    }
+    let section = Section { shdr: section.shdr.clone(), data: data };
+    assert!(file.decompressed_data(&section).is_err());
+
+    let file = open(include_bytes!("compressed/cmp-zlib-gnu.o"));
+    let section = file.get_section(".zdebug_info").unwrap();
+    assert!(section.is_zdebug());
+    assert_eq!(&file.decompressed_data(section).unwrap()[..], &debug_info[..]);
+
+    let plain = open(include_bytes!("compressed/cmp32.o"));
+    let file = open(include_bytes!("compressed/cmp32-zlib.o"));
+    // objcopy leaves sections alone when compressing would not shrink them
+    for &(name, compressed) in [(".debug_info", true), (".debug_line", false)].iter() {
+        let section = file.get_section(name).unwrap();
+        assert_eq!(file.compression_header(section).unwrap().is_some(), compressed);
+        assert_eq!(&file.decompressed_data(section).unwrap()[..],
+                   &plain.get_section(name).unwrap().data[..]);
+    }
+}
+
+#[test]
+fn core_dump() {
+    let file = open(include_bytes!("core/crash.core"));
+    let core = file.core().unwrap();
+
+    // The main thread raised SIGABRT with %rbx pointing at `marker`
+    assert_eq!(core.threads.len(), 2);
+    let thread = &core.threads[0];
+    assert_eq!(thread.signal, 6);
+    assert_eq!(core.threads[1].tid, thread.tid + 1);
+    assert_eq!(thread.registers.len(), 27);
+    assert_eq!(thread.register("rbx"), Some(0x403000));
+    assert!(thread.fpregs.is_some());
+    assert_eq!(file.read_word_at_vaddr(0x403000, 0), Some(0x5354414b4d415053));
+
+    let sp = thread.sp().unwrap();
+    assert!(file.read_word_at_vaddr(sp, 0).is_some());
+    assert!(core.region_for_vaddr(sp).unwrap().flags.0 & types::PF_W.0 != 0);
+    let pc = thread.pc().unwrap();
+    assert_eq!(core.region_for_vaddr(pc).unwrap().path, Some("/tmp/core/crash".to_string()));
+
+    // The second thread is parked on the static stack in .bss
+    let sp = core.threads[1].sp().unwrap();
+    assert!(0x403010 <= sp && sp <= 0x403010 + 4096);
+
+    assert_eq!(core.auxv_value(types::AT_PAGESZ), Some(4096));
+    let entry = core.auxv_value(types::AT_ENTRY).unwrap();
+    assert_eq!(core.region_for_vaddr(entry).unwrap().path, Some("/tmp/core/crash".to_string()));
+    assert!(core.files.iter().all(|f| f.path == "/tmp/core/crash"));
+    assert_eq!(core.files[0].offset, 0);
+
+    assert!(dyn_so().core().is_err());
+}
+
+#[test]
+fn core_dump_segment_bounds() {
+    let bytes = include_bytes!("core/crash.core");
+    let parse = |bytes: &[u8]| File::open_stream(&mut io::Cursor::new(bytes));
+
+    // The last segment runs past the end of a truncated file
+    match parse(&bytes[..bytes.len() - 1]) {
+        Err(ParseError::InvalidFormat(None)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    // A segment size far past the end of the file is rejected before
+    // allocating for it
+    let mut bytes = bytes.to_vec();
+    let phoff = open(&bytes).ehdr.phoff as usize;
+    for b in bytes[phoff + 32..phoff + 40].iter_mut() {
+        *b = 0xff;
+    }
+    match parse(&bytes) {
+        Err(ParseError::InvalidFormat(None)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    // A memory region whose end overflows the address space
+    let mut file = open(include_bytes!("core/crash.core"));
+    for p in file.phdrs.iter_mut().filter(|p| p.progtype == types::PT_LOAD) {
+        p.memsz = u64::max_value();
+    }
+    match file.core() {
+        Err(ParseError::InvalidFormat(None)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+/// A directory of files for a test, removed when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    /// A fresh directory named after `name` and the process, so that
+    /// concurrent test runs do not share it
+    fn new(name: &str) -> TempDir {
+        let pid = unsafe { ::libc::getpid() };
+        let path = ::std::env::temp_dir().join(format!("{}-{}", name, pid));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::File::create(path).unwrap().write_all(data).unwrap();
+}
+
+#[test]
+fn debug_file_lookup() {
+    let stripped_bytes = include_bytes!("debuglink/dbg.so");
+    let debug_bytes = &include_bytes!("debuglink/dbg.so.debug")[..];
+    let stripped = open(stripped_bytes);
+    let link = stripped.debuglink().unwrap().unwrap();
+    assert_eq!(link.file, "dbg.so.debug");
+    assert_eq!(link.crc, super::debuglink::debuglink_crc32(debug_bytes));
+    assert!(stripped.get_section(".debug_info").is_none());
+
+    let dir = TempDir::new("elf-debuglink-test");
+    let root = dir.0.clone();
+    let bin = root.join("bin").join("dbg.so");
+    let debug_dir = root.join("debug");
+    let dirs = vec![debug_dir.clone()];
+    write_file(&bin, &stripped_bytes[..]);
+    assert!(stripped.find_debug_file(&bin, &dirs).unwrap().is_none());
+
+    // Found next to the binary through .gnu_debuglink
+    let beside = root.join("bin").join(".debug").join("dbg.so.debug");
+    write_file(&beside, debug_bytes);
+    let debug = stripped.find_debug_file(&bin, &dirs).unwrap().unwrap();
+    assert!(debug.get_section(".debug_info").is_some());
+
+    // A file whose CRC does not match is skipped
+    let mut corrupt = debug_bytes.to_vec();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 0xff;
+    write_file(&beside, &corrupt);
+    write_file(&root.join("bin").join("dbg.so.debug"), &corrupt);
+    assert!(stripped.find_debug_file(&bin, &dirs).unwrap().is_none());
+
+    // Found through the build ID, which takes precedence
+    let candidates = stripped.debug_file_candidates(&bin, &dirs).unwrap();
+    let by_id: PathBuf = debug_dir.join(".build-id/eb/e132e6df5039a9399bac0178598ccbe947c175.debug");
+    assert_eq!(candidates[0], by_id);
+    assert_eq!(candidates[3], debug_dir.join(root.strip_prefix("/").unwrap()).join("bin/dbg.so.debug"));
+    write_file(&by_id, debug_bytes);
+    let debug = stripped.find_debug_file(&bin, &dirs).unwrap().unwrap();
+    assert_eq!(debug.build_id().unwrap(), stripped.build_id().unwrap());
+}
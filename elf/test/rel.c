@@ -0,0 +1,10 @@
+/* gcc -c -O2 -fPIC -fno-asynchronous-unwind-tables -o rel.o rel.c */
+extern int shared_counter;
+extern int external_fn(int);
+static int local_table[4] = { 1, 2, 3, 4 };
+int *table_ptr = local_table;
+
+int fixture_call(int x) {
+    shared_counter += x;
+    return external_fn(local_table[x & 3]);
+}
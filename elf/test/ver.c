@@ -0,0 +1,11 @@
+/* gcc -shared -fPIC -O2 -nostdlib -Wl,--version-script=ver.map \
+ *     -Wl,--no-as-needed -lm -o ver.so ver.c
+ */
+double cos(double);
+
+int answer_v1(void) { return 41; }
+int answer_v2(void) { return 42; }
+__asm__(".symver answer_v1, answer@VER_1");
+__asm__(".symver answer_v2, answer@@VER_2");
+
+double unversioned_cos(double x) { return cos(x); }
@@ -1,4 +1,51 @@
+use std::error;
 use std::fmt;
+use std::str;
+
+/// Error returned when a string names none of a type's known values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseNameError(pub String);
+
+impl fmt::Display for ParseNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&format!("unrecognized name '{}'", self.0))
+    }
+}
+
+impl error::Error for ParseNameError {
+    fn description(&self) -> &str {
+        "unrecognized name"
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number no larger than `max`
+fn parse_number(s: &str, max: u64) -> Option<u64> {
+    let s = s.trim();
+    let n = if s.starts_with("0x") || s.starts_with("0X") {
+        u64::from_str_radix(&s[2..], 16).ok()
+    } else {
+        s.parse::<u64>().ok()
+    };
+    n.and_then(|n| if n <= max { Some(n) } else { None })
+}
+
+/// Looks `s` up in `names`, ignoring case and an optional constant `prefix`
+fn parse_name<'a, T, I>(s: &str, prefix: &str, names: I) -> Result<T, ParseNameError>
+    where I: Iterator<Item = (T, &'a str)>
+{
+    let name = s.trim().to_uppercase();
+    let name = if name.starts_with(&prefix.to_uppercase()) {
+        &name[prefix.len()..]
+    } else {
+        &name[..]
+    };
+    for (value, n) in names {
+        if n.to_uppercase() == name {
+            return Ok(value);
+        }
+    }
+    Err(ParseNameError(s.to_string()))
+}
 
 /// Length of ELF file header platform-independent identification fields
 pub const EI_NIDENT: usize = 16;
@@ -43,9 +90,9 @@ impl fmt::Display for Class {
             ELFCLASSNONE => "Invalid",
             ELFCLASS32 => "32-bit",
             ELFCLASS64 => "64-bit",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
@@ -71,9 +118,9 @@ impl fmt::Display for Data {
             ELFDATANONE => "Invalid",
             ELFDATA2LSB => "2's complement, little endian",
             ELFDATA2MSB => "2's complement, big endian",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
@@ -98,9 +145,9 @@ impl fmt::Display for Version {
         let str = match *self {
             EV_NONE => "Invalid",
             EV_CURRENT => "1 (Current)",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
@@ -131,6 +178,12 @@ pub const ELFOSABI_TRU64 : OSABI = OSABI(10);
 pub const ELFOSABI_MODESTO : OSABI = OSABI(11);
 /// OpenBSD
 pub const ELFOSABI_OPENBSD : OSABI = OSABI(12);
+/// ARM EABI
+pub const ELFOSABI_ARM_AEABI : OSABI = OSABI(64);
+/// ARM
+pub const ELFOSABI_ARM : OSABI = OSABI(97);
+/// Standalone (embedded) application
+pub const ELFOSABI_STANDALONE : OSABI = OSABI(255);
 
 impl fmt::Debug for OSABI {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -152,9 +205,12 @@ impl fmt::Display for OSABI {
             ELFOSABI_TRU64 => "Compaq TRU64 UNIX",
             ELFOSABI_MODESTO => "Novell Modesto",
             ELFOSABI_OPENBSD => "OpenBSD",
-            _ => "Unknown",
+            ELFOSABI_ARM_AEABI => "ARM EABI",
+            ELFOSABI_ARM => "ARM",
+            ELFOSABI_STANDALONE => "Standalone App",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
@@ -186,9 +242,9 @@ impl fmt::Display for Type {
             ET_EXEC => "Executable file",
             ET_DYN => "Shared object file",
             ET_CORE => "Core file",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
@@ -201,6 +257,7 @@ pub const EM_SPARC : Machine = Machine(2);
 pub const EM_386 : Machine = Machine(3);
 pub const EM_68K : Machine = Machine(4);
 pub const EM_88K : Machine = Machine(5);
+pub const EM_IAMCU : Machine = Machine(6);
 pub const EM_860 : Machine = Machine(7);
 pub const EM_MIPS : Machine = Machine(8);
 pub const EM_S370 : Machine = Machine(9);
@@ -212,6 +269,7 @@ pub const EM_960 : Machine = Machine(19);
 pub const EM_PPC : Machine = Machine(20);
 pub const EM_PPC64 : Machine = Machine(21);
 pub const EM_S390 : Machine = Machine(22);
+pub const EM_SPU : Machine = Machine(23);
 pub const EM_V800 : Machine = Machine(36);
 pub const EM_FR20 : Machine = Machine(37);
 pub const EM_RH32 : Machine = Machine(38);
@@ -240,6 +298,8 @@ pub const EM_ST100 : Machine = Machine(60);
 pub const EM_TINYJ : Machine = Machine(61);
 pub const EM_X86_64 : Machine = Machine(62);
 pub const EM_PDSP : Machine = Machine(63);
+pub const EM_PDP10 : Machine = Machine(64);
+pub const EM_PDP11 : Machine = Machine(65);
 pub const EM_FX66 : Machine = Machine(66);
 pub const EM_ST9PLUS : Machine = Machine(67);
 pub const EM_ST7 : Machine = Machine(68);
@@ -268,11 +328,306 @@ pub const EM_MN10200 : Machine = Machine(90);
 pub const EM_PJ : Machine = Machine(91);
 pub const EM_OPENRISC : Machine = Machine(92);
 pub const EM_ARC_A5 : Machine = Machine(93);
+/// Later name of `EM_ARC_A5`
+pub const EM_ARC_COMPACT : Machine = EM_ARC_A5;
 pub const EM_XTENSA : Machine = Machine(94);
+pub const EM_VIDEOCORE : Machine = Machine(95);
+pub const EM_TMM_GPP : Machine = Machine(96);
+pub const EM_NS32K : Machine = Machine(97);
+pub const EM_TPC : Machine = Machine(98);
+pub const EM_SNP1K : Machine = Machine(99);
+pub const EM_ST200 : Machine = Machine(100);
+pub const EM_IP2K : Machine = Machine(101);
+pub const EM_MAX : Machine = Machine(102);
+pub const EM_CR : Machine = Machine(103);
+pub const EM_F2MC16 : Machine = Machine(104);
+pub const EM_MSP430 : Machine = Machine(105);
+pub const EM_BLACKFIN : Machine = Machine(106);
+pub const EM_SE_C33 : Machine = Machine(107);
+pub const EM_SEP : Machine = Machine(108);
+pub const EM_ARCA : Machine = Machine(109);
+pub const EM_UNICORE : Machine = Machine(110);
+pub const EM_EXCESS : Machine = Machine(111);
+pub const EM_DXP : Machine = Machine(112);
+pub const EM_ALTERA_NIOS2 : Machine = Machine(113);
+pub const EM_CRX : Machine = Machine(114);
+pub const EM_XGATE : Machine = Machine(115);
+pub const EM_C166 : Machine = Machine(116);
+pub const EM_M16C : Machine = Machine(117);
+pub const EM_DSPIC30F : Machine = Machine(118);
+pub const EM_CE : Machine = Machine(119);
+pub const EM_M32C : Machine = Machine(120);
+pub const EM_TSK3000 : Machine = Machine(131);
+pub const EM_RS08 : Machine = Machine(132);
+pub const EM_SHARC : Machine = Machine(133);
+pub const EM_ECOG2 : Machine = Machine(134);
+pub const EM_SCORE7 : Machine = Machine(135);
+pub const EM_DSP24 : Machine = Machine(136);
+pub const EM_VIDEOCORE3 : Machine = Machine(137);
+pub const EM_LATTICEMICO32 : Machine = Machine(138);
+pub const EM_SE_C17 : Machine = Machine(139);
+pub const EM_TI_C6000 : Machine = Machine(140);
+pub const EM_TI_C2000 : Machine = Machine(141);
+pub const EM_TI_C5500 : Machine = Machine(142);
+pub const EM_TI_ARP32 : Machine = Machine(143);
+pub const EM_TI_PRU : Machine = Machine(144);
+pub const EM_MMDSP_PLUS : Machine = Machine(160);
+pub const EM_CYPRESS_M8C : Machine = Machine(161);
+pub const EM_R32C : Machine = Machine(162);
+pub const EM_TRIMEDIA : Machine = Machine(163);
+pub const EM_QDSP6 : Machine = Machine(164);
+pub const EM_8051 : Machine = Machine(165);
+pub const EM_STXP7X : Machine = Machine(166);
+pub const EM_NDS32 : Machine = Machine(167);
+pub const EM_ECOG1X : Machine = Machine(168);
+pub const EM_MAXQ30 : Machine = Machine(169);
+pub const EM_XIMO16 : Machine = Machine(170);
+pub const EM_MANIK : Machine = Machine(171);
+pub const EM_CRAYNV2 : Machine = Machine(172);
+pub const EM_RX : Machine = Machine(173);
+pub const EM_METAG : Machine = Machine(174);
+pub const EM_MCST_ELBRUS : Machine = Machine(175);
+pub const EM_ECOG16 : Machine = Machine(176);
+pub const EM_CR16 : Machine = Machine(177);
+pub const EM_ETPU : Machine = Machine(178);
+pub const EM_SLE9X : Machine = Machine(179);
+pub const EM_L10M : Machine = Machine(180);
+pub const EM_K10M : Machine = Machine(181);
 pub const EM_AARCH64 : Machine = Machine(183);
+pub const EM_AVR32 : Machine = Machine(185);
+pub const EM_STM8 : Machine = Machine(186);
+pub const EM_TILE64 : Machine = Machine(187);
 pub const EM_TILEPRO : Machine = Machine(188);
 pub const EM_MICROBLAZE : Machine = Machine(189);
+pub const EM_CUDA : Machine = Machine(190);
 pub const EM_TILEGX : Machine = Machine(191);
+pub const EM_CLOUDSHIELD : Machine = Machine(192);
+pub const EM_COREA_1ST : Machine = Machine(193);
+pub const EM_COREA_2ND : Machine = Machine(194);
+pub const EM_ARCV2 : Machine = Machine(195);
+pub const EM_OPEN8 : Machine = Machine(196);
+pub const EM_RL78 : Machine = Machine(197);
+pub const EM_VIDEOCORE5 : Machine = Machine(198);
+pub const EM_78KOR : Machine = Machine(199);
+pub const EM_56800EX : Machine = Machine(200);
+pub const EM_BA1 : Machine = Machine(201);
+pub const EM_BA2 : Machine = Machine(202);
+pub const EM_XCORE : Machine = Machine(203);
+pub const EM_MCHP_PIC : Machine = Machine(204);
+pub const EM_INTELGT : Machine = Machine(205);
+pub const EM_KM32 : Machine = Machine(210);
+pub const EM_KMX32 : Machine = Machine(211);
+pub const EM_EMX16 : Machine = Machine(212);
+pub const EM_EMX8 : Machine = Machine(213);
+pub const EM_KVARC : Machine = Machine(214);
+pub const EM_CDP : Machine = Machine(215);
+pub const EM_COGE : Machine = Machine(216);
+pub const EM_COOL : Machine = Machine(217);
+pub const EM_NORC : Machine = Machine(218);
+pub const EM_CSR_KALIMBA : Machine = Machine(219);
+pub const EM_Z80 : Machine = Machine(220);
+pub const EM_VISIUM : Machine = Machine(221);
+pub const EM_FT32 : Machine = Machine(222);
+pub const EM_MOXIE : Machine = Machine(223);
+pub const EM_AMDGPU : Machine = Machine(224);
+pub const EM_RISCV : Machine = Machine(243);
+pub const EM_BPF : Machine = Machine(247);
+pub const EM_CSKY : Machine = Machine(252);
+pub const EM_LOONGARCH : Machine = Machine(258);
+pub const EM_ALPHA : Machine = Machine(0x9026);
+
+/// Constant name (without the `EM_` prefix) and description of each machine
+const MACHINE_NAMES: &'static [(Machine, &'static str, &'static str)] = &[
+    (EM_NONE, "NONE", "No machine"),
+    (EM_M32, "M32", "AT&T WE 32100"),
+    (EM_SPARC, "SPARC", "SUN SPARC"),
+    (EM_386, "386", "Intel 80386"),
+    (EM_68K, "68K", "Motorola m68k family"),
+    (EM_88K, "88K", "Motorola m88k family"),
+    (EM_IAMCU, "IAMCU", "Intel MCU"),
+    (EM_860, "860", "Intel 80860"),
+    (EM_MIPS, "MIPS", "MIPS R3000 big-endian"),
+    (EM_S370, "S370", "IBM System/370"),
+    (EM_MIPS_RS3_LE, "MIPS_RS3_LE", "MIPS R3000 little-endian"),
+    (EM_PARISC, "PARISC", "HPPA"),
+    (EM_VPP500, "VPP500", "Fujitsu VPP500"),
+    (EM_SPARC32PLUS, "SPARC32PLUS", "Sun's 'v8plus'"),
+    (EM_960, "960", "Intel 80960"),
+    (EM_PPC, "PPC", "PowerPC"),
+    (EM_PPC64, "PPC64", "PowerPC 64-bit"),
+    (EM_S390, "S390", "IBM S390"),
+    (EM_SPU, "SPU", "IBM SPU/SPC"),
+    (EM_V800, "V800", "NEC V800 series"),
+    (EM_FR20, "FR20", "Fujitsu FR20"),
+    (EM_RH32, "RH32", "TRW RH-32"),
+    (EM_RCE, "RCE", "Motorola RCE"),
+    (EM_ARM, "ARM", "ARM"),
+    (EM_FAKE_ALPHA, "FAKE_ALPHA", "Digital Alpha"),
+    (EM_SH, "SH", "Hitachi SH"),
+    (EM_SPARCV9, "SPARCV9", "SPARC v9 64-bit"),
+    (EM_TRICORE, "TRICORE", "Siemens Tricore"),
+    (EM_ARC, "ARC", "Argonaut RISC Core"),
+    (EM_H8_300, "H8_300", "Hitachi H8/300"),
+    (EM_H8_300H, "H8_300H", "Hitachi H8/300H"),
+    (EM_H8S, "H8S", "Hitachi H8S"),
+    (EM_H8_500, "H8_500", "Hitachi H8/500"),
+    (EM_IA_64, "IA_64", "Intel Merced"),
+    (EM_MIPS_X, "MIPS_X", "Stanford MIPS-X"),
+    (EM_COLDFIRE, "COLDFIRE", "Motorola Coldfire"),
+    (EM_68HC12, "68HC12", "Motorola M68HC12"),
+    (EM_MMA, "MMA", "Fujitsu MMA Multimedia Accelerator"),
+    (EM_PCP, "PCP", "Siemens PCP"),
+    (EM_NCPU, "NCPU", "Sony nCPU embeeded RISC"),
+    (EM_NDR1, "NDR1", "Denso NDR1 microprocessor"),
+    (EM_STARCORE, "STARCORE", "Motorola Start*Core processor"),
+    (EM_ME16, "ME16", "Toyota ME16 processor"),
+    (EM_ST100, "ST100", "STMicroelectronic ST100 processor"),
+    (EM_TINYJ, "TINYJ", "Advanced Logic Corp. Tinyj emb.fa"),
+    (EM_X86_64, "X86_64", "AMD x86-64 architecture"),
+    (EM_PDSP, "PDSP", "Sony DSP Processor"),
+    (EM_PDP10, "PDP10", "Digital PDP-10"),
+    (EM_PDP11, "PDP11", "Digital PDP-11"),
+    (EM_FX66, "FX66", "Siemens FX66 microcontroller"),
+    (EM_ST9PLUS, "ST9PLUS", "STMicroelectronics ST9+ 8/16 mc"),
+    (EM_ST7, "ST7", "STmicroelectronics ST7 8 bit mc"),
+    (EM_68HC16, "68HC16", "Motorola MC68HC16 microcontroller"),
+    (EM_68HC11, "68HC11", "Motorola MC68HC11 microcontroller"),
+    (EM_68HC08, "68HC08", "Motorola MC68HC08 microcontroller"),
+    (EM_68HC05, "68HC05", "Motorola MC68HC05 microcontroller"),
+    (EM_SVX, "SVX", "Silicon Graphics SVx"),
+    (EM_ST19, "ST19", "STMicroelectronics ST19 8 bit mc"),
+    (EM_VAX, "VAX", "Digital VAX"),
+    (EM_CRIS, "CRIS", "Axis Communications 32-bit embedded processor"),
+    (EM_JAVELIN, "JAVELIN", "Infineon Technologies 32-bit embedded processor"),
+    (EM_FIREPATH, "FIREPATH", "Element 14 64-bit DSP Processor"),
+    (EM_ZSP, "ZSP", "LSI Logic 16-bit DSP Processor"),
+    (EM_MMIX, "MMIX", "Donald Knuth's educational 64-bit processor"),
+    (EM_HUANY, "HUANY", "Harvard University machine-independent object files"),
+    (EM_PRISM, "PRISM", "SiTera Prism"),
+    (EM_AVR, "AVR", "Atmel AVR 8-bit microcontroller"),
+    (EM_FR30, "FR30", "Fujitsu FR30"),
+    (EM_D10V, "D10V", "Mitsubishi D10V"),
+    (EM_D30V, "D30V", "Mitsubishi D30V"),
+    (EM_V850, "V850", "NEC v850"),
+    (EM_M32R, "M32R", "Mitsubishi M32R"),
+    (EM_MN10300, "MN10300", "Matsushita MN10300"),
+    (EM_MN10200, "MN10200", "Matsushita MN10200"),
+    (EM_PJ, "PJ", "picoJava"),
+    (EM_OPENRISC, "OPENRISC", "OpenRISC 32-bit embedded processor"),
+    (EM_ARC_A5, "ARC_A5", "ARC Cores Tangent-A5"),
+    (EM_XTENSA, "XTENSA", "Tensilica Xtensa Architecture"),
+    (EM_VIDEOCORE, "VIDEOCORE", "Alphamosaic VideoCore"),
+    (EM_TMM_GPP, "TMM_GPP", "Thompson Multimedia General Purpose Proc"),
+    (EM_NS32K, "NS32K", "National Semi. 32000"),
+    (EM_TPC, "TPC", "Tenor Network TPC"),
+    (EM_SNP1K, "SNP1K", "Trebia SNP 1000"),
+    (EM_ST200, "ST200", "STMicroelectronics ST200"),
+    (EM_IP2K, "IP2K", "Ubicom IP2xxx"),
+    (EM_MAX, "MAX", "MAX processor"),
+    (EM_CR, "CR", "National Semi. CompactRISC"),
+    (EM_F2MC16, "F2MC16", "Fujitsu F2MC16"),
+    (EM_MSP430, "MSP430", "Texas Instruments msp430"),
+    (EM_BLACKFIN, "BLACKFIN", "Analog Devices Blackfin DSP"),
+    (EM_SE_C33, "SE_C33", "Seiko Epson S1C33 family"),
+    (EM_SEP, "SEP", "Sharp embedded microprocessor"),
+    (EM_ARCA, "ARCA", "Arca RISC"),
+    (EM_UNICORE, "UNICORE", "PKU-Unity & MPRC Peking Uni. mc series"),
+    (EM_EXCESS, "EXCESS", "eXcess configurable cpu"),
+    (EM_DXP, "DXP", "Icera Semi. Deep Execution Processor"),
+    (EM_ALTERA_NIOS2, "ALTERA_NIOS2", "Altera Nios II"),
+    (EM_CRX, "CRX", "National Semi. CompactRISC CRX"),
+    (EM_XGATE, "XGATE", "Motorola XGATE"),
+    (EM_C166, "C166", "Infineon C16x/XC16x"),
+    (EM_M16C, "M16C", "Renesas M16C"),
+    (EM_DSPIC30F, "DSPIC30F", "Microchip Technology dsPIC30F"),
+    (EM_CE, "CE", "Freescale Communication Engine RISC"),
+    (EM_M32C, "M32C", "Renesas M32C"),
+    (EM_TSK3000, "TSK3000", "Altium TSK3000"),
+    (EM_RS08, "RS08", "Freescale RS08"),
+    (EM_SHARC, "SHARC", "Analog Devices SHARC family"),
+    (EM_ECOG2, "ECOG2", "Cyan Technology eCOG2"),
+    (EM_SCORE7, "SCORE7", "Sunplus S+core7 RISC"),
+    (EM_DSP24, "DSP24", "New Japan Radio (NJR) 24-bit DSP"),
+    (EM_VIDEOCORE3, "VIDEOCORE3", "Broadcom VideoCore III"),
+    (EM_LATTICEMICO32, "LATTICEMICO32", "RISC for Lattice FPGA"),
+    (EM_SE_C17, "SE_C17", "Seiko Epson C17"),
+    (EM_TI_C6000, "TI_C6000", "Texas Instruments TMS320C6000 DSP"),
+    (EM_TI_C2000, "TI_C2000", "Texas Instruments TMS320C2000 DSP"),
+    (EM_TI_C5500, "TI_C5500", "Texas Instruments TMS320C55x DSP"),
+    (EM_TI_ARP32, "TI_ARP32", "Texas Instruments App. Specific RISC"),
+    (EM_TI_PRU, "TI_PRU", "Texas Instruments Prog. Realtime Unit"),
+    (EM_MMDSP_PLUS, "MMDSP_PLUS", "STMicroelectronics 64bit VLIW DSP"),
+    (EM_CYPRESS_M8C, "CYPRESS_M8C", "Cypress M8C"),
+    (EM_R32C, "R32C", "Renesas R32C"),
+    (EM_TRIMEDIA, "TRIMEDIA", "NXP Semi. TriMedia"),
+    (EM_QDSP6, "QDSP6", "QUALCOMM DSP6"),
+    (EM_8051, "8051", "Intel 8051 and variants"),
+    (EM_STXP7X, "STXP7X", "STMicroelectronics STxP7x"),
+    (EM_NDS32, "NDS32", "Andes Tech. compact code emb. RISC"),
+    (EM_ECOG1X, "ECOG1X", "Cyan Technology eCOG1X"),
+    (EM_MAXQ30, "MAXQ30", "Dallas Semi. MAXQ30 mc"),
+    (EM_XIMO16, "XIMO16", "New Japan Radio (NJR) 16-bit DSP"),
+    (EM_MANIK, "MANIK", "M2000 Reconfigurable RISC"),
+    (EM_CRAYNV2, "CRAYNV2", "Cray NV2 vector architecture"),
+    (EM_RX, "RX", "Renesas RX"),
+    (EM_METAG, "METAG", "Imagination Tech. META"),
+    (EM_MCST_ELBRUS, "MCST_ELBRUS", "MCST Elbrus"),
+    (EM_ECOG16, "ECOG16", "Cyan Technology eCOG16"),
+    (EM_CR16, "CR16", "National Semi. CompactRISC CR16"),
+    (EM_ETPU, "ETPU", "Freescale Extended Time Processing Unit"),
+    (EM_SLE9X, "SLE9X", "Infineon Tech. SLE9X"),
+    (EM_L10M, "L10M", "Intel L10M"),
+    (EM_K10M, "K10M", "Intel K10M"),
+    (EM_AARCH64, "AARCH64", "ARM AARCH64"),
+    (EM_AVR32, "AVR32", "Amtel 32-bit microprocessor"),
+    (EM_STM8, "STM8", "STMicroelectronics STM8"),
+    (EM_TILE64, "TILE64", "Tilera TILE64"),
+    (EM_TILEPRO, "TILEPRO", "Tilera TILEPro"),
+    (EM_MICROBLAZE, "MICROBLAZE", "Xilinx MicroBlaze"),
+    (EM_CUDA, "CUDA", "NVIDIA CUDA"),
+    (EM_TILEGX, "TILEGX", "Tilera TILE-Gx"),
+    (EM_CLOUDSHIELD, "CLOUDSHIELD", "CloudShield"),
+    (EM_COREA_1ST, "COREA_1ST", "KIPO-KAIST Core-A 1st gen"),
+    (EM_COREA_2ND, "COREA_2ND", "KIPO-KAIST Core-A 2nd gen"),
+    (EM_ARCV2, "ARCV2", "Synopsys ARCv2 ISA"),
+    (EM_OPEN8, "OPEN8", "Open8 RISC"),
+    (EM_RL78, "RL78", "Renesas RL78"),
+    (EM_VIDEOCORE5, "VIDEOCORE5", "Broadcom VideoCore V"),
+    (EM_78KOR, "78KOR", "Renesas 78KOR"),
+    (EM_56800EX, "56800EX", "Freescale 56800EX DSC"),
+    (EM_BA1, "BA1", "Beyond BA1"),
+    (EM_BA2, "BA2", "Beyond BA2"),
+    (EM_XCORE, "XCORE", "XMOS xCORE"),
+    (EM_MCHP_PIC, "MCHP_PIC", "Microchip 8-bit PIC(r)"),
+    (EM_INTELGT, "INTELGT", "Intel Graphics Technology"),
+    (EM_KM32, "KM32", "KM211 KM32"),
+    (EM_KMX32, "KMX32", "KM211 KMX32"),
+    (EM_EMX16, "EMX16", "KM211 KMX16"),
+    (EM_EMX8, "EMX8", "KM211 KMX8"),
+    (EM_KVARC, "KVARC", "KM211 KVARC"),
+    (EM_CDP, "CDP", "Paneve CDP"),
+    (EM_COGE, "COGE", "Cognitive Smart Memory Processor"),
+    (EM_COOL, "COOL", "Bluechip CoolEngine"),
+    (EM_NORC, "NORC", "Nanoradio Optimized RISC"),
+    (EM_CSR_KALIMBA, "CSR_KALIMBA", "CSR Kalimba"),
+    (EM_Z80, "Z80", "Zilog Z80"),
+    (EM_VISIUM, "VISIUM", "Controls and Data Services VISIUMcore"),
+    (EM_FT32, "FT32", "FTDI Chip FT32"),
+    (EM_MOXIE, "MOXIE", "Moxie processor"),
+    (EM_AMDGPU, "AMDGPU", "AMD GPU"),
+    (EM_RISCV, "RISCV", "RISC-V"),
+    (EM_BPF, "BPF", "Linux BPF -- in-kernel virtual machine"),
+    (EM_CSKY, "CSKY", "C-SKY"),
+    (EM_LOONGARCH, "LOONGARCH", "LoongArch"),
+    (EM_ALPHA, "ALPHA", "Digital Alpha"),
+];
+
+impl Machine {
+    /// The constant name without its `EM_` prefix, e.g. "X86_64"
+    pub fn name(&self) -> Option<&'static str> {
+        MACHINE_NAMES.iter().find(|&&(m, _, _)| m == *self).map(|&(_, name, _)| name)
+    }
+}
 
 impl fmt::Debug for Machine {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -282,88 +637,23 @@ impl fmt::Debug for Machine {
 
 impl fmt::Display for Machine {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let str = match *self {
-            EM_NONE => "No machine",
-            EM_M32 => "AT&T WE 32100",
-            EM_SPARC => "SUN SPARC",
-            EM_386 => "Intel 80386",
-            EM_68K => "Motorola m68k family",
-            EM_88K => "Motorola m88k family",
-            EM_860 => "Intel 80860",
-            EM_MIPS => "MIPS R3000 big-endian",
-            EM_S370 => "IBM System/370",
-            EM_MIPS_RS3_LE => "MIPS R3000 little-endian",
-            EM_PARISC => "HPPA",
-            EM_VPP500 => "Fujitsu VPP500",
-            EM_SPARC32PLUS => "Sun's 'v8plus'",
-            EM_960 => "Intel 80960",
-            EM_PPC => "PowerPC",
-            EM_PPC64 => "PowerPC 64-bit",
-            EM_S390 => "IBM S390",
-            EM_V800 => "NEC V800 series",
-            EM_FR20 => "Fujitsu FR20",
-            EM_RH32 => "TRW RH-32",
-            EM_RCE => "Motorola RCE",
-            EM_ARM => "ARM",
-            EM_FAKE_ALPHA => "Digital Alpha",
-            EM_SH => "Hitachi SH",
-            EM_SPARCV9 => "SPARC v9 64-bit",
-            EM_TRICORE => "Siemens Tricore",
-            EM_ARC => "Argonaut RISC Core",
-            EM_H8_300 => "Hitachi H8/300",
-            EM_H8_300H => "Hitachi H8/300H",
-            EM_H8S => "Hitachi H8S",
-            EM_H8_500 => "Hitachi H8/500",
-            EM_IA_64 => "Intel Merced",
-            EM_MIPS_X => "Stanford MIPS-X",
-            EM_COLDFIRE => "Motorola Coldfire",
-            EM_68HC12 => "Motorola M68HC12",
-            EM_MMA => "Fujitsu MMA Multimedia Accelerato",
-            EM_PCP => "Siemens PCP",
-            EM_NCPU => "Sony nCPU embeeded RISC",
-            EM_NDR1 => "Denso NDR1 microprocessor",
-            EM_STARCORE => "Motorola Start*Core processor",
-            EM_ME16 => "Toyota ME16 processor",
-            EM_ST100 => "STMicroelectronic ST100 processor",
-            EM_TINYJ => "Advanced Logic Corp. Tinyj emb.fa",
-            EM_X86_64 => "AMD x86-64 architecture",
-            EM_PDSP => "Sony DSP Processor",
-            EM_FX66 => "Siemens FX66 microcontroller",
-            EM_ST9PLUS => "STMicroelectronics ST9+ 8/16 mc",
-            EM_ST7 => "STmicroelectronics ST7 8 bit mc",
-            EM_68HC16 => "Motorola MC68HC16 microcontroller",
-            EM_68HC11 => "Motorola MC68HC11 microcontroller",
-            EM_68HC08 => "Motorola MC68HC08 microcontroller",
-            EM_68HC05 => "Motorola MC68HC05 microcontroller",
-            EM_SVX => "Silicon Graphics SVx",
-            EM_ST19 => "STMicroelectronics ST19 8 bit mc",
-            EM_VAX => "Digital VAX",
-            EM_CRIS => "Axis Communications 32-bit embedded processor",
-            EM_JAVELIN => "Infineon Technologies 32-bit embedded processor",
-            EM_FIREPATH => "Element 14 64-bit DSP Processor",
-            EM_ZSP => "LSI Logic 16-bit DSP Processor",
-            EM_MMIX => "Donald Knuth's educational 64-bit processor",
-            EM_HUANY => "Harvard University machine-independent object files",
-            EM_PRISM => "SiTera Prism",
-            EM_AVR => "Atmel AVR 8-bit microcontroller",
-            EM_FR30 => "Fujitsu FR30",
-            EM_D10V => "Mitsubishi D10V",
-            EM_D30V => "Mitsubishi D30V",
-            EM_V850 => "NEC v850",
-            EM_M32R => "Mitsubishi M32R",
-            EM_MN10300 => "Matsushita MN10300",
-            EM_MN10200 => "Matsushita MN10200",
-            EM_PJ => "picoJava",
-            EM_OPENRISC => "OpenRISC 32-bit embedded processor",
-            EM_ARC_A5 => "ARC Cores Tangent-A5",
-            EM_XTENSA => "Tensilica Xtensa Architecture",
-            EM_AARCH64 => "ARM AARCH64",
-            EM_TILEPRO => "Tilera TILEPro",
-            EM_MICROBLAZE => "Xilinx MicroBlaze",
-            EM_TILEGX => "Tilera TILE-Gx",
-            _ => "Unknown",
-        };
-        write!(f, "{}", str)
+        match MACHINE_NAMES.iter().find(|&&(m, _, _)| m == *self) {
+            Some(&(_, _, desc)) => f.pad(desc),
+            None => f.pad(&format!("<unknown>: {:#x}", self.0)),
+        }
+    }
+}
+
+impl str::FromStr for Machine {
+    type Err = ParseNameError;
+
+    /// Accepts constant names with or without the `EM_` prefix, in any
+    /// case ("EM_X86_64", "aarch64"), and raw numbers.
+    fn from_str(s: &str) -> Result<Machine, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xffff) {
+            return Ok(Machine(n as u16));
+        }
+        parse_name(s, "EM_", MACHINE_NAMES.iter().map(|&(m, name, _)| (m, name)))
     }
 }
 
@@ -391,13 +681,22 @@ pub struct FileHeader {
     pub machine:    Machine,
     /// Virtual address of program entry point
     pub entry:      u64,
+    /// Processor-specific flags
+    pub flags:      u32,
+    /// Offset into the ELF file where the program header table begins
+    pub phoff:      u64,
+    /// Offset into the ELF file where the section header table begins
+    pub shoff:      u64,
+    /// Index of the section holding the section name string table
+    pub shstrndx:   u16,
 }
 
 impl FileHeader {
     pub fn new() -> FileHeader {
         FileHeader { class : ELFCLASSNONE, data : ELFDATANONE, version : EV_NONE,
             elftype : ET_NONE, machine : EM_NONE, osabi : ELFOSABI_NONE,
-            abiversion : 0, entry : 0 }
+            abiversion : 0, entry : 0, flags : 0, phoff : 0, shoff : 0,
+            shstrndx : 0 }
     }
 }
 
@@ -445,6 +744,29 @@ impl fmt::Display for ProgFlag {
     }
 }
 
+impl str::FromStr for ProgFlag {
+    type Err = ParseNameError;
+
+    /// Accepts the letters printed by `Display` in any order ("RW", "R E"),
+    /// with "X" as an alternative to "E", and raw numbers.
+    fn from_str(s: &str) -> Result<ProgFlag, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xffffffff) {
+            return Ok(ProgFlag(n as u32));
+        }
+        let mut flags = PF_NONE;
+        for c in s.chars() {
+            flags.0 |= match c.to_ascii_uppercase() {
+                'R' => PF_R.0,
+                'W' => PF_W.0,
+                'E' | 'X' => PF_X.0,
+                ' ' => 0,
+                _ => return Err(ParseNameError(s.to_string())),
+            };
+        }
+        Ok(flags)
+    }
+}
+
 /// Represents ELF Program Header type
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct ProgType(pub u32);
@@ -464,12 +786,47 @@ pub const PT_SHLIB : ProgType = ProgType(5);
 pub const PT_PHDR : ProgType = ProgType(6);
 /// Thread-local storage segment
 pub const PT_TLS : ProgType = ProgType(7);
+/// Start of OS-specific segment types
+pub const PT_LOOS : ProgType = ProgType(0x60000000);
 /// GCC .eh_frame_hdr segment
 pub const PT_GNU_EH_FRAME : ProgType = ProgType(0x6474e550);
 /// Indicates stack executability
 pub const PT_GNU_STACK : ProgType = ProgType(0x6474e551);
 /// Read-only after relocation
 pub const PT_GNU_RELRO : ProgType = ProgType(0x6474e552);
+/// GNU property notes for the loader
+pub const PT_GNU_PROPERTY : ProgType = ProgType(0x6474e553);
+/// Stack frame information in SFrame format
+pub const PT_GNU_SFRAME : ProgType = ProgType(0x6474e554);
+/// Sun-specific segment
+pub const PT_SUNWBSS : ProgType = ProgType(0x6ffffffa);
+/// Stack segment
+pub const PT_SUNWSTACK : ProgType = ProgType(0x6ffffffb);
+/// End of OS-specific segment types
+pub const PT_HIOS : ProgType = ProgType(0x6fffffff);
+/// Start of processor-specific segment types
+pub const PT_LOPROC : ProgType = ProgType(0x70000000);
+/// End of processor-specific segment types
+pub const PT_HIPROC : ProgType = ProgType(0x7fffffff);
+
+/// Name of each segment type, without the `PT_` prefix
+const PROG_TYPE_NAMES: &'static [(ProgType, &'static str)] = &[
+    (PT_NULL, "NULL"),
+    (PT_LOAD, "LOAD"),
+    (PT_DYNAMIC, "DYNAMIC"),
+    (PT_INTERP, "INTERP"),
+    (PT_NOTE, "NOTE"),
+    (PT_SHLIB, "SHLIB"),
+    (PT_PHDR, "PHDR"),
+    (PT_TLS, "TLS"),
+    (PT_GNU_EH_FRAME, "GNU_EH_FRAME"),
+    (PT_GNU_STACK, "GNU_STACK"),
+    (PT_GNU_RELRO, "GNU_RELRO"),
+    (PT_GNU_PROPERTY, "GNU_PROPERTY"),
+    (PT_GNU_SFRAME, "GNU_SFRAME"),
+    (PT_SUNWBSS, "SUNWBSS"),
+    (PT_SUNWSTACK, "SUNWSTACK"),
+];
 
 impl fmt::Debug for ProgType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -479,21 +836,27 @@ impl fmt::Debug for ProgType {
 
 impl fmt::Display for ProgType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let str = match *self {
-            PT_NULL => "NULL",
-            PT_LOAD => "LOAD",
-            PT_DYNAMIC => "DYNAMIC",
-            PT_INTERP => "INTERP",
-            PT_NOTE => "NOTE",
-            PT_SHLIB => "SHLIB",
-            PT_PHDR => "PHDR",
-            PT_TLS => "TLS",
-            PT_GNU_EH_FRAME => "GNU_EH_FRAME",
-            PT_GNU_STACK => "GNU_STACK",
-            PT_GNU_RELRO => "GNU_RELRO",
-            _ => "Unknown",
-        };
-        write!(f, "{}", str)
+        match PROG_TYPE_NAMES.iter().find(|&&(t, _)| t == *self) {
+            Some(&(_, name)) => f.pad(name),
+            None if self.0 >= PT_LOOS.0 && self.0 <= PT_HIOS.0 =>
+                f.pad(&format!("LOOS+{:#x}", self.0 - PT_LOOS.0)),
+            None if self.0 >= PT_LOPROC.0 && self.0 <= PT_HIPROC.0 =>
+                f.pad(&format!("LOPROC+{:#x}", self.0 - PT_LOPROC.0)),
+            None => f.pad(&format!("<unknown>: {:#x}", self.0)),
+        }
+    }
+}
+
+impl str::FromStr for ProgType {
+    type Err = ParseNameError;
+
+    /// Accepts the names printed by `Display` with or without the `PT_`
+    /// prefix, in any case, and raw numbers.
+    fn from_str(s: &str) -> Result<ProgType, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xffffffff) {
+            return Ok(ProgType(n as u32));
+        }
+        parse_name(s, "PT_", PROG_TYPE_NAMES.iter().cloned())
     }
 }
 
@@ -566,20 +929,71 @@ pub const SHT_PREINIT_ARRAY : SectionType = SectionType(16);
 pub const SHT_GROUP : SectionType = SectionType(17);
 /// Extended symbol table section index
 pub const SHT_SYMTAB_SHNDX : SectionType = SectionType(18);
-/// Number of reserved SHT_* values
+/// Number of reserved SHT_* values (since reused for SHT_RELR)
 pub const SHT_NUM : SectionType = SectionType(19);
+/// Section data contains compact relative relocations
+pub const SHT_RELR : SectionType = SectionType(19);
+/// Start of OS-specific section types
+pub const SHT_LOOS : SectionType = SectionType(0x60000000);
+/// LLVM address-significance table
+pub const SHT_LLVM_ADDRSIG : SectionType = SectionType(0x6fff4c03);
 /// Object attributes
 pub const SHT_GNU_ATTRIBUTES : SectionType = SectionType(0x6ffffff5);
 /// GNU-style hash section
 pub const SHT_GNU_HASH : SectionType = SectionType(0x6ffffff6);
 /// Pre-link library list
 pub const SHT_GNU_LIBLIST : SectionType = SectionType(0x6ffffff7);
+/// Checksum for DSO content
+pub const SHT_CHECKSUM : SectionType = SectionType(0x6ffffff8);
 /// Version definition section
 pub const SHT_GNU_VERDEF : SectionType = SectionType(0x6ffffffd);
 /// Version needs section
 pub const SHT_GNU_VERNEED : SectionType = SectionType(0x6ffffffe);
 /// Version symbol table
 pub const SHT_GNU_VERSYM : SectionType = SectionType(0x6fffffff);
+/// End of OS-specific section types
+pub const SHT_HIOS : SectionType = SectionType(0x6fffffff);
+/// Start of processor-specific section types
+pub const SHT_LOPROC : SectionType = SectionType(0x70000000);
+/// End of processor-specific section types
+pub const SHT_HIPROC : SectionType = SectionType(0x7fffffff);
+/// Start of application-specific section types
+pub const SHT_LOUSER : SectionType = SectionType(0x80000000);
+/// End of application-specific section types
+pub const SHT_HIUSER : SectionType = SectionType(0x8fffffff);
+
+/// Name of each section type, as printed by readelf
+///
+/// Names are the constants without their `SHT_` prefix, except for the
+/// GNU version sections which drop the `GNU_` part as well.
+const SECTION_TYPE_NAMES: &'static [(SectionType, &'static str)] = &[
+    (SHT_NULL, "NULL"),
+    (SHT_PROGBITS, "PROGBITS"),
+    (SHT_SYMTAB, "SYMTAB"),
+    (SHT_STRTAB, "STRTAB"),
+    (SHT_RELA, "RELA"),
+    (SHT_HASH, "HASH"),
+    (SHT_DYNAMIC, "DYNAMIC"),
+    (SHT_NOTE, "NOTE"),
+    (SHT_NOBITS, "NOBITS"),
+    (SHT_REL, "REL"),
+    (SHT_SHLIB, "SHLIB"),
+    (SHT_DYNSYM, "DYNSYM"),
+    (SHT_INIT_ARRAY, "INIT_ARRAY"),
+    (SHT_FINI_ARRAY, "FINI_ARRAY"),
+    (SHT_PREINIT_ARRAY, "PREINIT_ARRAY"),
+    (SHT_GROUP, "GROUP"),
+    (SHT_SYMTAB_SHNDX, "SYMTAB_SHNDX"),
+    (SHT_RELR, "RELR"),
+    (SHT_LLVM_ADDRSIG, "LLVM_ADDRSIG"),
+    (SHT_GNU_ATTRIBUTES, "GNU_ATTRIBUTES"),
+    (SHT_GNU_HASH, "GNU_HASH"),
+    (SHT_GNU_LIBLIST, "GNU_LIBLIST"),
+    (SHT_CHECKSUM, "CHECKSUM"),
+    (SHT_GNU_VERDEF, "VERDEF"),
+    (SHT_GNU_VERNEED, "VERNEED"),
+    (SHT_GNU_VERSYM, "VERSYM"),
+];
 
 impl fmt::Debug for SectionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -589,34 +1003,29 @@ impl fmt::Debug for SectionType {
 
 impl fmt::Display for SectionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let str = match *self {
-            SHT_NULL => "SHT_NULL",
-            SHT_PROGBITS => "SHT_PROGBITS",
-            SHT_SYMTAB => "SHT_SYMTAB",
-            SHT_STRTAB => "SHT_STRTAB",
-            SHT_RELA => "SHT_RELA",
-            SHT_HASH => "SHT_HASH",
-            SHT_DYNAMIC => "SHT_DYNAMIC",
-            SHT_NOTE => "SHT_NOTE",
-            SHT_NOBITS => "SHT_NOBITS",
-            SHT_REL => "SHT_REL",
-            SHT_SHLIB => "SHT_SHLIB",
-            SHT_DYNSYM => "SHT_DYNSYM",
-            SHT_INIT_ARRAY => "SHT_INIT_ARRAY",
-            SHT_FINI_ARRAY => "SHT_FINI_ARRAY",
-            SHT_PREINIT_ARRAY => "SHT_PREINIT_ARRAY",
-            SHT_GROUP => "SHT_GROUP",
-            SHT_SYMTAB_SHNDX => "SHT_SYMTAB_SHNDX",
-            SHT_NUM => "SHT_NUM",
-            SHT_GNU_ATTRIBUTES => "SHT_GNU_ATTRIBUTES",
-            SHT_GNU_HASH => "SHT_GNU_HASH",
-            SHT_GNU_LIBLIST => "SHT_GNU_LIBLIST",
-            SHT_GNU_VERDEF => "SHT_GNU_VERDEF",
-            SHT_GNU_VERNEED => "SHT_GNU_VERNEED",
-            SHT_GNU_VERSYM => "SHT_GNU_VERSYM",
-            _ => "Unknown",
-        };
-        write!(f, "{}", str)
+        match SECTION_TYPE_NAMES.iter().find(|&&(t, _)| t == *self) {
+            Some(&(_, name)) => f.pad(name),
+            None if self.0 >= SHT_LOOS.0 && self.0 <= SHT_HIOS.0 =>
+                f.pad(&format!("LOOS+{:#x}", self.0 - SHT_LOOS.0)),
+            None if self.0 >= SHT_LOPROC.0 && self.0 <= SHT_HIPROC.0 =>
+                f.pad(&format!("LOPROC+{:#x}", self.0 - SHT_LOPROC.0)),
+            None if self.0 >= SHT_LOUSER.0 && self.0 <= SHT_HIUSER.0 =>
+                f.pad(&format!("LOUSER+{:#x}", self.0 - SHT_LOUSER.0)),
+            None => f.pad(&format!("<unknown>: {:#x}", self.0)),
+        }
+    }
+}
+
+impl str::FromStr for SectionType {
+    type Err = ParseNameError;
+
+    /// Accepts the names printed by `Display` with or without the `SHT_`
+    /// prefix, in any case, and raw numbers.
+    fn from_str(s: &str) -> Result<SectionType, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xffffffff) {
+            return Ok(SectionType(n as u32));
+        }
+        parse_name(s, "SHT_", SECTION_TYPE_NAMES.iter().cloned())
     }
 }
 
@@ -647,6 +1056,33 @@ pub const SHF_OS_NONCONFORMING : SectionFlag = SectionFlag(256);
 pub const SHF_GROUP : SectionFlag = SectionFlag(512);
 /// Section hold thread-local data
 pub const SHF_TLS : SectionFlag = SectionFlag(1024);
+/// Section data is compressed
+pub const SHF_COMPRESSED : SectionFlag = SectionFlag(2048);
+/// OS-specific flags
+pub const SHF_MASKOS : SectionFlag = SectionFlag(0x0ff00000);
+/// Section must not be garbage collected by the linker
+pub const SHF_GNU_RETAIN : SectionFlag = SectionFlag(0x200000);
+/// Processor-specific flags
+pub const SHF_MASKPROC : SectionFlag = SectionFlag(0xf0000000);
+/// Section is excluded from executables and shared objects
+pub const SHF_EXCLUDE : SectionFlag = SectionFlag(0x80000000);
+
+/// Letter readelf uses for each flag, in the order it prints them
+const SECTION_FLAG_LETTERS: &'static [(SectionFlag, char)] = &[
+    (SHF_WRITE, 'W'),
+    (SHF_ALLOC, 'A'),
+    (SHF_EXECINSTR, 'X'),
+    (SHF_MERGE, 'M'),
+    (SHF_STRINGS, 'S'),
+    (SHF_INFO_LINK, 'I'),
+    (SHF_LINK_ORDER, 'L'),
+    (SHF_OS_NONCONFORMING, 'O'),
+    (SHF_GROUP, 'G'),
+    (SHF_TLS, 'T'),
+    (SHF_COMPRESSED, 'C'),
+    (SHF_GNU_RETAIN, 'R'),
+    (SHF_EXCLUDE, 'E'),
+];
 
 impl fmt::Debug for SectionFlag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -654,17 +1090,84 @@ impl fmt::Debug for SectionFlag {
     }
 }
 
+/// Prints the flags as readelf's key letters, e.g. "WA" or "AX"
+///
+/// Bits without a letter of their own are shown as 'o' (OS-specific),
+/// 'p' (processor-specific) or 'x' (unknown).
 impl fmt::Display for SectionFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut letters = String::new();
+        let mut rest = self.0;
+        for &(flag, letter) in SECTION_FLAG_LETTERS.iter() {
+            if (self.0 & flag.0) != 0 {
+                letters.push(letter);
+                rest &= !flag.0;
+            }
+        }
+        if (rest & SHF_MASKOS.0) != 0 {
+            letters.push('o');
+        }
+        if (rest & SHF_MASKPROC.0) != 0 {
+            letters.push('p');
+        }
+        if (rest & !(SHF_MASKOS.0 | SHF_MASKPROC.0)) != 0 {
+            letters.push('x');
+        }
+        f.pad(&letters)
+    }
+}
+
+impl str::FromStr for SectionFlag {
+    type Err = ParseNameError;
+
+    /// Accepts the key letters printed by `Display` ("WAX") and raw numbers.
+    fn from_str(s: &str) -> Result<SectionFlag, ParseNameError> {
+        if let Some(n) = parse_number(s, u64::max_value()) {
+            return Ok(SectionFlag(n));
+        }
+        let mut flags = SHF_NONE;
+        for c in s.trim().chars() {
+            match SECTION_FLAG_LETTERS.iter().find(|&&(_, letter)| letter == c) {
+                Some(&(flag, _)) => flags.0 |= flag.0,
+                None => return Err(ParseNameError(s.to_string())),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Represents the compression algorithm of an `SHF_COMPRESSED` section
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct CompressionType(pub u32);
+/// ZLIB/DEFLATE algorithm
+pub const ELFCOMPRESS_ZLIB : CompressionType = CompressionType(1);
+/// Zstandard algorithm
+pub const ELFCOMPRESS_ZSTD : CompressionType = CompressionType(2);
+
+impl fmt::Debug for CompressionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:#x}", self.0)
     }
 }
 
+impl fmt::Display for CompressionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match *self {
+            ELFCOMPRESS_ZLIB => "ZLIB",
+            ELFCOMPRESS_ZSTD => "ZSTD",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
+        };
+        f.pad(str)
+    }
+}
+
 /// Encapsulates the contents of an ELF Section Header
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SectionHeader {
     /// Section Name
     pub name:      String,
+    /// Offset of the section name in the section name string table
+    pub name_idx:  u32,
     /// Section Type
     pub shtype:    SectionType,
     /// Section Flags
@@ -712,6 +1215,12 @@ pub const STT_TLS	 : SymbolType = SymbolType(6);
 /// Indirect code object symbol
 pub const STT_GNU_IFUNC : SymbolType = SymbolType(10);
 
+impl fmt::Debug for SymbolType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
 impl fmt::Display for SymbolType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let str = match *self {
@@ -723,9 +1232,42 @@ impl fmt::Display for SymbolType {
             STT_COMMON => "common data object",
             STT_TLS => "thread-local data object",
             STT_GNU_IFUNC => "indirect code object",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
+    }
+}
+
+/// Name of each symbol type, as printed by readelf
+const SYMBOL_TYPE_NAMES: &'static [(SymbolType, &'static str)] = &[
+    (STT_NOTYPE, "NOTYPE"),
+    (STT_OBJECT, "OBJECT"),
+    (STT_FUNC, "FUNC"),
+    (STT_SECTION, "SECTION"),
+    (STT_FILE, "FILE"),
+    (STT_COMMON, "COMMON"),
+    (STT_TLS, "TLS"),
+    (STT_GNU_IFUNC, "IFUNC"),
+    (STT_GNU_IFUNC, "GNU_IFUNC"),
+];
+
+impl SymbolType {
+    /// The constant name without its `STT_` prefix, e.g. "FUNC"
+    pub fn name(&self) -> Option<&'static str> {
+        SYMBOL_TYPE_NAMES.iter().find(|&&(v, _)| v == *self).map(|&(_, name)| name)
+    }
+}
+
+impl str::FromStr for SymbolType {
+    type Err = ParseNameError;
+
+    /// Accepts the names returned by `name()` with or without the `STT_`
+    /// prefix, in any case, and raw numbers.
+    fn from_str(s: &str) -> Result<SymbolType, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xf) {
+            return Ok(SymbolType(n as u8));
+        }
+        parse_name(s, "STT_", SYMBOL_TYPE_NAMES.iter().cloned())
     }
 }
 
@@ -740,6 +1282,12 @@ pub const STB_WEAK : SymbolBind = SymbolBind(2);
 /// Unique symbol
 pub const STB_GNU_UNIQUE : SymbolBind = SymbolBind(10);
 
+impl fmt::Debug for SymbolBind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
 impl fmt::Display for SymbolBind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let str = match *self {
@@ -747,9 +1295,38 @@ impl fmt::Display for SymbolBind {
             STB_GLOBAL => "global",
             STB_WEAK => "weak",
             STB_GNU_UNIQUE => "unique",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
+    }
+}
+
+/// Name of each symbol binding, as printed by readelf
+const SYMBOL_BIND_NAMES: &'static [(SymbolBind, &'static str)] = &[
+    (STB_LOCAL, "LOCAL"),
+    (STB_GLOBAL, "GLOBAL"),
+    (STB_WEAK, "WEAK"),
+    (STB_GNU_UNIQUE, "UNIQUE"),
+    (STB_GNU_UNIQUE, "GNU_UNIQUE"),
+];
+
+impl SymbolBind {
+    /// The constant name without its `STB_` prefix, e.g. "GLOBAL"
+    pub fn name(&self) -> Option<&'static str> {
+        SYMBOL_BIND_NAMES.iter().find(|&&(v, _)| v == *self).map(|&(_, name)| name)
+    }
+}
+
+impl str::FromStr for SymbolBind {
+    type Err = ParseNameError;
+
+    /// Accepts the names returned by `name()` with or without the `STB_`
+    /// prefix, in any case, and raw numbers.
+    fn from_str(s: &str) -> Result<SymbolBind, ParseNameError> {
+        if let Some(n) = parse_number(s, 0xf) {
+            return Ok(SymbolBind(n as u8));
+        }
+        parse_name(s, "STB_", SYMBOL_BIND_NAMES.iter().cloned())
     }
 }
 
@@ -764,6 +1341,12 @@ pub const STV_HIDDEN : SymbolVis = SymbolVis(2);
 /// Protected visibility
 pub const STV_PROTECTED : SymbolVis = SymbolVis(3);
 
+impl fmt::Debug for SymbolVis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
 impl fmt::Display for SymbolVis {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let str = match *self {
@@ -771,13 +1354,75 @@ impl fmt::Display for SymbolVis {
             STV_INTERNAL => "internal",
             STV_HIDDEN => "hidden",
             STV_PROTECTED => "protected",
-            _ => "Unknown",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
         };
-        write!(f, "{}", str)
+        f.pad(str)
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// Name of each symbol visibility, as printed by readelf
+const SYMBOL_VIS_NAMES: &'static [(SymbolVis, &'static str)] = &[
+    (STV_DEFAULT, "DEFAULT"),
+    (STV_INTERNAL, "INTERNAL"),
+    (STV_HIDDEN, "HIDDEN"),
+    (STV_PROTECTED, "PROTECTED"),
+];
+
+impl SymbolVis {
+    /// The constant name without its `STV_` prefix, e.g. "HIDDEN"
+    pub fn name(&self) -> Option<&'static str> {
+        SYMBOL_VIS_NAMES.iter().find(|&&(v, _)| v == *self).map(|&(_, name)| name)
+    }
+}
+
+impl str::FromStr for SymbolVis {
+    type Err = ParseNameError;
+
+    /// Accepts the names returned by `name()` with or without the `STV_`
+    /// prefix, in any case, and raw numbers.
+    fn from_str(s: &str) -> Result<SymbolVis, ParseNameError> {
+        if let Some(n) = parse_number(s, 0x3) {
+            return Ok(SymbolVis(n as u8));
+        }
+        parse_name(s, "STV_", SYMBOL_VIS_NAMES.iter().cloned())
+    }
+}
+
+/// Undefined section index
+pub const SHN_UNDEF : u16 = 0;
+/// Start of the reserved section indices
+pub const SHN_LORESERVE : u16 = 0xff00;
+/// Symbol has an absolute value
+pub const SHN_ABS : u16 = 0xfff1;
+/// Symbol is a common block
+pub const SHN_COMMON : u16 = 0xfff2;
+/// Section index is held in the extended section index table
+pub const SHN_XINDEX : u16 = 0xffff;
+
+/// Symbol is local to the file (`.gnu.version` index)
+pub const VER_NDX_LOCAL : u16 = 0;
+/// Symbol is global but unversioned (`.gnu.version` index)
+pub const VER_NDX_GLOBAL : u16 = 1;
+/// Marks a `.gnu.version` entry whose version is not the default
+pub const VERSYM_HIDDEN : u16 = 0x8000;
+/// Version definition of the file itself
+pub const VER_FLG_BASE : u16 = 0x1;
+/// Weak version reference
+pub const VER_FLG_WEAK : u16 = 0x2;
+
+/// GNU version information of a dynamic symbol
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SymbolVersion {
+    /// Version name, e.g. "GLIBC_2.2.5"
+    pub name: String,
+    /// Library expected to provide the version, for versions the file
+    /// needs rather than defines
+    pub file: Option<String>,
+    /// Hidden versions are not the default version of the symbol name
+    pub hidden: bool,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Symbol {
     /// Symbol name
     pub name: String,
@@ -785,11 +1430,37 @@ pub struct Symbol {
     pub value: u64,
     /// Symbol size
     pub size: u64,
+    /// Index of the section the symbol is defined in
+    pub shndx: u16,
+    /// Version of a dynamic symbol, if the file uses symbol versioning
+    pub version: Option<SymbolVersion>,
     info: u8,
     other: u8,
 }
 
 impl Symbol {
+    pub fn new(name: String, value: u64, size: u64, shndx: u16, info: u8, other: u8) -> Symbol {
+        Symbol { name: name, value: value, size: size, shndx: shndx, version: None,
+                 info: info, other: other }
+    }
+
+    /// The symbol name with its version appended the way `nm -D` shows
+    /// it: "name@@VERSION" for the default version of a defined symbol and
+    /// "name@VERSION" for hidden versions and undefined references.
+    pub fn versioned_name(&self) -> String {
+        match self.version {
+            // Symbols naming a version definition are shown without it
+            Some(ref v) if v.name != self.name || self.shndx != SHN_ABS => {
+                if v.hidden || v.file.is_some() || self.shndx == SHN_UNDEF {
+                    format!("{}@{}", self.name, v.name)
+                } else {
+                    format!("{}@@{}", self.name, v.name)
+                }
+            }
+            _ => self.name.clone(),
+        }
+    }
+
     pub fn sym_type(&self) -> SymbolType {
         SymbolType(self.info & 0xf)
     }
@@ -802,3 +1473,384 @@ impl Symbol {
         SymbolVis(self.other & 0x3)
     }
 }
+
+/// Encapsulates the contents of an ELF relocation entry
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    /// Location at which to apply the relocation
+    pub offset: u64,
+    /// Index of the symbol the relocation refers to
+    pub sym:    u32,
+    /// Processor-specific relocation type
+    pub rtype:  u32,
+    /// Constant addend, present for SHT_RELA entries only
+    pub addend: Option<i64>,
+}
+
+/// Returns the name of relocation type `rtype` on `machine`, if known.
+pub fn reloc_type_name(machine: Machine, rtype: u32) -> Option<&'static str> {
+    let name = match (machine, rtype) {
+        (EM_X86_64, 0) => "R_X86_64_NONE",
+        (EM_X86_64, 1) => "R_X86_64_64",
+        (EM_X86_64, 2) => "R_X86_64_PC32",
+        (EM_X86_64, 3) => "R_X86_64_GOT32",
+        (EM_X86_64, 4) => "R_X86_64_PLT32",
+        (EM_X86_64, 5) => "R_X86_64_COPY",
+        (EM_X86_64, 6) => "R_X86_64_GLOB_DAT",
+        (EM_X86_64, 7) => "R_X86_64_JUMP_SLOT",
+        (EM_X86_64, 8) => "R_X86_64_RELATIVE",
+        (EM_X86_64, 9) => "R_X86_64_GOTPCREL",
+        (EM_X86_64, 10) => "R_X86_64_32",
+        (EM_X86_64, 11) => "R_X86_64_32S",
+        (EM_X86_64, 12) => "R_X86_64_16",
+        (EM_X86_64, 13) => "R_X86_64_PC16",
+        (EM_X86_64, 14) => "R_X86_64_8",
+        (EM_X86_64, 15) => "R_X86_64_PC8",
+        (EM_X86_64, 16) => "R_X86_64_DTPMOD64",
+        (EM_X86_64, 17) => "R_X86_64_DTPOFF64",
+        (EM_X86_64, 18) => "R_X86_64_TPOFF64",
+        (EM_X86_64, 19) => "R_X86_64_TLSGD",
+        (EM_X86_64, 20) => "R_X86_64_TLSLD",
+        (EM_X86_64, 21) => "R_X86_64_DTPOFF32",
+        (EM_X86_64, 22) => "R_X86_64_GOTTPOFF",
+        (EM_X86_64, 23) => "R_X86_64_TPOFF32",
+        (EM_X86_64, 24) => "R_X86_64_PC64",
+        (EM_X86_64, 25) => "R_X86_64_GOTOFF64",
+        (EM_X86_64, 26) => "R_X86_64_GOTPC32",
+        (EM_X86_64, 27) => "R_X86_64_GOT64",
+        (EM_X86_64, 28) => "R_X86_64_GOTPCREL64",
+        (EM_X86_64, 29) => "R_X86_64_GOTPC64",
+        (EM_X86_64, 30) => "R_X86_64_GOTPLT64",
+        (EM_X86_64, 31) => "R_X86_64_PLTOFF64",
+        (EM_X86_64, 32) => "R_X86_64_SIZE32",
+        (EM_X86_64, 33) => "R_X86_64_SIZE64",
+        (EM_X86_64, 34) => "R_X86_64_GOTPC32_TLSDESC",
+        (EM_X86_64, 35) => "R_X86_64_TLSDESC_CALL",
+        (EM_X86_64, 36) => "R_X86_64_TLSDESC",
+        (EM_X86_64, 37) => "R_X86_64_IRELATIVE",
+        (EM_X86_64, 38) => "R_X86_64_RELATIVE64",
+        (EM_X86_64, 41) => "R_X86_64_GOTPCRELX",
+        (EM_X86_64, 42) => "R_X86_64_REX_GOTPCRELX",
+        (EM_386, 0) => "R_386_NONE",
+        (EM_386, 1) => "R_386_32",
+        (EM_386, 2) => "R_386_PC32",
+        (EM_386, 3) => "R_386_GOT32",
+        (EM_386, 4) => "R_386_PLT32",
+        (EM_386, 5) => "R_386_COPY",
+        (EM_386, 6) => "R_386_GLOB_DAT",
+        (EM_386, 7) => "R_386_JUMP_SLOT",
+        (EM_386, 8) => "R_386_RELATIVE",
+        (EM_386, 9) => "R_386_GOTOFF",
+        (EM_386, 10) => "R_386_GOTPC",
+        (EM_386, 14) => "R_386_TLS_TPOFF",
+        (EM_386, 35) => "R_386_TLS_DTPMOD32",
+        (EM_386, 36) => "R_386_TLS_DTPOFF32",
+        (EM_386, 37) => "R_386_TLS_TPOFF32",
+        (EM_386, 42) => "R_386_IRELATIVE",
+        (EM_386, 43) => "R_386_GOT32X",
+        (EM_ARM, 0) => "R_ARM_NONE",
+        (EM_ARM, 2) => "R_ARM_ABS32",
+        (EM_ARM, 3) => "R_ARM_REL32",
+        (EM_ARM, 10) => "R_ARM_THM_CALL",
+        (EM_ARM, 20) => "R_ARM_COPY",
+        (EM_ARM, 21) => "R_ARM_GLOB_DAT",
+        (EM_ARM, 22) => "R_ARM_JUMP_SLOT",
+        (EM_ARM, 23) => "R_ARM_RELATIVE",
+        (EM_ARM, 25) => "R_ARM_BASE_PREL",
+        (EM_ARM, 26) => "R_ARM_GOT_BREL",
+        (EM_ARM, 28) => "R_ARM_CALL",
+        (EM_ARM, 29) => "R_ARM_JUMP24",
+        (EM_ARM, 30) => "R_ARM_THM_JUMP24",
+        (EM_ARM, 42) => "R_ARM_PREL31",
+        (EM_ARM, 43) => "R_ARM_MOVW_ABS_NC",
+        (EM_ARM, 44) => "R_ARM_MOVT_ABS",
+        (EM_ARM, 160) => "R_ARM_IRELATIVE",
+        (EM_AARCH64, 0) => "R_AARCH64_NONE",
+        (EM_AARCH64, 257) => "R_AARCH64_ABS64",
+        (EM_AARCH64, 258) => "R_AARCH64_ABS32",
+        (EM_AARCH64, 259) => "R_AARCH64_ABS16",
+        (EM_AARCH64, 260) => "R_AARCH64_PREL64",
+        (EM_AARCH64, 261) => "R_AARCH64_PREL32",
+        (EM_AARCH64, 262) => "R_AARCH64_PREL16",
+        (EM_AARCH64, 275) => "R_AARCH64_ADR_PREL_PG_HI21",
+        (EM_AARCH64, 277) => "R_AARCH64_ADD_ABS_LO12_NC",
+        (EM_AARCH64, 278) => "R_AARCH64_LDST8_ABS_LO12_NC",
+        (EM_AARCH64, 282) => "R_AARCH64_JUMP26",
+        (EM_AARCH64, 283) => "R_AARCH64_CALL26",
+        (EM_AARCH64, 284) => "R_AARCH64_LDST16_ABS_LO12_NC",
+        (EM_AARCH64, 285) => "R_AARCH64_LDST32_ABS_LO12_NC",
+        (EM_AARCH64, 286) => "R_AARCH64_LDST64_ABS_LO12_NC",
+        (EM_AARCH64, 299) => "R_AARCH64_LDST128_ABS_LO12_NC",
+        (EM_AARCH64, 311) => "R_AARCH64_ADR_GOT_PAGE",
+        (EM_AARCH64, 312) => "R_AARCH64_LD64_GOT_LO12_NC",
+        (EM_AARCH64, 1024) => "R_AARCH64_COPY",
+        (EM_AARCH64, 1025) => "R_AARCH64_GLOB_DAT",
+        (EM_AARCH64, 1026) => "R_AARCH64_JUMP_SLOT",
+        (EM_AARCH64, 1027) => "R_AARCH64_RELATIVE",
+        (EM_AARCH64, 1028) => "R_AARCH64_TLS_DTPMOD",
+        (EM_AARCH64, 1029) => "R_AARCH64_TLS_DTPREL",
+        (EM_AARCH64, 1030) => "R_AARCH64_TLS_TPREL",
+        (EM_AARCH64, 1031) => "R_AARCH64_TLSDESC",
+        (EM_AARCH64, 1032) => "R_AARCH64_IRELATIVE",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Represents the tag of an entry in the dynamic section
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct DynTag(pub i64);
+/// Marks the end of the dynamic array
+pub const DT_NULL : DynTag = DynTag(0);
+/// String table offset of a needed library name
+pub const DT_NEEDED : DynTag = DynTag(1);
+/// Total size of the PLT relocation entries
+pub const DT_PLTRELSZ : DynTag = DynTag(2);
+/// Address of the PLT and/or GOT
+pub const DT_PLTGOT : DynTag = DynTag(3);
+/// Address of the symbol hash table
+pub const DT_HASH : DynTag = DynTag(4);
+/// Address of the dynamic string table
+pub const DT_STRTAB : DynTag = DynTag(5);
+/// Address of the dynamic symbol table
+pub const DT_SYMTAB : DynTag = DynTag(6);
+/// Address of the Rela relocation table
+pub const DT_RELA : DynTag = DynTag(7);
+/// Total size of the Rela relocation table
+pub const DT_RELASZ : DynTag = DynTag(8);
+/// Size of a Rela relocation entry
+pub const DT_RELAENT : DynTag = DynTag(9);
+/// Size of the dynamic string table
+pub const DT_STRSZ : DynTag = DynTag(10);
+/// Size of a dynamic symbol table entry
+pub const DT_SYMENT : DynTag = DynTag(11);
+/// Address of the initialization function
+pub const DT_INIT : DynTag = DynTag(12);
+/// Address of the termination function
+pub const DT_FINI : DynTag = DynTag(13);
+/// String table offset of the shared object name
+pub const DT_SONAME : DynTag = DynTag(14);
+/// String table offset of the library search path (deprecated)
+pub const DT_RPATH : DynTag = DynTag(15);
+/// Start symbol search in the shared object itself
+pub const DT_SYMBOLIC : DynTag = DynTag(16);
+/// Address of the Rel relocation table
+pub const DT_REL : DynTag = DynTag(17);
+/// Total size of the Rel relocation table
+pub const DT_RELSZ : DynTag = DynTag(18);
+/// Size of a Rel relocation entry
+pub const DT_RELENT : DynTag = DynTag(19);
+/// Type of relocation used by the PLT
+pub const DT_PLTREL : DynTag = DynTag(20);
+/// Reserved for debugger use
+pub const DT_DEBUG : DynTag = DynTag(21);
+/// Relocations might modify a non-writable segment
+pub const DT_TEXTREL : DynTag = DynTag(22);
+/// Address of the PLT relocation entries
+pub const DT_JMPREL : DynTag = DynTag(23);
+/// Process all relocations before transferring control
+pub const DT_BIND_NOW : DynTag = DynTag(24);
+/// Address of the array of constructors
+pub const DT_INIT_ARRAY : DynTag = DynTag(25);
+/// Address of the array of destructors
+pub const DT_FINI_ARRAY : DynTag = DynTag(26);
+/// Size of the array of constructors
+pub const DT_INIT_ARRAYSZ : DynTag = DynTag(27);
+/// Size of the array of destructors
+pub const DT_FINI_ARRAYSZ : DynTag = DynTag(28);
+/// String table offset of the library search path
+pub const DT_RUNPATH : DynTag = DynTag(29);
+/// Flags for the object being loaded
+pub const DT_FLAGS : DynTag = DynTag(30);
+/// Address of the array of pre-constructors
+pub const DT_PREINIT_ARRAY : DynTag = DynTag(32);
+/// Size of the array of pre-constructors
+pub const DT_PREINIT_ARRAYSZ : DynTag = DynTag(33);
+/// Address of the GNU-style hash table
+pub const DT_GNU_HASH : DynTag = DynTag(0x6ffffef5);
+/// Address of the symbol version table
+pub const DT_VERSYM : DynTag = DynTag(0x6ffffff0);
+/// Number of relative Rela relocations
+pub const DT_RELACOUNT : DynTag = DynTag(0x6ffffff9);
+/// Number of relative Rel relocations
+pub const DT_RELCOUNT : DynTag = DynTag(0x6ffffffa);
+/// State flags for the object being loaded
+pub const DT_FLAGS_1 : DynTag = DynTag(0x6ffffffb);
+/// Address of the version definition table
+pub const DT_VERDEF : DynTag = DynTag(0x6ffffffc);
+/// Number of version definitions
+pub const DT_VERDEFNUM : DynTag = DynTag(0x6ffffffd);
+/// Address of the version needs table
+pub const DT_VERNEED : DynTag = DynTag(0x6ffffffe);
+/// Number of version needs
+pub const DT_VERNEEDNUM : DynTag = DynTag(0x6fffffff);
+
+impl fmt::Debug for DynTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl fmt::Display for DynTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match *self {
+            DT_NULL => "NULL",
+            DT_NEEDED => "NEEDED",
+            DT_PLTRELSZ => "PLTRELSZ",
+            DT_PLTGOT => "PLTGOT",
+            DT_HASH => "HASH",
+            DT_STRTAB => "STRTAB",
+            DT_SYMTAB => "SYMTAB",
+            DT_RELA => "RELA",
+            DT_RELASZ => "RELASZ",
+            DT_RELAENT => "RELAENT",
+            DT_STRSZ => "STRSZ",
+            DT_SYMENT => "SYMENT",
+            DT_INIT => "INIT",
+            DT_FINI => "FINI",
+            DT_SONAME => "SONAME",
+            DT_RPATH => "RPATH",
+            DT_SYMBOLIC => "SYMBOLIC",
+            DT_REL => "REL",
+            DT_RELSZ => "RELSZ",
+            DT_RELENT => "RELENT",
+            DT_PLTREL => "PLTREL",
+            DT_DEBUG => "DEBUG",
+            DT_TEXTREL => "TEXTREL",
+            DT_JMPREL => "JMPREL",
+            DT_BIND_NOW => "BIND_NOW",
+            DT_INIT_ARRAY => "INIT_ARRAY",
+            DT_FINI_ARRAY => "FINI_ARRAY",
+            DT_INIT_ARRAYSZ => "INIT_ARRAYSZ",
+            DT_FINI_ARRAYSZ => "FINI_ARRAYSZ",
+            DT_RUNPATH => "RUNPATH",
+            DT_FLAGS => "FLAGS",
+            DT_PREINIT_ARRAY => "PREINIT_ARRAY",
+            DT_PREINIT_ARRAYSZ => "PREINIT_ARRAYSZ",
+            DT_GNU_HASH => "GNU_HASH",
+            DT_VERSYM => "VERSYM",
+            DT_RELACOUNT => "RELACOUNT",
+            DT_RELCOUNT => "RELCOUNT",
+            DT_FLAGS_1 => "FLAGS_1",
+            DT_VERDEF => "VERDEF",
+            DT_VERDEFNUM => "VERDEFNUM",
+            DT_VERNEED => "VERNEED",
+            DT_VERNEEDNUM => "VERNEEDNUM",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
+        };
+        f.pad(str)
+    }
+}
+
+/// Represents the flags of a `DT_FLAGS` dynamic entry
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct DynFlag(pub u64);
+/// Object may use $ORIGIN
+pub const DF_ORIGIN : DynFlag = DynFlag(0x1);
+/// Symbol resolution starts with the object itself
+pub const DF_SYMBOLIC : DynFlag = DynFlag(0x2);
+/// Relocations might modify a non-writable segment
+pub const DF_TEXTREL : DynFlag = DynFlag(0x4);
+/// Process all relocations before transferring control
+pub const DF_BIND_NOW : DynFlag = DynFlag(0x8);
+/// Object uses the static thread-local storage model
+pub const DF_STATIC_TLS : DynFlag = DynFlag(0x10);
+
+impl fmt::Debug for DynFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl fmt::Display for DynFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [(DF_ORIGIN, "ORIGIN"),
+                     (DF_SYMBOLIC, "SYMBOLIC"),
+                     (DF_TEXTREL, "TEXTREL"),
+                     (DF_BIND_NOW, "BIND_NOW"),
+                     (DF_STATIC_TLS, "STATIC_TLS")];
+        let mut first = true;
+        for &(flag, name) in names.iter() {
+            if (self.0 & flag.0) != 0 {
+                if !first { try!(write!(f, " ")); }
+                try!(write!(f, "{}", name));
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Represents the type of an ELF note
+///
+/// Note types are only meaningful together with the note's owner name.
+/// The `NT_GNU_*` constants are defined for the "GNU" owner, the others for
+/// the "CORE" and "LINUX" owners used in core dumps.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct NoteType(pub u32);
+/// ABI information
+pub const NT_GNU_ABI_TAG : NoteType = NoteType(1);
+/// Synthetic hwcap information
+pub const NT_GNU_HWCAP : NoteType = NoteType(2);
+/// Unique build ID bitstring
+pub const NT_GNU_BUILD_ID : NoteType = NoteType(3);
+/// Version of the gold linker
+pub const NT_GNU_GOLD_VERSION : NoteType = NoteType(4);
+/// Program property
+pub const NT_GNU_PROPERTY_TYPE_0 : NoteType = NoteType(5);
+/// Process status (`prstatus`), one per thread
+pub const NT_PRSTATUS : NoteType = NoteType(1);
+/// Floating-point registers (`elf_fpregset_t`), one per thread
+pub const NT_FPREGSET : NoteType = NoteType(2);
+/// Process information (`prpsinfo`)
+pub const NT_PRPSINFO : NoteType = NoteType(3);
+/// Auxiliary vector
+pub const NT_AUXV : NoteType = NoteType(6);
+/// Information on the signal that caused the dump (`siginfo_t`)
+pub const NT_SIGINFO : NoteType = NoteType(0x53494749);
+/// Files mapped into the address space
+pub const NT_FILE : NoteType = NoteType(0x46494c45);
+/// x86 extended state saved by XSAVE, one per thread ("LINUX" owner)
+pub const NT_X86_XSTATE : NoteType = NoteType(0x202);
+
+impl fmt::Debug for NoteType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl fmt::Display for NoteType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match *self {
+            NT_GNU_ABI_TAG => "NT_GNU_ABI_TAG (ABI version tag)",
+            NT_GNU_HWCAP => "NT_GNU_HWCAP (DSO-supplied software HWCAP info)",
+            NT_GNU_BUILD_ID => "NT_GNU_BUILD_ID (unique build ID bitstring)",
+            NT_GNU_GOLD_VERSION => "NT_GNU_GOLD_VERSION (gold version)",
+            NT_GNU_PROPERTY_TYPE_0 => "NT_GNU_PROPERTY_TYPE_0",
+            _ => return f.pad(&format!("<unknown>: {:#x}", self.0)),
+        };
+        f.pad(str)
+    }
+}
+
+// Auxiliary vector entry types, as found in `NT_AUXV` notes
+/// End of the vector
+pub const AT_NULL : u64 = 0;
+/// Address of the program headers of the executable
+pub const AT_PHDR : u64 = 3;
+/// Size of a program header table entry
+pub const AT_PHENT : u64 = 4;
+/// Number of program headers
+pub const AT_PHNUM : u64 = 5;
+/// System page size
+pub const AT_PAGESZ : u64 = 6;
+/// Load address of the program interpreter
+pub const AT_BASE : u64 = 7;
+/// Entry point of the executable
+pub const AT_ENTRY : u64 = 9;
+/// Address of 16 random bytes
+pub const AT_RANDOM : u64 = 25;
+/// Address of the file name of the executable
+pub const AT_EXECFN : u64 = 31;
+/// Load address of the vDSO
+pub const AT_SYSINFO_EHDR : u64 = 33;
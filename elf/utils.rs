@@ -1,13 +1,9 @@
 #[macro_export]
 macro_rules! read_u8 {
     ($elf:ident, $io:ident) => ({
-        use byteorder::{LittleEndian, BigEndian, ReadBytesExt};
-        match $elf.ehdr.data {
-            types::ELFDATA2LSB => { $io.read_u8::<LittleEndian>() }
-            types::ELFDATA2MSB => { $io.read_u8::<BigEndian>() }
-            types::ELFDATANONE => { panic!("Unable to resolve file endianness"); }
-            _ => { panic!("Unable to resolve file endianness"); }
-        }
+        use byteorder::ReadBytesExt;
+        // Single bytes need no byte order conversion
+        $io.read_u8()
     });
 }
 
@@ -50,9 +46,54 @@ macro_rules! read_u64 {
     });
 }
 
-use std;
-pub fn get_string(data: &Vec<u8>, start: usize) -> Result<String, std::string::FromUtf8Error> {
-    let mut end: usize = 0;
+#[macro_export]
+macro_rules! write_u16 {
+    ($elf:ident, $io:ident, $val:expr) => ({
+        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        match $elf.ehdr.data {
+            types::ELFDATA2LSB => { $io.write_u16::<LittleEndian>($val) }
+            types::ELFDATA2MSB => { $io.write_u16::<BigEndian>($val) }
+            types::ELFDATANONE => { panic!("Unable to resolve file endianness"); }
+            _ => { panic!("Unable to resolve file endianness"); }
+        }
+    });
+}
+
+#[macro_export]
+macro_rules! write_u32 {
+    ($elf:ident, $io:ident, $val:expr) => ({
+        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        match $elf.ehdr.data {
+            types::ELFDATA2LSB => { $io.write_u32::<LittleEndian>($val) }
+            types::ELFDATA2MSB => { $io.write_u32::<BigEndian>($val) }
+            types::ELFDATANONE => { panic!("Unable to resolve file endianness"); }
+            _ => { panic!("Unable to resolve file endianness"); }
+        }
+    });
+}
+
+#[macro_export]
+macro_rules! write_u64 {
+    ($elf:ident, $io:ident, $val:expr) => ({
+        use byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+        match $elf.ehdr.data {
+            types::ELFDATA2LSB => { $io.write_u64::<LittleEndian>($val) }
+            types::ELFDATA2MSB => { $io.write_u64::<BigEndian>($val) }
+            types::ELFDATANONE => { panic!("Unable to resolve file endianness"); }
+            _ => { panic!("Unable to resolve file endianness"); }
+        }
+    });
+}
+
+use super::ParseError;
+
+/// Reads the NUL-terminated string at `start` of a string table. An
+/// offset past the end of the table is an `InvalidFormat` error.
+pub fn get_string(data: &[u8], start: usize) -> Result<String, ParseError> {
+    if start > data.len() {
+        return Err(ParseError::InvalidFormat(None));
+    }
+    let mut end: usize = data.len();
     for i in start..data.len() {
         if data[i] == 0u8 {
             end = i;
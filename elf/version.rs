@@ -0,0 +1,206 @@
+//! Decoding of GNU symbol versioning information: the version symbol
+//! table (`.gnu.version`), version definitions (`.gnu.version_d`) and
+//! version needs (`.gnu.version_r`).
+
+use std::collections::HashMap;
+use std::io;
+
+use super::{File, ParseError, Section};
+use super::types;
+use super::utils;
+
+/// A version defined by the file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionDefinition {
+    /// Index referenced by `.gnu.version` entries
+    pub index: u16,
+    /// `VER_FLG_*` flags
+    pub flags: u16,
+    /// Version name
+    pub name: String,
+    /// Names of the versions this one inherits from
+    pub parents: Vec<String>,
+}
+
+/// A version of another object the file depends on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NeededVersion {
+    /// Index referenced by `.gnu.version` entries
+    pub index: u16,
+    /// `VER_FLG_*` flags
+    pub flags: u16,
+    /// Version name
+    pub name: String,
+}
+
+/// The versions needed from one dependency
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionNeed {
+    /// Name of the dependency, as in its `DT_NEEDED` entry
+    pub file: String,
+    pub versions: Vec<NeededVersion>,
+}
+
+impl File {
+    fn section_of_type(&self, shtype: types::SectionType) -> Option<&Section> {
+        self.sections.iter().find(|s| s.shdr.shtype == shtype)
+    }
+
+    fn linked_strtab(&self, section: &Section) -> Result<&Section, ParseError> {
+        match self.sections.get(section.shdr.link as usize) {
+            Some(s) => Ok(s),
+            None => Err(ParseError::InvalidFormat(None)),
+        }
+    }
+
+    /// Decodes the version definitions in `.gnu.version_d`.
+    pub fn version_definitions(&self) -> Result<Vec<VersionDefinition>, ParseError> {
+        let section = match self.section_of_type(types::SHT_GNU_VERDEF) {
+            Some(s) => s,
+            None => return Ok(Vec::new()),
+        };
+        let strtab = try!(self.linked_strtab(section));
+        let data = &section.data[..];
+
+        let mut defs = Vec::new();
+        let mut offset = 0u64;
+        loop {
+            // Elf_Verdef has the same layout in both ELF classes
+            if offset + 20 > data.len() as u64 {
+                return Err(ParseError::InvalidFormat(None));
+            }
+            let mut rdr = io::Cursor::new(data);
+            rdr.set_position(offset);
+            let _version = try!(read_u16!(self, rdr));
+            let flags = try!(read_u16!(self, rdr));
+            let index = try!(read_u16!(self, rdr));
+            let count = try!(read_u16!(self, rdr));
+            let _hash = try!(read_u32!(self, rdr));
+            let aux = try!(read_u32!(self, rdr)) as u64;
+            let next = try!(read_u32!(self, rdr)) as u64;
+
+            // The first Elf_Verdaux names the version, the rest its parents
+            let mut names = Vec::new();
+            let mut aux_offset = offset + aux;
+            for _ in 0..count {
+                rdr.set_position(aux_offset);
+                let name = try!(read_u32!(self, rdr));
+                let aux_next = try!(read_u32!(self, rdr)) as u64;
+                names.push(try!(utils::get_string(&strtab.data, name as usize)));
+                if aux_next == 0 { break; }
+                aux_offset += aux_next;
+            }
+            if names.is_empty() {
+                return Err(ParseError::InvalidFormat(None));
+            }
+            let name = names.remove(0);
+            defs.push(VersionDefinition { index: index, flags: flags, name: name, parents: names });
+
+            if next == 0 { break; }
+            offset += next;
+        }
+        Ok(defs)
+    }
+
+    /// Decodes the version needs in `.gnu.version_r`.
+    pub fn version_needs(&self) -> Result<Vec<VersionNeed>, ParseError> {
+        let section = match self.section_of_type(types::SHT_GNU_VERNEED) {
+            Some(s) => s,
+            None => return Ok(Vec::new()),
+        };
+        let strtab = try!(self.linked_strtab(section));
+        let data = &section.data[..];
+
+        let mut needs = Vec::new();
+        let mut offset = 0u64;
+        loop {
+            // Elf_Verneed has the same layout in both ELF classes
+            if offset + 16 > data.len() as u64 {
+                return Err(ParseError::InvalidFormat(None));
+            }
+            let mut rdr = io::Cursor::new(data);
+            rdr.set_position(offset);
+            let _version = try!(read_u16!(self, rdr));
+            let count = try!(read_u16!(self, rdr));
+            let file = try!(read_u32!(self, rdr));
+            let aux = try!(read_u32!(self, rdr)) as u64;
+            let next = try!(read_u32!(self, rdr)) as u64;
+
+            let mut versions = Vec::new();
+            let mut aux_offset = offset + aux;
+            for _ in 0..count {
+                rdr.set_position(aux_offset);
+                let _hash = try!(read_u32!(self, rdr));
+                let flags = try!(read_u16!(self, rdr));
+                let index = try!(read_u16!(self, rdr));
+                let name = try!(read_u32!(self, rdr));
+                let aux_next = try!(read_u32!(self, rdr)) as u64;
+                versions.push(NeededVersion {
+                    index: index,
+                    flags: flags,
+                    name:  try!(utils::get_string(&strtab.data, name as usize)),
+                });
+                if aux_next == 0 { break; }
+                aux_offset += aux_next;
+            }
+            needs.push(VersionNeed {
+                file:     try!(utils::get_string(&strtab.data, file as usize)),
+                versions: versions,
+            });
+
+            if next == 0 { break; }
+            offset += next;
+        }
+        Ok(needs)
+    }
+
+    /// Returns the versions of the symbols in the dynamic symbol table
+    /// `dynsym`, in symbol table order, or `None` if the file has no
+    /// `.gnu.version` section for it.
+    pub fn symbol_versions(&self, dynsym: &Section)
+                           -> Result<Option<Vec<Option<types::SymbolVersion>>>, ParseError> {
+        let versym = self.sections.iter()
+            .filter(|s| s.shdr.shtype == types::SHT_GNU_VERSYM)
+            .find(|s| match self.sections.get(s.shdr.link as usize) {
+                Some(linked) => linked.shdr == dynsym.shdr,
+                None => false,
+            });
+        let versym = match versym {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+
+        let mut names = HashMap::new();
+        for def in try!(self.version_definitions()) {
+            names.insert(def.index, (def.name, None));
+        }
+        for need in try!(self.version_needs()) {
+            for version in need.versions {
+                names.insert(version.index, (version.name, Some(need.file.clone())));
+            }
+        }
+
+        let mut versions = Vec::new();
+        let mut rdr = io::Cursor::new(&versym.data[..]);
+        loop {
+            if rdr.position() + 2 > versym.data.len() as u64 { break; }
+
+            let entry = try!(read_u16!(self, rdr));
+            let index = entry & !types::VERSYM_HIDDEN;
+            if index == types::VER_NDX_LOCAL || index == types::VER_NDX_GLOBAL {
+                versions.push(None);
+                continue;
+            }
+            let &(ref name, ref file) = match names.get(&index) {
+                Some(version) => version,
+                None => return Err(ParseError::InvalidFormat(None)),
+            };
+            versions.push(Some(types::SymbolVersion {
+                name:   name.clone(),
+                file:   file.clone(),
+                hidden: (entry & types::VERSYM_HIDDEN) != 0,
+            }));
+        }
+        Ok(Some(versions))
+    }
+}
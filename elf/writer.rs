@@ -0,0 +1,276 @@
+//! Serialization of an `elf::File` back into its on-disk representation.
+//!
+//! The writer places every table and section at the file offset recorded
+//! in its header, so a file that was parsed and not modified is written
+//! back byte-for-byte (assuming the gaps between sections were zero
+//! padding, as linkers emit them).
+
+use std::fs;
+use std::io;
+use std::io::{Seek, Write};
+use std::path::Path;
+use std;
+
+use super::File;
+use super::types;
+
+#[derive(Debug)]
+pub enum WriteError {
+    IoError(io::Error),
+    /// The file header does not specify a valid class or data encoding
+    InvalidHeader,
+    /// The section's data does not match the size in its header
+    InvalidSection(String),
+    /// No section with the given name exists
+    MissingSection(String),
+    /// The change would move or grow a section that is loaded at run time
+    AllocatedSection(String),
+}
+
+impl std::convert::From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::IoError(e)
+    }
+}
+
+fn align_up(x: u64, align: u64) -> u64 {
+    if align <= 1 { x } else { (x + align - 1) / align * align }
+}
+
+impl File {
+    /// End of everything in the file except the section header table
+    fn end_of_contents(&self) -> u64 {
+        let mut end = self.ehsize();
+        if !self.phdrs.is_empty() {
+            end = std::cmp::max(end, self.ehdr.phoff + self.phdrs.len() as u64 * self.phentsize());
+        }
+        for s in self.sections.iter() {
+            if s.shdr.shtype != types::SHT_NOBITS {
+                end = std::cmp::max(end, s.shdr.offset + s.shdr.size);
+            }
+        }
+        for (p, _) in self.phdrs.iter().zip(self.segments.iter()) {
+            end = std::cmp::max(end, p.offset + p.filesz);
+        }
+        end
+    }
+
+    /// Moves section `idx` to the end of the file and the section header
+    /// table after it.
+    fn move_to_end(&mut self, idx: usize) {
+        let end = self.end_of_contents();
+        let offset = align_up(end, self.sections[idx].shdr.addralign);
+        self.sections[idx].shdr.offset = offset;
+        self.move_section_headers_to_end();
+    }
+
+    fn move_section_headers_to_end(&mut self) {
+        let word = if self.ehdr.class == types::ELFCLASS32 { 4 } else { 8 };
+        self.ehdr.shoff = align_up(self.end_of_contents(), word);
+    }
+
+    /// Replaces the contents of the section named `name`.
+    ///
+    /// Sections occupying memory at run time (`SHF_ALLOC`) must keep their
+    /// file offset and address, so their new contents may not be larger
+    /// than the old ones. Other sections are moved to the end of the file
+    /// when they grow.
+    pub fn replace_section<T: AsRef<str>>(&mut self, name: T, data: Vec<u8>) -> Result<(), WriteError> {
+        let name = name.as_ref();
+        let idx = match self.sections.iter().position(|s| s.shdr.name == name) {
+            Some(idx) => idx,
+            None => return Err(WriteError::MissingSection(name.to_string())),
+        };
+        let old_size = self.sections[idx].shdr.size;
+        let new_size = data.len() as u64;
+        let alloc = (self.sections[idx].shdr.flags.0 & types::SHF_ALLOC.0) != 0;
+        if alloc && new_size > old_size {
+            return Err(WriteError::AllocatedSection(name.to_string()));
+        }
+
+        self.sections[idx].shdr.size = new_size;
+        self.sections[idx].data = data;
+        if new_size > old_size {
+            self.move_to_end(idx);
+        }
+        Ok(())
+    }
+
+    /// Appends a new section holding `data` and returns its index.
+    ///
+    /// The section is placed at the end of the file and is not part of any
+    /// segment, so it may not have the `SHF_ALLOC` flag.
+    pub fn add_section(&mut self,
+                       name: &str,
+                       shtype: types::SectionType,
+                       flags: types::SectionFlag,
+                       addralign: u64,
+                       data: Vec<u8>) -> Result<usize, WriteError> {
+        if (flags.0 & types::SHF_ALLOC.0) != 0 {
+            return Err(WriteError::AllocatedSection(name.to_string()));
+        }
+
+        // Record the name in the section name string table
+        let shstrndx = self.ehdr.shstrndx as usize;
+        if shstrndx == 0 || shstrndx >= self.sections.len() {
+            return Err(WriteError::InvalidHeader);
+        }
+        let name_idx = self.sections[shstrndx].data.len() as u32;
+        let mut names = self.sections[shstrndx].data.clone();
+        names.extend(name.as_bytes());
+        names.push(0);
+        let shstrtab_name = self.sections[shstrndx].shdr.name.clone();
+        try!(self.replace_section(shstrtab_name, names));
+
+        self.sections.push(super::Section {
+            shdr: types::SectionHeader {
+                name:      name.to_string(),
+                name_idx:  name_idx,
+                shtype:    shtype,
+                flags:     flags,
+                addr:      0,
+                offset:    0,
+                size:      data.len() as u64,
+                link:      0,
+                info:      0,
+                addralign: addralign,
+                entsize:   0,
+            },
+            data: data,
+        });
+        let idx = self.sections.len() - 1;
+        self.move_to_end(idx);
+        Ok(idx)
+    }
+
+    /// Serializes the file into an in-memory image.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        if self.ehdr.data != types::ELFDATA2LSB && self.ehdr.data != types::ELFDATA2MSB {
+            return Err(WriteError::InvalidHeader);
+        }
+        if self.ehdr.class != types::ELFCLASS32 && self.ehdr.class != types::ELFCLASS64 {
+            return Err(WriteError::InvalidHeader);
+        }
+        for s in self.sections.iter() {
+            if s.shdr.shtype != types::SHT_NOBITS && s.data.len() as u64 != s.shdr.size {
+                return Err(WriteError::InvalidSection(s.shdr.name.clone()));
+            }
+        }
+
+        let mut size = self.end_of_contents();
+        if !self.sections.is_empty() {
+            size = std::cmp::max(size, self.ehdr.shoff + self.sections.len() as u64 * self.shentsize());
+        }
+        let mut io_file = io::Cursor::new(vec![0u8; size as usize]);
+
+        // Write the file header
+        let mut ident = [0u8; types::EI_NIDENT];
+        ident[0] = types::ELFMAG0;
+        ident[1] = types::ELFMAG1;
+        ident[2] = types::ELFMAG2;
+        ident[3] = types::ELFMAG3;
+        ident[types::EI_CLASS] = self.ehdr.class.0;
+        ident[types::EI_DATA] = self.ehdr.data.0;
+        ident[types::EI_VERSION] = self.ehdr.version.0 as u8;
+        ident[types::EI_OSABI] = self.ehdr.osabi.0;
+        ident[types::EI_ABIVERSION] = self.ehdr.abiversion;
+        try!(io_file.write_all(&ident));
+        try!(write_u16!(self, io_file, self.ehdr.elftype.0));
+        try!(write_u16!(self, io_file, self.ehdr.machine.0));
+        try!(write_u32!(self, io_file, self.ehdr.version.0));
+        let phoff = if self.phdrs.is_empty() { 0 } else { self.ehdr.phoff };
+        let shoff = if self.sections.is_empty() { 0 } else { self.ehdr.shoff };
+        if self.ehdr.class == types::ELFCLASS32 {
+            try!(write_u32!(self, io_file, self.ehdr.entry as u32));
+            try!(write_u32!(self, io_file, phoff as u32));
+            try!(write_u32!(self, io_file, shoff as u32));
+        } else {
+            try!(write_u64!(self, io_file, self.ehdr.entry));
+            try!(write_u64!(self, io_file, phoff));
+            try!(write_u64!(self, io_file, shoff));
+        }
+        try!(write_u32!(self, io_file, self.ehdr.flags));
+        try!(write_u16!(self, io_file, self.ehsize() as u16));
+        try!(write_u16!(self, io_file, self.phentsize() as u16));
+        try!(write_u16!(self, io_file, self.phdrs.len() as u16));
+        try!(write_u16!(self, io_file, self.shentsize() as u16));
+        try!(write_u16!(self, io_file, self.sections.len() as u16));
+        try!(write_u16!(self, io_file, self.ehdr.shstrndx));
+
+        // Write the program headers
+        try!(io_file.seek(io::SeekFrom::Start(phoff)));
+        for phdr in self.phdrs.iter() {
+            try!(write_u32!(self, io_file, phdr.progtype.0));
+            if self.ehdr.class == types::ELFCLASS32 {
+                try!(write_u32!(self, io_file, phdr.offset as u32));
+                try!(write_u32!(self, io_file, phdr.vaddr as u32));
+                try!(write_u32!(self, io_file, phdr.paddr as u32));
+                try!(write_u32!(self, io_file, phdr.filesz as u32));
+                try!(write_u32!(self, io_file, phdr.memsz as u32));
+                try!(write_u32!(self, io_file, phdr.flags.0));
+                try!(write_u32!(self, io_file, phdr.align as u32));
+            } else {
+                try!(write_u32!(self, io_file, phdr.flags.0));
+                try!(write_u64!(self, io_file, phdr.offset));
+                try!(write_u64!(self, io_file, phdr.vaddr));
+                try!(write_u64!(self, io_file, phdr.paddr));
+                try!(write_u64!(self, io_file, phdr.filesz));
+                try!(write_u64!(self, io_file, phdr.memsz));
+                try!(write_u64!(self, io_file, phdr.align));
+            }
+        }
+
+        // Write the segment data kept for files without sections
+        for (p, data) in self.phdrs.iter().zip(self.segments.iter()) {
+            try!(io_file.seek(io::SeekFrom::Start(p.offset)));
+            try!(io_file.write_all(data));
+        }
+
+        // Write the section data
+        for s in self.sections.iter() {
+            if s.shdr.shtype == types::SHT_NOBITS || s.data.is_empty() { continue; }
+            try!(io_file.seek(io::SeekFrom::Start(s.shdr.offset)));
+            try!(io_file.write_all(&s.data));
+        }
+
+        // Write the section headers
+        try!(io_file.seek(io::SeekFrom::Start(shoff)));
+        for s in self.sections.iter() {
+            let shdr = &s.shdr;
+            try!(write_u32!(self, io_file, shdr.name_idx));
+            try!(write_u32!(self, io_file, shdr.shtype.0));
+            if self.ehdr.class == types::ELFCLASS32 {
+                try!(write_u32!(self, io_file, shdr.flags.0 as u32));
+                try!(write_u32!(self, io_file, shdr.addr as u32));
+                try!(write_u32!(self, io_file, shdr.offset as u32));
+                try!(write_u32!(self, io_file, shdr.size as u32));
+                try!(write_u32!(self, io_file, shdr.link));
+                try!(write_u32!(self, io_file, shdr.info));
+                try!(write_u32!(self, io_file, shdr.addralign as u32));
+                try!(write_u32!(self, io_file, shdr.entsize as u32));
+            } else {
+                try!(write_u64!(self, io_file, shdr.flags.0));
+                try!(write_u64!(self, io_file, shdr.addr));
+                try!(write_u64!(self, io_file, shdr.offset));
+                try!(write_u64!(self, io_file, shdr.size));
+                try!(write_u32!(self, io_file, shdr.link));
+                try!(write_u32!(self, io_file, shdr.info));
+                try!(write_u64!(self, io_file, shdr.addralign));
+                try!(write_u64!(self, io_file, shdr.entsize));
+            }
+        }
+
+        Ok(io_file.into_inner())
+    }
+
+    pub fn write_stream<T: io::Write>(&self, io_file: &mut T) -> Result<(), WriteError> {
+        let bytes = try!(self.to_bytes());
+        try!(io_file.write_all(&bytes));
+        Ok(())
+    }
+
+    pub fn write_path<T: AsRef<Path>>(&self, path: T) -> Result<(), WriteError> {
+        let mut io_file = try!(fs::File::create(path));
+        self.write_stream(&mut io_file)
+    }
+}
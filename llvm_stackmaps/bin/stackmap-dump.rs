@@ -0,0 +1,666 @@
+extern crate util;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::process;
+
+use util::byteorder::{BigEndian, LittleEndian};
+use util::elf;
+use util::elf::types;
+use util::llvm_stackmaps::{Location, LocationVariant, Record, StackMap};
+use util::unravel::registers::Arch;
+
+#[derive(Default)]
+struct Options {
+    json: bool,
+    merge: bool,
+    diff: bool,
+    paths: Vec<String>,
+}
+
+const USAGE: &'static str = "\
+Usage: stackmap-dump <option(s)> elf-file(s)
+ Display the LLVM stack maps (.llvm_stackmaps) of ELF format files
+ Options are:
+  -j --json              Print each file's stack map as a line of JSON
+  -m --merge             Merge the stack maps of all the files into one
+  -d --diff              Show how the records of each function changed
+                         between the stack maps of two files
+     --help              Display this information";
+
+fn parse_args<I: Iterator<Item=String>>(args: I) -> Result<Options, String> {
+    let mut opts = Options::default();
+    for arg in args {
+        match &arg[..] {
+            "-j" | "--json" => opts.json = true,
+            "-m" | "--merge" => opts.merge = true,
+            "-d" | "--diff" => opts.diff = true,
+            "--help" => return Err(String::new()),
+            _ if arg.starts_with("-") && arg.len() > 1 => {
+                return Err(format!("unrecognized option '{}'", arg));
+            }
+            _ => opts.paths.push(arg),
+        }
+    }
+    if opts.paths.is_empty() {
+        return Err(String::new());
+    }
+    if opts.merge && opts.diff {
+        return Err("--merge and --diff cannot be combined".to_string());
+    }
+    if opts.diff && opts.paths.len() != 2 {
+        return Err("--diff needs two files".to_string());
+    }
+    Ok(opts)
+}
+
+/// A stack map and what the file it came from tells about it
+struct Dump {
+    map: StackMap,
+    machine: types::Machine,
+    /// Whether function addresses are yet to be relocated
+    relocatable: bool,
+    /// Name of each function, by stack size record
+    function_names: Vec<Option<String>>,
+}
+
+fn invalid_data<E: fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+fn parse_error(e: elf::ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))
+}
+
+/// Names functions by the symbol at or around their address.
+fn names_by_address(file: &elf::File, map: &StackMap) -> io::Result<Vec<Option<String>>> {
+    let mut functions = BTreeMap::new();
+    let mut symbols = file.symbols().map_err(parse_error)?;
+    symbols.extend(file.dynamic_symbols().map_err(parse_error)?);
+    for sym in symbols.into_iter() {
+        if sym.sym_type() == types::STT_FUNC && sym.shndx != types::SHN_UNDEF {
+            functions.entry(sym.value).or_insert((sym.name, sym.size));
+        }
+    }
+    Ok(map.stack_sizes().iter().map(|ss| {
+        let address = ss.function_address();
+        if let Some(&(ref name, _)) = functions.get(&address) {
+            return Some(name.clone());
+        }
+        match functions.range(..address).next_back() {
+            Some((&start, &(ref name, size))) if address - start < size => {
+                Some(format!("{}+{:#x}", name, address - start))
+            }
+            _ => None,
+        }
+    }).collect())
+}
+
+/// Names functions by the relocations filling in their addresses, as
+/// the addresses of a relocatable file are not final.
+fn names_by_relocation(file: &elf::File, section_index: usize, map: &StackMap)
+                       -> io::Result<Vec<Option<String>>> {
+    let mut names = vec![None; map.stack_sizes().len()];
+    let record_size = if map.header().version() == 1 { 16 } else { 24 };
+    for section in file.sections.iter() {
+        let shtype = section.shdr.shtype;
+        if (shtype != types::SHT_REL && shtype != types::SHT_RELA)
+            || section.shdr.info as usize != section_index {
+            continue;
+        }
+        let symbols = match file.sections.get(section.shdr.link as usize) {
+            Some(symtab) => file.get_symbols(symtab).map_err(parse_error)?,
+            None => continue,
+        };
+        for reloc in file.get_relocations(section).map_err(parse_error)? {
+            if reloc.offset < 16 || (reloc.offset - 16) % record_size != 0 {
+                continue;
+            }
+            let i = ((reloc.offset - 16) / record_size) as usize;
+            if let (Some(name), Some(sym)) = (names.get_mut(i), symbols.get(reloc.sym as usize)) {
+                *name = Some(match reloc.addend.unwrap_or(0) {
+                    0 => sym.name.clone(),
+                    addend if addend < 0 => format!("{}-{:#x}", sym.name, -addend),
+                    addend => format!("{}+{:#x}", sym.name, addend),
+                });
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn read_dump(file: &elf::File) -> io::Result<Dump> {
+    let section_index = match file.sections.iter().position(|s| s.shdr.name == ".llvm_stackmaps") {
+        Some(i) => i,
+        None => return Err(invalid_data("no .llvm_stackmaps section")),
+    };
+    let data = &file.sections[section_index].data[..];
+    let map = if file.ehdr.data == types::ELFDATA2MSB {
+        StackMap::read_from::<BigEndian>(data)
+    } else {
+        StackMap::read_from::<LittleEndian>(data)
+    }.map_err(invalid_data)?;
+    let relocatable = file.ehdr.elftype == types::ET_REL;
+    let function_names = if relocatable {
+        names_by_relocation(file, section_index, &map)?
+    } else {
+        names_by_address(file, &map)?
+    };
+    Ok(Dump {
+        map: map,
+        machine: file.ehdr.machine,
+        relocatable: relocatable,
+        function_names: function_names,
+    })
+}
+
+/// Name of the register with DWARF number `regnum`.
+fn register_name(machine: types::Machine, regnum: u16) -> String {
+    match Arch::from_elf_machine(machine.0).and_then(|arch| arch.register_name(regnum)) {
+        Some(name) => name.to_string(),
+        None => format!("reg{}", regnum),
+    }
+}
+
+/// Merges the stack maps of several files, keeping the names of their
+/// functions.
+fn merge_dumps(dumps: Vec<Dump>) -> io::Result<Dump> {
+    let machine = dumps[0].machine;
+    let relocatable = dumps.iter().any(|d| d.relocatable);
+    let mut maps = Vec::with_capacity(dumps.len());
+    let mut function_names = Vec::new();
+    for d in dumps.into_iter() {
+        if d.machine != machine {
+            return Err(invalid_data("files are for different machines"));
+        }
+        function_names.extend(d.function_names);
+        maps.push(d.map);
+    }
+    let map = StackMap::merge(&maps).map_err(invalid_data)?;
+    Ok(Dump { map: map, machine: machine, relocatable: relocatable, function_names: function_names })
+}
+
+impl Dump {
+    fn register(&self, regnum: u16) -> String {
+        register_name(self.machine, regnum)
+    }
+
+    /// The function each record belongs to, by index in `stack_sizes`,
+    /// for maps that say so.
+    fn record_functions(&self) -> Vec<Option<usize>> {
+        let mut owners = vec![None; self.map.records().len()];
+        let mut start = 0;
+        if let Some(functions) = self.map.functions() {
+            for (i, f) in functions.iter().enumerate() {
+                for owner in owners[start..start + f.records().len()].iter_mut() {
+                    *owner = Some(i);
+                }
+                start += f.records().len();
+            }
+        }
+        owners
+    }
+
+    /// Address of the call site of a record of function `f`, if final and
+    /// within the address space
+    fn site_address(&self, f: usize, r: &Record) -> Option<u64> {
+        if self.relocatable {
+            return None;
+        }
+        self.map.stack_sizes()[f].function_address().checked_add(r.instruction_offset() as u64)
+    }
+
+    fn function_name(&self, i: usize) -> Option<&str> {
+        self.function_names.get(i).and_then(|n| n.as_ref()).map(|n| &n[..])
+    }
+
+    /// Key matching a function across builds: its name, or its address
+    /// if it has none.
+    fn function_keys(&self) -> Vec<String> {
+        self.map.stack_sizes().iter().enumerate().map(|(i, ss)| match self.function_name(i) {
+            Some(name) => name.to_string(),
+            None => format!("{:#x}", ss.function_address()),
+        }).collect()
+    }
+
+    /// Renders a record as e.g. `ID 1 at +0x8: rax, const 7; live-outs: rbx`.
+    fn record_summary(&self, r: &Record) -> String {
+        let locations: Vec<String> = r.locations().iter().map(|l| self.location(l)).collect();
+        let mut summary = format!("ID {} at +{:#x}: {}", r.patchpoint_id(), r.instruction_offset(),
+                                  locations.join(", "));
+        if !r.live_outs().is_empty() {
+            let live_outs: Vec<String> = r.live_outs().iter()
+                .map(|lo| self.register(lo.regnum())).collect();
+            summary.push_str(&format!("; live-outs: {}", live_outs.join(", ")));
+        }
+        summary
+    }
+
+    /// Renders a location as e.g. `rax`, `rbp-16`, `[rbp-16]` or `const 42`.
+    fn location(&self, l: &Location) -> String {
+        let based = |regnum, offset: i32| if offset == 0 {
+            self.register(regnum)
+        } else {
+            format!("{}{:+}", self.register(regnum), offset)
+        };
+        match *l.variant() {
+            LocationVariant::Register { dwarf_regnum } => self.register(dwarf_regnum),
+            LocationVariant::Direct { dwarf_regnum, offset } => based(dwarf_regnum, offset),
+            LocationVariant::Indirect { dwarf_regnum, offset } => {
+                format!("[{}]", based(dwarf_regnum, offset))
+            }
+            LocationVariant::Constant { value } => format!("const {}", value),
+            LocationVariant::ConstIndex { offset } => {
+                match self.map.large_constants().get(offset as usize) {
+                    Some(c) if offset >= 0 => format!("const {}", c.value),
+                    _ => format!("const #{} (missing)", offset),
+                }
+            }
+        }
+    }
+}
+
+fn print_text(w: &mut Write, dump: &Dump) -> io::Result<()> {
+    let map = &dump.map;
+    writeln!(w, "Stack map version {}", map.header().version())?;
+
+    writeln!(w, "\nFunctions ({}):", map.stack_sizes().len())?;
+    writeln!(w, "  Num: Address            StackSize Records Name")?;
+    for (i, ss) in map.stack_sizes().iter().enumerate() {
+        let count = match ss.record_count() {
+            Some(count) => count.to_string(),
+            None => "-".to_string(),
+        };
+        writeln!(w, "{:5}: 0x{:016x} {:9} {:>7} {}", i, ss.function_address(),
+                 ss.stack_size(), count, dump.function_name(i).unwrap_or("?"))?;
+    }
+
+    writeln!(w, "\nConstants ({}):", map.large_constants().len())?;
+    for (i, c) in map.large_constants().iter().enumerate() {
+        writeln!(w, "{:5}: {:#x} ({})", i, c.value, c.value)?;
+    }
+
+    writeln!(w, "\nRecords ({}):", map.records().len())?;
+    let owners = dump.record_functions();
+    for (i, r) in map.records().iter().enumerate() {
+        let site = match owners[i] {
+            Some(f) => {
+                let name = match dump.function_name(f) {
+                    Some(name) => format!("{}+{:#x}", name, r.instruction_offset()),
+                    None => format!("function {}+{:#x}", f, r.instruction_offset()),
+                };
+                match dump.site_address(f, r) {
+                    Some(address) => format!("{} ({:#x})", name, address),
+                    None => name,
+                }
+            }
+            None => format!("offset {:#x}", r.instruction_offset()),
+        };
+        writeln!(w, "{:5}: ID {} at {}", i, r.patchpoint_id(), site)?;
+        for (j, l) in r.locations().iter().enumerate() {
+            writeln!(w, "         location {}: {} ({} bytes)", j, dump.location(l), l.size())?;
+        }
+        for lo in r.live_outs().iter() {
+            writeln!(w, "         live-out: {} ({} bytes)",
+                     dump.register(lo.regnum()), lo.size_in_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// A JSON value, just enough for the dump's output
+enum Json {
+    Null,
+    Int(i64),
+    UInt(u64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+fn write_json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Int(n) => write!(f, "{}", n),
+            Json::UInt(n) => write!(f, "{}", n),
+            Json::Str(ref s) => write_json_string(f, s),
+            Json::Array(ref items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, &(key, ref value)) in fields.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write_json_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn json_name(name: Option<&str>) -> Json {
+    match name {
+        Some(name) => Json::Str(name.to_string()),
+        None => Json::Null,
+    }
+}
+
+fn json_location(dump: &Dump, l: &Location) -> Json {
+    let register = |regnum| vec![("register", Json::Str(dump.register(regnum))),
+                                 ("dwarf_regnum", Json::UInt(regnum as u64))];
+    let mut fields = match *l.variant() {
+        LocationVariant::Register { dwarf_regnum } => {
+            let mut fields = vec![("kind", Json::Str("register".to_string()))];
+            fields.extend(register(dwarf_regnum));
+            fields
+        }
+        LocationVariant::Direct { dwarf_regnum, offset } |
+        LocationVariant::Indirect { dwarf_regnum, offset } => {
+            let kind = match *l.variant() {
+                LocationVariant::Direct { .. } => "direct",
+                _ => "indirect",
+            };
+            let mut fields = vec![("kind", Json::Str(kind.to_string()))];
+            fields.extend(register(dwarf_regnum));
+            fields.push(("offset", Json::Int(offset as i64)));
+            fields
+        }
+        LocationVariant::Constant { value } => {
+            vec![("kind", Json::Str("constant".to_string())), ("value", Json::Int(value as i64))]
+        }
+        LocationVariant::ConstIndex { offset } => {
+            let value = match dump.map.large_constants().get(offset as usize) {
+                Some(c) if offset >= 0 => Json::UInt(c.value),
+                _ => Json::Null,
+            };
+            vec![("kind", Json::Str("constant_index".to_string())),
+                 ("index", Json::Int(offset as i64)),
+                 ("value", value)]
+        }
+    };
+    fields.push(("size", Json::UInt(l.size() as u64)));
+    fields.push(("text", Json::Str(dump.location(l))));
+    Json::Object(fields)
+}
+
+/// The fields of a record that do not depend on its function
+fn json_record_fields(dump: &Dump, r: &Record) -> Vec<(&'static str, Json)> {
+    vec![
+        ("id", Json::UInt(r.patchpoint_id())),
+        ("instruction_offset", Json::UInt(r.instruction_offset() as u64)),
+        ("locations", Json::Array(r.locations().iter().map(|l| json_location(dump, l)).collect())),
+        ("live_outs", Json::Array(r.live_outs().iter().map(|lo| Json::Object(vec![
+            ("register", Json::Str(dump.register(lo.regnum()))),
+            ("dwarf_regnum", Json::UInt(lo.regnum() as u64)),
+            ("size", Json::UInt(lo.size_in_bytes() as u64)),
+        ])).collect())),
+    ]
+}
+
+fn to_json(dump: &Dump, file: Json) -> Json {
+    let map = &dump.map;
+    let functions = map.stack_sizes().iter().enumerate().map(|(i, ss)| Json::Object(vec![
+        ("address", Json::UInt(ss.function_address())),
+        ("name", json_name(dump.function_name(i))),
+        ("stack_size", Json::UInt(ss.stack_size())),
+        ("record_count", match ss.record_count() {
+            Some(count) => Json::UInt(count),
+            None => Json::Null,
+        }),
+    ])).collect();
+    let constants = map.large_constants().iter().map(|c| Json::UInt(c.value)).collect();
+    let owners = dump.record_functions();
+    let records = map.records().iter().enumerate().map(|(i, r)| {
+        let (function, address) = match owners[i] {
+            Some(f) => (json_name(dump.function_name(f)),
+                        dump.site_address(f, r).map(Json::UInt).unwrap_or(Json::Null)),
+            None => (Json::Null, Json::Null),
+        };
+        let mut fields = json_record_fields(dump, r);
+        fields.insert(1, ("function", function));
+        fields.insert(3, ("address", address));
+        Json::Object(fields)
+    }).collect();
+    Json::Object(vec![
+        ("file", file),
+        ("machine", Json::Str(dump.machine.to_string())),
+        ("version", Json::UInt(map.header().version() as u64)),
+        ("functions", Json::Array(functions)),
+        ("constants", Json::Array(constants)),
+        ("records", Json::Array(records)),
+    ])
+}
+
+fn print_dump(w: &mut Write, dump: &Dump, file: Json, opts: &Options) -> io::Result<()> {
+    if opts.json {
+        writeln!(w, "{}", to_json(dump, file))
+    } else {
+        print_text(w, dump)
+    }
+}
+
+fn dump(w: &mut Write, file: &elf::File, path: &str, opts: &Options) -> io::Result<()> {
+    let dump = read_dump(file)?;
+    print_dump(w, &dump, Json::Str(path.to_string()), opts)
+}
+
+fn dump_merged(w: &mut Write, files: &[(String, elf::File)], opts: &Options) -> io::Result<()> {
+    let mut dumps = Vec::with_capacity(files.len());
+    for &(ref path, ref file) in files.iter() {
+        dumps.push(read_dump(file).map_err(|e| invalid_data(format!("{}: {}", path, e)))?);
+    }
+    let merged = merge_dumps(dumps)?;
+    let paths = files.iter().map(|&(ref path, _)| Json::Str(path.clone())).collect();
+    print_dump(w, &merged, Json::Array(paths), opts)
+}
+
+fn dump_diff(w: &mut Write, old: (&str, &elf::File), new: (&str, &elf::File), opts: &Options)
+             -> io::Result<()> {
+    let old_dump = read_dump(old.1).map_err(|e| invalid_data(format!("{}: {}", old.0, e)))?;
+    let new_dump = read_dump(new.1).map_err(|e| invalid_data(format!("{}: {}", new.0, e)))?;
+    let diffs = old_dump.map.diff_by_key(&old_dump.function_keys(),
+                                         &new_dump.map, &new_dump.function_keys())
+        .map_err(invalid_data)?;
+
+    if opts.json {
+        let record = |dump: &Dump, r: &Record| Json::Object(json_record_fields(dump, r));
+        let functions = diffs.iter().map(|d| Json::Object(vec![
+            ("function", Json::Str(d.function.clone())),
+            ("added", Json::Array(d.added.iter().map(|r| record(&new_dump, r)).collect())),
+            ("removed", Json::Array(d.removed.iter().map(|r| record(&old_dump, r)).collect())),
+            ("changed", Json::Array(d.changed.iter().map(|&(o, n)| Json::Object(vec![
+                ("old", record(&old_dump, o)),
+                ("new", record(&new_dump, n)),
+            ])).collect())),
+        ])).collect();
+        return writeln!(w, "{}", Json::Object(vec![
+            ("old", Json::Str(old.0.to_string())),
+            ("new", Json::Str(new.0.to_string())),
+            ("functions", Json::Array(functions)),
+        ]));
+    }
+
+    writeln!(w, "--- {}\n+++ {}", old.0, new.0)?;
+    if diffs.is_empty() {
+        return writeln!(w, "\nNo differences.");
+    }
+    for d in diffs.iter() {
+        writeln!(w, "\nFunction {}:", d.function)?;
+        for r in d.removed.iter() {
+            writeln!(w, "  removed: {}", old_dump.record_summary(r))?;
+        }
+        for r in d.added.iter() {
+            writeln!(w, "    added: {}", new_dump.record_summary(r))?;
+        }
+        for &(o, n) in d.changed.iter() {
+            writeln!(w, "  changed: {}", old_dump.record_summary(o))?;
+            writeln!(w, "       to: {}", new_dump.record_summary(n))?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            if !msg.is_empty() {
+                let _ = writeln!(io::stderr(), "stackmap-dump: {}", msg);
+            }
+            let _ = writeln!(io::stderr(), "{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut status = 0;
+    let mut files = Vec::new();
+    for path in opts.paths.iter() {
+        match elf::File::open_path(path) {
+            Ok(f) => files.push((path.clone(), f)),
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "stackmap-dump: Error: {}: {:?}", path, e);
+                status = 1;
+            }
+        }
+    }
+
+    if opts.merge || opts.diff {
+        let result = if status != 0 {
+            Ok(())
+        } else if opts.merge {
+            dump_merged(&mut out, &files, &opts)
+        } else {
+            dump_diff(&mut out, (&files[0].0, &files[0].1), (&files[1].0, &files[1].1), &opts)
+        };
+        if let Err(e) = result {
+            let _ = writeln!(io::stderr(), "stackmap-dump: Error: {}", e);
+            status = 1;
+        }
+        process::exit(status);
+    }
+
+    for &(ref path, ref file) in files.iter() {
+        if opts.paths.len() > 1 && !opts.json {
+            let _ = writeln!(out, "\nFile: {}", path);
+        }
+        if let Err(e) = dump(&mut out, file, path, &opts) {
+            let _ = writeln!(io::stderr(), "stackmap-dump: Error: {}: {}", path, e);
+            status = 1;
+        }
+    }
+    process::exit(status);
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::{dump, dump_diff, dump_merged, names_by_address, parse_args, Dump, Options};
+    use util::elf;
+    use util::llvm_stackmaps::StackMapBuilder;
+
+    fn golden(fixtures: &[&[u8]], args: &str, expected: &str) {
+        let mut argv: Vec<String> = args.split(' ').filter(|s| !s.is_empty())
+            .map(|s| s.to_string()).collect();
+        let mut files = Vec::new();
+        for (i, fixture) in fixtures.iter().enumerate() {
+            let path = format!("fixture{}", i);
+            argv.push(path.clone());
+            files.push((path, elf::File::open_stream(&mut io::Cursor::new(fixture)).unwrap()));
+        }
+        let opts: Options = parse_args(argv.into_iter()).ok().unwrap();
+        let mut out = Vec::new();
+        if opts.merge {
+            dump_merged(&mut out, &files, &opts).unwrap();
+        } else if opts.diff {
+            dump_diff(&mut out, (&files[0].0, &files[0].1), (&files[1].0, &files[1].1), &opts)
+                .unwrap();
+        } else {
+            dump(&mut out, &files[0].1, &files[0].0, &opts).unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        if out != expected {
+            panic!("output of `stackmap-dump {}` differs from golden file:\n{}", args, out);
+        }
+    }
+
+    #[test]
+    fn text() {
+        golden(&[include_bytes!("../test/stackmaps")], "",
+               include_str!("../test/dump/stackmaps.txt"));
+    }
+
+    #[test]
+    fn relocatable() {
+        golden(&[include_bytes!("../test/stackmaps.o")], "",
+               include_str!("../test/dump/stackmaps.o.txt"));
+    }
+
+    #[test]
+    fn json() {
+        golden(&[include_bytes!("../test/stackmaps")], "--json",
+               include_str!("../test/dump/stackmaps.json"));
+    }
+
+    #[test]
+    fn merge() {
+        golden(&[include_bytes!("../test/stackmaps.o"), include_bytes!("../test/stackmaps-next.o")],
+               "--merge", include_str!("../test/dump/merge.txt"));
+    }
+
+    #[test]
+    fn diff() {
+        let fixtures: [&[u8]; 2] = [include_bytes!("../test/stackmaps.o"),
+                                    include_bytes!("../test/stackmaps-next.o")];
+        golden(&fixtures, "--diff", include_str!("../test/dump/diff.txt"));
+        golden(&fixtures, "--diff --json", include_str!("../test/dump/diff.json"));
+        // Functions are matched by name, not by their unrelocated address
+        golden(&[include_bytes!("../test/stackmaps"), include_bytes!("../test/stackmaps.o")],
+               "--diff", "--- fixture0\n+++ fixture1\n\nNo differences.\n");
+    }
+
+    #[test]
+    fn addresses_at_end_of_address_space() {
+        let file = elf::File::open_stream(&mut io::Cursor::new(&include_bytes!("../test/stackmaps")[..]))
+            .unwrap();
+        let mut b = StackMapBuilder::new(3);
+        b.function(u64::max_value(), 8).record(1, 8, vec![], vec![]).unwrap();
+        let map = b.build().unwrap();
+        assert_eq!(names_by_address(&file, &map).unwrap(), vec![None]);
+
+        let dump = Dump {
+            map: map,
+            machine: file.ehdr.machine,
+            relocatable: false,
+            function_names: vec![None],
+        };
+        assert_eq!(dump.site_address(0, &dump.map.records()[0]), None);
+    }
+}
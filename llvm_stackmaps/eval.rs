@@ -0,0 +1,116 @@
+//! Evaluation of stack map locations against the state of a frame.
+//!
+//! The frame's registers and memory are supplied through the
+//! `RegisterProvider` and `MemoryReader` traits, so the same evaluation
+//! serves a live stack walk with libunwind, a walk with unravel, or a
+//! post-mortem walk over a core file.
+
+use super::{Location, LocationVariant, StackMap};
+use super::errors::EvaluateError;
+
+/// Register values of the frame a record applies to.
+pub trait RegisterProvider {
+    /// Returns the value of the register with the given DWARF number, or
+    /// `None` if it is not known for the frame.
+    fn register(&self, dwarf_regnum: u16) -> Option<u64>;
+}
+
+impl RegisterProvider for ::unravel::Cursor {
+    fn register(&self, dwarf_regnum: u16) -> Option<u64> {
+        ::unravel::Cursor::register(self, dwarf_regnum)
+    }
+}
+
+/// Memory of the process a record applies to.
+pub trait MemoryReader {
+    /// Reads the `size`-byte integer (1, 2, 4 or 8 bytes) stored at
+    /// `address` in the byte order of the process, zero-extended, or
+    /// returns `None` if the memory cannot be read.
+    fn read(&self, address: u64, size: usize) -> Option<u64>;
+}
+
+/// The result of evaluating a `Location`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LocationValue {
+    /// The value itself, from a register or a constant
+    Value(u64),
+    /// The address of a stack object (a `Direct` location, e.g. an
+    /// `alloca`); the object itself is not read
+    StackSlot(u64),
+    /// A value spilled to the stack slot at `address` (an `Indirect`
+    /// location); updating the slot updates the value
+    Spilled { address: u64, value: u64 },
+}
+
+impl LocationValue {
+    /// The value of the location: the address of a `StackSlot`, or the
+    /// contents of a `Spilled` slot.
+    pub fn value(&self) -> u64 {
+        match *self {
+            LocationValue::Value(v) | LocationValue::StackSlot(v) => v,
+            LocationValue::Spilled { value, .. } => value,
+        }
+    }
+}
+
+/// Truncates `value` to the low `size` bytes.
+fn truncate(value: u64, size: usize) -> u64 {
+    if size >= 8 { value } else { value & ((1 << (size * 8)) - 1) }
+}
+
+impl Location {
+    /// Size in bytes the location is evaluated with. Maps that leave the
+    /// size unset describe word-sized values.
+    fn value_size(&self) -> Result<usize, EvaluateError> {
+        match self.size {
+            0 => Ok(8),
+            1 | 2 | 4 | 8 => Ok(self.size as usize),
+            other => Err(EvaluateError::UnsupportedSize(other)),
+        }
+    }
+
+    /// Evaluates the location in the frame described by `regs` and `mem`.
+    ///
+    /// Register and spilled values narrower than a word are zero-extended
+    /// from their `size()` bytes; small constants are sign-extended.
+    pub fn evaluate<R, M>(&self, map: &StackMap, regs: &R, mem: &M)
+                          -> Result<LocationValue, EvaluateError>
+        where R: RegisterProvider, M: MemoryReader
+    {
+        let register = |regnum: u16| regs.register(regnum)
+            .ok_or(EvaluateError::MissingRegister(regnum));
+        match self.variant {
+            LocationVariant::Register { dwarf_regnum } => {
+                let size = self.value_size()?;
+                Ok(LocationValue::Value(truncate(register(dwarf_regnum)?, size)))
+            }
+            LocationVariant::Direct { dwarf_regnum, offset } => {
+                let address = register(dwarf_regnum)?.wrapping_add(offset as i64 as u64);
+                Ok(LocationValue::StackSlot(address))
+            }
+            LocationVariant::Indirect { dwarf_regnum, offset } => {
+                let size = self.value_size()?;
+                let address = register(dwarf_regnum)?.wrapping_add(offset as i64 as u64);
+                match mem.read(address, size) {
+                    Some(value) => Ok(LocationValue::Spilled {
+                        address: address,
+                        value: truncate(value, size),
+                    }),
+                    None => Err(EvaluateError::UnreadableMemory(address)),
+                }
+            }
+            LocationVariant::Constant { value } => {
+                Ok(LocationValue::Value(value as i64 as u64))
+            }
+            LocationVariant::ConstIndex { offset } => {
+                if offset < 0 {
+                    return Err(EvaluateError::InvalidConstantIndex(offset));
+                }
+                match map.large_constants().get(offset as usize) {
+                    Some(c) => Ok(LocationValue::Value(c.value)),
+                    None => Err(EvaluateError::InvalidConstantIndex(offset)),
+                }
+            }
+        }
+    }
+}
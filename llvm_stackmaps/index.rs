@@ -0,0 +1,121 @@
+//! Lookup of stack map records by the return address of their call site.
+//!
+//! A stack walk sees return addresses, while records only store the offset
+//! of the call site within its function. `ReturnAddressIndex` resolves
+//! every record to an absolute address once, so that each frame of a walk
+//! costs a binary search instead of a scan over all records.
+
+use std::iter::Cloned;
+use std::slice;
+
+use super::{Record, StackMap};
+
+/// Stack map records sorted by absolute return address.
+#[derive(Clone, Debug)]
+pub struct ReturnAddressIndex<'a> {
+    entries: Vec<(u64, &'a Record)>,
+    /// Run-time address of the function of each entry
+    functions: Vec<u64>,
+}
+
+impl<'a> ReturnAddressIndex<'a> {
+    /// Indexes the records of `map`. `function_address` is called with the
+    /// position of each record in `map.records()` and the record itself,
+    /// and returns the run-time address of the function containing it, or
+    /// `None` to leave the record out of the index.
+    ///
+    /// When several records share a return address, `lookup` returns the
+    /// first of them.
+    pub fn new<F>(map: &'a StackMap, mut function_address: F) -> Self
+        where F: FnMut(usize, &Record) -> Option<u64>
+    {
+        let mut entries = Vec::with_capacity(map.records().len());
+        for (i, r) in map.records().iter().enumerate() {
+            if let Some(fn_address) = function_address(i, r) {
+                entries.push((fn_address, r));
+            }
+        }
+        ReturnAddressIndex::sorted(entries)
+    }
+
+    /// Indexes the records of `map` using the function addresses of its
+    /// stack size records, which are link-time addresses; `load_bias` is
+    /// added to obtain run-time addresses. Returns `None` for maps that
+    /// do not say which function each record belongs to (see
+    /// `StackMap::functions`).
+    pub fn for_functions(map: &'a StackMap, load_bias: u64) -> Option<Self> {
+        let functions = match map.functions() {
+            Some(functions) => functions,
+            None => return None,
+        };
+        let mut entries = Vec::with_capacity(map.records().len());
+        for f in functions.iter() {
+            let fn_address = match f.address().checked_add(load_bias) {
+                Some(fn_address) => fn_address,
+                None => continue,
+            };
+            for r in f.records() {
+                entries.push((fn_address, r));
+            }
+        }
+        Some(ReturnAddressIndex::sorted(entries))
+    }
+
+    /// Indexes records given with the address of their function, leaving
+    /// out records whose return address would not fit in 64 bits.
+    fn sorted(entries: Vec<(u64, &'a Record)>) -> Self {
+        let mut entries: Vec<(u64, u64, &'a Record)> = entries.into_iter()
+            .filter_map(|(fn_address, r)| {
+                fn_address.checked_add(r.instruction_offset() as u64)
+                    .map(|addr| (addr, fn_address, r))
+            })
+            .collect();
+        // A stable sort keeps duplicates in record order
+        entries.sort_by_key(|&(addr, _, _)| addr);
+        ReturnAddressIndex {
+            functions: entries.iter().map(|&(_, fn_address, _)| fn_address).collect(),
+            entries: entries.into_iter().map(|(addr, _, r)| (addr, r)).collect(),
+        }
+    }
+
+    /// Returns the record whose call site returns to `return_address`.
+    pub fn lookup(&self, return_address: u64) -> Option<&'a Record> {
+        match self.entries.binary_search_by_key(&return_address, |&(addr, _)| addr) {
+            Ok(mut i) => {
+                while i > 0 && self.entries[i - 1].0 == return_address { i -= 1; }
+                Some(self.entries[i].1)
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the record whose call site returns to `return_address`
+    /// within the function starting at `function_address`, e.g. as found
+    /// by the unwinder for a frame. Unlike `lookup`, a return address
+    /// that the index attributes to another function does not match.
+    pub fn lookup_in_function(&self, function_address: u64, return_address: u64)
+                              -> Option<&'a Record> {
+        let start = match self.entries.binary_search_by_key(&return_address, |&(addr, _)| addr) {
+            Ok(mut i) => {
+                while i > 0 && self.entries[i - 1].0 == return_address { i -= 1; }
+                i
+            }
+            Err(_) => return None,
+        };
+        self.entries[start..].iter().zip(self.functions[start..].iter())
+            .take_while(|&(&(addr, _), _)| addr == return_address)
+            .find(|&(_, &f)| f == function_address)
+            .map(|(&(_, r), _)| r)
+    }
+
+    /// Number of indexed records
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// Iterates over the indexed records and their return addresses, in
+    /// address order.
+    pub fn iter<'b>(&'b self) -> Cloned<slice::Iter<'b, (u64, &'a Record)>> {
+        self.entries.iter().cloned()
+    }
+}
@@ -0,0 +1,127 @@
+//! Registers live across patchpoints.
+//!
+//! The live-outs of a patchpoint's record are the registers that hold
+//! values still needed after the call, which code patched over the call
+//! must preserve. `LiveRegisters` collects them as a set, for a record or
+//! for all the patchpoints of a function.
+
+use std::cmp;
+
+use super::{Function, Record};
+
+/// A set of registers by DWARF number, with the number of bytes live in
+/// each.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiveRegisters {
+    /// Bit `n % 64` of word `n / 64` is set if register `n` is live
+    bits: Vec<u64>,
+    /// Bytes live in each register, by DWARF number
+    sizes: Vec<u8>,
+}
+
+impl LiveRegisters {
+    pub fn new() -> Self {
+        LiveRegisters::default()
+    }
+
+    /// Adds `size` live bytes of register `dwarf_regnum`. A register
+    /// already in the set keeps the larger of its sizes.
+    pub fn insert(&mut self, dwarf_regnum: u16, size: u8) {
+        let n = dwarf_regnum as usize;
+        if self.sizes.len() <= n {
+            self.sizes.resize(n + 1, 0);
+            self.bits.resize(n / 64 + 1, 0);
+        }
+        self.bits[n / 64] |= 1 << (n % 64);
+        self.sizes[n] = cmp::max(self.sizes[n], size);
+    }
+
+    /// Adds the registers of `other`, keeping the larger size of those in
+    /// both.
+    pub fn union_with(&mut self, other: &LiveRegisters) {
+        for (regnum, size) in other.iter() {
+            self.insert(regnum, size);
+        }
+    }
+
+    pub fn contains(&self, dwarf_regnum: u16) -> bool {
+        let n = dwarf_regnum as usize;
+        self.bits.get(n / 64).map_or(false, |w| w & (1 << (n % 64)) != 0)
+    }
+
+    /// Bytes live in register `dwarf_regnum`, if it is in the set
+    pub fn size(&self, dwarf_regnum: u16) -> Option<u8> {
+        if self.contains(dwarf_regnum) {
+            Some(self.sizes[dwarf_regnum as usize])
+        } else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|&w| w == 0)
+    }
+
+    /// The set as a bitset: bit `n % 64` of word `n / 64` is set if
+    /// register `n` is live. Words past the highest live register are
+    /// omitted.
+    pub fn words(&self) -> &[u64] {
+        &self.bits[..]
+    }
+
+    /// The registers and their live sizes, in DWARF order
+    pub fn iter(&self) -> LiveRegistersIter {
+        LiveRegistersIter { set: self, next: 0 }
+    }
+}
+
+/// Iterator over the registers of a `LiveRegisters`, returned by
+/// `LiveRegisters::iter`
+#[derive(Clone, Debug)]
+pub struct LiveRegistersIter<'a> {
+    set: &'a LiveRegisters,
+    next: usize,
+}
+
+impl<'a> Iterator for LiveRegistersIter<'a> {
+    type Item = (u16, u8);
+
+    fn next(&mut self) -> Option<(u16, u8)> {
+        while self.next < self.set.sizes.len() {
+            let n = self.next;
+            self.next += 1;
+            if self.set.contains(n as u16) {
+                return Some((n as u16, self.set.sizes[n]));
+            }
+        }
+        None
+    }
+}
+
+impl Record {
+    /// The registers live after the call, with the number of bytes live
+    /// in each
+    pub fn live_registers(&self) -> LiveRegisters {
+        let mut set = LiveRegisters::new();
+        for lo in self.live_outs.iter() {
+            set.insert(lo.dwarf_regnum, lo.size_in_bytes);
+        }
+        set
+    }
+}
+
+impl<'a> Function<'a> {
+    /// The registers live after any of the function's calls, with the
+    /// largest number of bytes live in each
+    pub fn live_registers(&self) -> LiveRegisters {
+        let mut set = LiveRegisters::new();
+        for r in self.records.iter() {
+            set.union_with(&r.live_registers());
+        }
+        set
+    }
+}
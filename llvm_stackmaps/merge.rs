@@ -0,0 +1,186 @@
+//! Combining and comparing stack maps.
+//!
+//! `StackMap::merge` concatenates the maps of several object files into
+//! one, as the linker does with their sections, but with a single pool of
+//! large constants. `StackMap::diff` reports how the records of each
+//! function changed between two builds.
+
+use std::collections::BTreeMap;
+
+use super::{LargeConstant, Location, LocationVariant, Record, StackMap};
+use super::errors::MergeError;
+
+impl StackMap {
+    /// Merges `maps`, which must all be of the same version, keeping
+    /// their functions and records in order. Equal large constants are
+    /// pooled, and `ConstIndex` locations renumbered to match; a location
+    /// referring to no constant of its map is an error, as it would
+    /// otherwise refer to one of another map in the pool.
+    pub fn merge(maps: &[StackMap]) -> Result<StackMap, MergeError> {
+        let version = match maps.first() {
+            Some(map) => map.header.version,
+            None => return Err(MergeError::NoMaps),
+        };
+        let mut merged = StackMap {
+            header: maps[0].header.clone(),
+            stack_sizes: Vec::new(),
+            large_constants: Vec::new(),
+            records: Vec::new(),
+        };
+        let mut pool = BTreeMap::new();
+        for (i, map) in maps.iter().enumerate() {
+            if map.header.version != version {
+                return Err(MergeError::VersionMismatch {
+                    map: i,
+                    version: map.header.version,
+                    expected: version,
+                });
+            }
+            merged.stack_sizes.extend(map.stack_sizes.iter().cloned());
+
+            let mut renumber = Vec::with_capacity(map.large_constants.len());
+            for c in map.large_constants.iter() {
+                let next = merged.large_constants.len();
+                let index = *pool.entry(c.value).or_insert(next);
+                if index == next {
+                    merged.large_constants.push(LargeConstant { value: c.value });
+                }
+                renumber.push(index as i32);
+            }
+            for (j, r) in map.records.iter().enumerate() {
+                let mut r = r.clone();
+                for (k, l) in r.locations.iter_mut().enumerate() {
+                    if let LocationVariant::ConstIndex { ref mut offset } = l.variant {
+                        if *offset < 0 || *offset as usize >= renumber.len() {
+                            return Err(MergeError::DanglingConstant {
+                                map: i,
+                                record: j,
+                                location: k,
+                            });
+                        }
+                        *offset = renumber[*offset as usize];
+                    }
+                }
+                merged.records.push(r);
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Compares the records of each function of this map with those of
+    /// `new`, matching functions by address. See `diff_by_key`.
+    pub fn diff<'a>(&'a self, new: &'a StackMap)
+                    -> Result<Vec<FunctionDiff<'a, u64>>, MergeError> {
+        let old_keys: Vec<u64> = self.stack_sizes.iter().map(|ss| ss.function_address).collect();
+        let new_keys: Vec<u64> = new.stack_sizes.iter().map(|ss| ss.function_address).collect();
+        self.diff_by_key(&old_keys, new, &new_keys)
+    }
+
+    /// Compares the records of each function of this map with those of
+    /// `new`. Functions are matched by key (e.g. their symbol names, which
+    /// unlike addresses survive a rebuild), given for each stack size
+    /// record of each map. Records of a function are matched by
+    /// patchpoint ID, in order among records with the same ID.
+    ///
+    /// Returns the functions whose records differ, ordered by key. Fails
+    /// if either map does not group its records by function (see
+    /// `StackMap::functions`), or if its keys are not one per function
+    /// and distinct.
+    pub fn diff_by_key<'a, K>(&'a self, old_keys: &[K], new: &'a StackMap, new_keys: &[K])
+                              -> Result<Vec<FunctionDiff<'a, K>>, MergeError>
+        where K: Ord + Clone
+    {
+        let mut functions = BTreeMap::new();
+        for (side, map, keys) in vec![(0, self, old_keys), (1, new, new_keys)] {
+            let grouped = match map.functions() {
+                Some(grouped) => grouped,
+                None => return Err(MergeError::Ungrouped { map: side }),
+            };
+            if keys.len() != grouped.len() {
+                return Err(MergeError::KeyCountMismatch {
+                    map: side,
+                    keys: keys.len(),
+                    functions: grouped.len(),
+                });
+            }
+            let mut seen = BTreeMap::new();
+            for (i, (f, key)) in grouped.into_iter().zip(keys.iter()).enumerate() {
+                if let Some(&first) = seen.get(key) {
+                    return Err(MergeError::DuplicateKey { map: side, function: i, first: first });
+                }
+                seen.insert(key, i);
+                let entry = functions.entry(key.clone()).or_insert((Vec::new(), Vec::new()));
+                let records = if side == 0 { &mut entry.0 } else { &mut entry.1 };
+                records.extend(f.records().iter());
+            }
+        }
+
+        let mut diffs = Vec::new();
+        for (key, (old_records, new_records)) in functions.into_iter() {
+            let mut diff = FunctionDiff {
+                function: key,
+                added: Vec::new(),
+                removed: Vec::new(),
+                changed: Vec::new(),
+            };
+            let mut unmatched: Vec<Option<&Record>> = new_records.iter().cloned().map(Some).collect();
+            for old in old_records.into_iter() {
+                let matching = unmatched.iter_mut()
+                    .find(|r| r.map_or(false, |r| r.patchpoint_id == old.patchpoint_id));
+                match matching {
+                    Some(slot) => {
+                        let new_record = slot.take().unwrap();
+                        if !same_record(self, old, new, new_record) {
+                            diff.changed.push((old, new_record));
+                        }
+                    }
+                    None => diff.removed.push(old),
+                }
+            }
+            diff.added.extend(unmatched.into_iter().filter_map(|r| r));
+            if !diff.is_empty() {
+                diffs.push(diff);
+            }
+        }
+        Ok(diffs)
+    }
+}
+
+/// Whether `a` of map `a_map` describes the same call site as `b` of
+/// `b_map`, comparing large constants by value and ignoring padding.
+fn same_record(a_map: &StackMap, a: &Record, b_map: &StackMap, b: &Record) -> bool {
+    let constant = |map: &StackMap, offset: i32| if offset < 0 {
+        None
+    } else {
+        map.large_constants.get(offset as usize).map(|c| c.value)
+    };
+    let same_location = |x: &Location, y: &Location| x.size == y.size && match (&x.variant, &y.variant) {
+        (&LocationVariant::ConstIndex { offset: i }, &LocationVariant::ConstIndex { offset: j }) => {
+            constant(a_map, i) == constant(b_map, j)
+        }
+        (v, w) => v == w,
+    };
+    a.instruction_offset == b.instruction_offset
+        && a.locations.len() == b.locations.len()
+        && a.locations.iter().zip(b.locations.iter()).all(|(x, y)| same_location(x, y))
+        && a.live_outs == b.live_outs
+}
+
+/// How the records of one function differ between two stack maps
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionDiff<'a, K> {
+    /// Key of the function, e.g. its address or name
+    pub function: K,
+    /// Records only in the new map
+    pub added: Vec<&'a Record>,
+    /// Records only in the old map
+    pub removed: Vec<&'a Record>,
+    /// Records in both maps whose call site differs, as (old, new)
+    pub changed: Vec<(&'a Record, &'a Record)>,
+}
+
+impl<'a, K> FunctionDiff<'a, K> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
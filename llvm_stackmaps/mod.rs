@@ -6,7 +6,7 @@
 //!
 //! ```
 //! Header {
-//!     uint8  : Stack Map Version (current version is 1)
+//!     uint8  : Stack Map Version (1, 2 or 3)
 //!     uint8  : Reserved (expected to be 0)
 //!     uint16 : Reserved (expected to be 0)
 //! }
@@ -16,6 +16,7 @@
 //! StkSizeRecord[NumFunctions] {
 //!     uint64 : Function Address
 //!     uint64 : Stack Size
+//!     uint64 : Record Count (version 2 and later)
 //! }
 //! Constants[NumConstants] {
 //!     uint64 : LargeConstant
@@ -27,7 +28,7 @@
 //!     uint16 : NumLocations
 //!     Location[NumLocations] {
 //!         uint8  : Register | Direct | Indirect | Constant | ConstantIndex
-//!         uint8  : Reserved (location flags)
+//!         uint8  : Location Size (versions 1 and 2; documented as reserved)
 //!         uint16 : Dwarf RegNum
 //!         int32  : Offset or SmallConstant
 //!     }
@@ -41,6 +42,20 @@
 //!     uint32 : Padding (only if required to align to 8 byte)
 //! }
 //! ```
+//!
+//! Version 3 widens each location and pads the locations to 8 bytes:
+//!
+//! ```
+//! Location[NumLocations] {
+//!     uint8  : Register | Direct | Indirect | Constant | ConstantIndex
+//!     uint8  : Reserved (expected to be 0)
+//!     uint16 : Location Size
+//!     uint16 : Dwarf RegNum
+//!     uint16 : Reserved (expected to be 0)
+//!     int32  : Offset or SmallConstant
+//! }
+//! uint32 : Padding (only if required to align to 8 byte)
+//! ```
 
 use byteorder::{ByteOrder};
 
@@ -51,100 +66,289 @@ pub trait Many {
 }
 
 pub mod errors {
-    use std::io;
+    use std::error;
+    use std::fmt;
+
+    /// Problems with the stack map header
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum HeaderError {
+        /// The stack map version is not one of those this module reads
+        UnknownVersion(u8),
+    }
 
-    #[derive(Debug)]
-    pub enum StackMapError {
-        Truncated { reading: &'static str },
+    /// What went wrong while reading a stack map
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        /// The section ends before the end of a field of this type
+        Truncated { want: &'static str },
+        /// The header describes a stack map this module cannot handle
         Header(HeaderError),
+        /// A location's type is none of those of `LocationVariant`
+        InvalidLocationType(u8),
     }
 
-    #[derive(Debug)]
-    pub enum HeaderError {
-        Prim(PrimError),
-    }
-    #[derive(Debug)]
-    pub struct ConstantError { pub name: &'static str, pub err: io::Error }
-    #[derive(Debug)]
-    pub enum StackSizeError {
-        Prim(PrimError),
-    }
-    #[derive(Debug)]
-    pub enum LargeConstantError {
-        Prim(PrimError),
-    }
-    #[derive(Debug)]
-    pub enum RecordError {
-        Prim(PrimError),
-        Constant(ConstantError),
-    }
-    #[derive(Debug)]
-    pub enum LocationError {
-        Prim(PrimError),
-        InvalidVariant(u8),
-    }
-    #[derive(Debug)]
-    pub enum PrimError {
-        Truncated { want: &'static str, at: usize },
-        Io(io::Error),
-    }
-
-    macro_rules! impl_from_for_read_error {
-        ($VariantErr:ident, $Variant:ident) => {
-            impl From<$VariantErr> for ReadError {
-                fn from(x: $VariantErr) -> Self { ReadError::$Variant(x) }
+    /// An error reading a stack map, with where in the section it occurred
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct ReadError {
+        pub kind: ErrorKind,
+        /// Offset in the section of the field that could not be read
+        pub offset: usize,
+        /// Index of the record being read, if any
+        pub record: Option<usize>,
+        /// Index within the record of the location being read, if any
+        pub location: Option<usize>,
+    }
+
+    impl ReadError {
+        pub fn new(kind: ErrorKind, offset: usize) -> Self {
+            ReadError { kind: kind, offset: offset, record: None, location: None }
+        }
+
+        /// Attributes the error to the `index`th record, unless it already
+        /// is attributed to one.
+        pub fn in_record(mut self, index: usize) -> Self {
+            self.record = self.record.or(Some(index));
+            self
+        }
+
+        /// Attributes the error to the `index`th location of its record,
+        /// unless it already is attributed to one.
+        pub fn in_location(mut self, index: usize) -> Self {
+            self.location = self.location.or(Some(index));
+            self
+        }
+    }
+
+    impl From<HeaderError> for ErrorKind {
+        fn from(x: HeaderError) -> Self { ErrorKind::Header(x) }
+    }
+
+    impl fmt::Display for HeaderError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                HeaderError::UnknownVersion(v) => write!(f, "unsupported stack map version {}", v),
             }
         }
     }
 
-    macro_rules! enum_read_error {
-        {$($Variant:ident($VariantErr:ident),)*} => {
-            #[derive(Debug)]
-            pub enum ReadError {
-                $($Variant($VariantErr),)*
+    impl error::Error for HeaderError {
+        fn description(&self) -> &str {
+            match *self {
+                HeaderError::UnknownVersion(_) => "unsupported stack map version",
             }
-            $(impl_from_for_read_error!{$VariantErr, $Variant})*
         }
     }
 
-    enum_read_error!{
-        StackMap(StackMapError),
-        Constant(ConstantError),
+    impl fmt::Display for ErrorKind {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                ErrorKind::Truncated { want } => write!(f, "section ends within {}", want),
+                ErrorKind::Header(ref e) => write!(f, "{}", e),
+                ErrorKind::InvalidLocationType(t) => write!(f, "invalid location type {:#x}", t),
+            }
+        }
+    }
+
+    impl error::Error for ErrorKind {
+        fn description(&self) -> &str {
+            match *self {
+                ErrorKind::Truncated { .. } => "section ends within a field",
+                ErrorKind::Header(ref e) => e.description(),
+                ErrorKind::InvalidLocationType(_) => "invalid location type",
+            }
+        }
+
+        fn cause(&self) -> Option<&error::Error> {
+            match *self {
+                ErrorKind::Header(ref e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    impl fmt::Display for ReadError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} at offset {:#x}", self.kind, self.offset)?;
+            match (self.record, self.location) {
+                (Some(r), Some(l)) => write!(f, " (record {}, location {})", r, l),
+                (Some(r), None) => write!(f, " (record {})", r),
+                (None, Some(l)) => write!(f, " (location {})", l),
+                (None, None) => Ok(()),
+            }
+        }
+    }
+
+    impl error::Error for ReadError {
+        fn description(&self) -> &str {
+            error::Error::description(&self.kind)
+        }
+
+        fn cause(&self) -> Option<&error::Error> {
+            Some(&self.kind)
+        }
+    }
+
+    /// Errors from `StackMapBuilder`
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum BuildError {
+        /// The builder's version is not one of those this module writes
         Header(HeaderError),
-        StackSize(StackSizeError),
-        LargeConstant(LargeConstantError),
-        Record(RecordError),
-        Location(LocationError),
-        Prim(PrimError),
+        /// Record `record` was added before any function
+        NoFunction { record: usize },
+        /// Location `location` of record `record` is of `size` bytes, which
+        /// does not fit the single size byte of versions 1 and 2
+        LocationTooLarge { record: usize, location: usize, size: u16 },
+    }
+
+    impl From<HeaderError> for BuildError {
+        fn from(x: HeaderError) -> Self { BuildError::Header(x) }
+    }
+
+    impl fmt::Display for BuildError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                BuildError::Header(ref e) => write!(f, "{}", e),
+                BuildError::NoFunction { record } =>
+                    write!(f, "record {} added before any function", record),
+                BuildError::LocationTooLarge { record, location, size } =>
+                    write!(f, "location {} of record {} has size {}, which does not fit in a byte",
+                           location, record, size),
+            }
+        }
+    }
+
+    impl error::Error for BuildError {
+        fn description(&self) -> &str {
+            match *self {
+                BuildError::Header(ref e) => e.description(),
+                BuildError::NoFunction { .. } => "record added before any function",
+                BuildError::LocationTooLarge { .. } => "location size does not fit in a byte",
+            }
+        }
+
+        fn cause(&self) -> Option<&error::Error> {
+            match *self {
+                BuildError::Header(ref e) => Some(e),
+                _ => None,
+            }
+        }
     }
-    
-    impl From<io::Error> for PrimError {
-        fn from(x: io::Error) -> Self { PrimError::Io(x) }
+
+    /// Errors from `StackMap::merge` and `StackMap::diff_by_key`
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum MergeError {
+        /// There were no maps to merge
+        NoMaps,
+        /// The map at index `map` is of another version than the first
+        VersionMismatch { map: usize, version: u8, expected: u8 },
+        /// The records of the map at index `map` cannot be grouped by
+        /// function (see `StackMap::functions`)
+        Ungrouped { map: usize },
+        /// The map at index `map` was given a key for each of `keys`
+        /// functions, but has `functions`
+        KeyCountMismatch { map: usize, keys: usize, functions: usize },
+        /// Function `function` of the map at index `map` has the same key
+        /// as its function `first`
+        DuplicateKey { map: usize, function: usize, first: usize },
+        /// Location `location` of record `record` of the map at index `map`
+        /// refers to a large constant the map does not have
+        DanglingConstant { map: usize, record: usize, location: usize },
     }
 
-    impl From<PrimError> for HeaderError {
-        fn from(x: PrimError) -> Self { HeaderError::Prim(x) }
+    impl fmt::Display for MergeError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                MergeError::NoMaps => write!(f, "no stack maps to merge"),
+                MergeError::VersionMismatch { map, version, expected } =>
+                    write!(f, "stack map {} is version {}, expected version {}",
+                           map, version, expected),
+                MergeError::Ungrouped { map } =>
+                    write!(f, "the records of stack map {} cannot be matched to their functions",
+                           map),
+                MergeError::KeyCountMismatch { map, keys, functions } =>
+                    write!(f, "stack map {} has {} functions, but {} keys were given",
+                           map, functions, keys),
+                MergeError::DuplicateKey { map, function, first } =>
+                    write!(f, "function {} of stack map {} has the same key as function {}",
+                           function, map, first),
+                MergeError::DanglingConstant { map, record, location } =>
+                    write!(f, "location {} of record {} of stack map {} refers to no large constant",
+                           location, record, map),
+            }
+        }
     }
 
-    impl From<PrimError> for StackSizeError {
-        fn from(x: PrimError) -> Self { StackSizeError::Prim(x) }
+    impl error::Error for MergeError {
+        fn description(&self) -> &str {
+            match *self {
+                MergeError::NoMaps => "no stack maps to merge",
+                MergeError::VersionMismatch { .. } => "stack maps of different versions",
+                MergeError::Ungrouped { .. } =>
+                    "stack map records cannot be matched to their functions",
+                MergeError::KeyCountMismatch { .. } => "not one key per function",
+                MergeError::DuplicateKey { .. } => "functions with the same key",
+                MergeError::DanglingConstant { .. } => "location refers to no large constant",
+            }
+        }
     }
 
-    impl From<PrimError> for LargeConstantError {
-        fn from(x: PrimError) -> Self { LargeConstantError::Prim(x) }
+    /// Errors from `Location::evaluate`
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum EvaluateError {
+        /// The register with this DWARF number is not known for the frame
+        MissingRegister(u16),
+        /// The stack slot at this address could not be read
+        UnreadableMemory(u64),
+        /// The location refers to a large constant that does not exist
+        InvalidConstantIndex(i32),
+        /// Values of this size (e.g. vector registers) are not supported
+        UnsupportedSize(u16),
     }
 
-    impl From<PrimError> for RecordError {
-        fn from(x: PrimError) -> Self { RecordError::Prim(x) }
+    impl fmt::Display for EvaluateError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match *self {
+                EvaluateError::MissingRegister(r) => write!(f, "register {} is not known", r),
+                EvaluateError::UnreadableMemory(a) => write!(f, "cannot read memory at {:#x}", a),
+                EvaluateError::InvalidConstantIndex(i) => write!(f, "no large constant {}", i),
+                EvaluateError::UnsupportedSize(s) => write!(f, "unsupported value size {}", s),
+            }
+        }
     }
 
-    impl From<ConstantError> for RecordError {
-        fn from(x: ConstantError) -> Self { RecordError::Constant(x) }
+    impl error::Error for EvaluateError {
+        fn description(&self) -> &str {
+            match *self {
+                EvaluateError::MissingRegister(_) => "register is not known",
+                EvaluateError::UnreadableMemory(_) => "cannot read memory",
+                EvaluateError::InvalidConstantIndex(_) => "no such large constant",
+                EvaluateError::UnsupportedSize(_) => "unsupported value size",
+            }
+        }
     }
 }
 
 use self::errors::*;
 
+mod eval;
+mod index;
+mod live_outs;
+mod merge;
+mod validate;
+mod view;
+mod write;
+
+pub use self::eval::{LocationValue, MemoryReader, RegisterProvider};
+pub use self::index::ReturnAddressIndex;
+pub use self::live_outs::{LiveRegisters, LiveRegistersIter};
+pub use self::merge::FunctionDiff;
+pub use self::validate::{Diagnostic, Problem, Target};
+pub use self::view::{LiveOuts, Locations, RecordRef, Records, StackMapRef, StackSizes};
+pub use self::write::StackMapBuilder;
+
+#[cfg(test)]
+mod test;
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct NumFunctions(pub u32);
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -168,6 +372,9 @@ macro_rules! impl_to_usize {
 
 impl_to_usize!(NumFunctions, NumConstants, NumRecords, NumLocations, NumLiveOuts);
 
+/// Stack map versions `StackMap::read_from` accepts
+pub const SUPPORTED_VERSIONS: &'static [u8] = &[1, 2, 3];
+
 impl StackMap {
     pub fn read_from<T>(r: &[u8]) -> Result<Self, ReadError>
         where T:ByteOrder
@@ -182,6 +389,27 @@ trait ReadFrom<T:ByteOrder>: Sized {
     fn read(bytes: &[u8], offset: usize) -> ReadResult<Self>;
 }
 
+/// Parts of the stack map whose layout depends on the format version.
+trait ReadVersioned<T:ByteOrder>: Sized {
+    type Count: ToUsize;
+    fn read_versioned(bytes: &[u8], offset: usize, version: u8) -> ReadResult<Self>;
+    /// Attributes an error reading the `index`th element of a list.
+    fn locate(err: ReadError, _index: usize) -> ReadError { err }
+    fn read_many_versioned(bytes: &[u8],
+                           mut offset: usize,
+                           count: &Self::Count,
+                           version: u8) -> ReadResult<Vec<Self>> {
+        let mut v = Vec::with_capacity(count.to_usize());
+        for index in 0..(count.to_usize()) {
+            let (e, new_offset) = Self::read_versioned(bytes, offset, version)
+                .map_err(|err| Self::locate(err, index))?;
+            offset = new_offset;
+            v.push(e);
+        }
+        Ok((v, offset))
+    }
+}
+
 trait ReadMany<T:ByteOrder>: ReadFrom<T> {
     type Count: ToUsize;
     fn read_many(bytes: &[u8],
@@ -202,17 +430,21 @@ impl<T:ByteOrder> ReadFrom<T> for StackMap {
         debug!("StackMap::read b: {:?} i: {}", b, i);
         let (hr, i): (Header, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackMap::read hr: {:?} i: {}", hr, i);
+        if !SUPPORTED_VERSIONS.contains(&hr.version) {
+            return Err(ReadError::new(HeaderError::UnknownVersion(hr.version).into(), 0));
+        }
+        let v = hr.version;
         let (nf, i): (NumFunctions, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackMap::read nf: {:?} i: {}", nf, i);
         let (nc, i): (NumConstants, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackMap::read nc: {:?} i: {}", nc, i);
         let (nr, i): (NumRecords, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackMap::read nr: {:?} i: {}", nr, i);
-        let (ss, i): (Vec<StackSize>, _) = ReadMany::<T>::read_many(b, i, &nf)?;
+        let (ss, i): (Vec<StackSize>, _) = ReadVersioned::<T>::read_many_versioned(b, i, &nf, v)?;
         debug!("StackMap::read ss: {:?} i: {}", ss, i);
         let (cs, i): (Vec<LargeConstant>, _) = ReadMany::<T>::read_many(b, i, &nc)?;
         debug!("StackMap::read cs: {:?} i: {}", cs, i);
-        let (rs, i): (Vec<Record>, _) = ReadMany::<T>::read_many(b, i, &nr)?;
+        let (rs, i): (Vec<Record>, _) = ReadVersioned::<T>::read_many_versioned(b, i, &nr, v)?;
         debug!("StackMap::read rs: {:?} i: {}", rs, i);
         Ok((StackMap { header: hr,
                        stack_sizes: ss,
@@ -242,15 +474,12 @@ macro_rules! impl_read_for_prim {
             fn read(b: &[u8], i: usize) -> Result<(Self, usize), ReadError> {
                 let size = ::std::mem::size_of::<$t>();
                 if b.len() < (i + size) {
-                    return Err(PrimError::Truncated {
-                        want: stringify!($t),
-                        at: i,
-                    }.into());
+                    return Err(ReadError::new(ErrorKind::Truncated { want: stringify!($t) }, i));
                 }
                 let ret = Ok((T::$read(b.split_at(i).1), i+size));
                 if $debug {
-                    println!("{}::read({:?}, {}) => {:?}",
-                             stringify!($t), b, i, ret);
+                    debug!("{}::read({:?}, {}) => {:?}",
+                           stringify!($t), b, i, ret);
                 }
                 ret
             }
@@ -266,10 +495,7 @@ impl_read_for_prim!(u64, read_u64, true);
 impl<T:ByteOrder> ReadFrom<T> for u8 {
     fn read(b: &[u8], i: usize) -> Result<(Self, usize), ReadError> {
         if b.len() < (i + 1) {
-            return Err(PrimError::Truncated {
-                want: stringify!(u8),
-                at: i,
-            }.into());
+            return Err(ReadError::new(ErrorKind::Truncated { want: stringify!(u8) }, i));
         }
         Ok((b[i], i+1))
     }
@@ -280,10 +506,7 @@ macro_rules! impl_read_from_for_u_wrapper {
         impl<T:ByteOrder> ReadFrom<T> for $S {
             fn read(b: &[u8], i: usize) -> Result<(Self, usize), ReadError> {
                 if b.len() < (i + $size) {
-                    return Err(PrimError::Truncated {
-                        want: stringify!($S),
-                        at: i,
-                    }.into());
+                    return Err(ReadError::new(ErrorKind::Truncated { want: stringify!($S) }, i));
                 }
                 Ok(($S(T::$read_u(b.split_at(i).1)), i+$size))
             }
@@ -306,20 +529,24 @@ impl_read_from_for_u32_wrapper!(NumRecords);
 impl_read_from_for_u16_wrapper!(NumLocations);
 impl_read_from_for_u16_wrapper!(NumLiveOuts);
 
-impl<T:ByteOrder> ReadMany<T> for StackSize { type Count = NumFunctions; }
 impl<T:ByteOrder> ReadMany<T> for LargeConstant { type Count = NumConstants; }
-impl<T:ByteOrder> ReadMany<T> for Record { type Count = NumRecords; }
-impl<T:ByteOrder> ReadMany<T> for Location { type Count = NumLocations; }
 
-impl<T:ByteOrder> ReadFrom<T> for StackSize {
-    fn read(b: &[u8], i: usize) -> ReadResult<Self> {
+impl<T:ByteOrder> ReadVersioned<T> for StackSize {
+    type Count = NumFunctions;
+    fn read_versioned(b: &[u8], i: usize, v: u8) -> ReadResult<Self> {
         debug!("StackSize::read b: {:?} i: {}", b, i);
         let (fa, i): (u64, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackSize::read fa: {:?} i: {}", fa, i);
         let (ss, i): (u64, _) = ReadFrom::<T>::read(b, i)?;
         debug!("StackSize::read ss: {:?} i: {}", ss, i);
+        let (rc, i) = if v == 1 { (None, i) } else {
+            let (rc, i): (u64, _) = ReadFrom::<T>::read(b, i)?;
+            (Some(rc), i)
+        };
+        debug!("StackSize::read rc: {:?} i: {}", rc, i);
         Ok((StackSize { function_address: fa,
-                        stack_size: ss }, i))
+                        stack_size: ss,
+                        record_count: rc }, i))
     }
 }
 
@@ -330,8 +557,10 @@ impl<T:ByteOrder> ReadFrom<T> for LargeConstant {
     }
 }
 
-impl<T:ByteOrder> ReadFrom<T> for Record {
-    fn read(b: &[u8], i: usize) -> ReadResult<Self> {
+impl<T:ByteOrder> ReadVersioned<T> for Record {
+    type Count = NumRecords;
+    fn locate(err: ReadError, index: usize) -> ReadError { err.in_record(index) }
+    fn read_versioned(b: &[u8], i: usize, v: u8) -> ReadResult<Self> {
         debug!("Record::read b: {:?} i: {}", b, i);
         let (pi, i): (u64, _) = ReadFrom::<T>::read(b, i)?;
         debug!("Record::read pi: {:?}, i: {}", pi, i);
@@ -341,15 +570,19 @@ impl<T:ByteOrder> ReadFrom<T> for Record {
         debug!("Record::read rs: {:?}, i: {}", rs, i);
         let (nl, i): (NumLocations, _) = ReadFrom::<T>::read(b, i)?;
         debug!("Record::read nl: {:?}, i: {}", nl, i);
-        let (ls, i): (Vec<Location>, _) = ReadMany::<T>::read_many(b, i, &nl)?;
+        let (ls, i): (Vec<Location>, _) = ReadVersioned::<T>::read_many_versioned(b, i, &nl, v)?;
         debug!("Record::read ls: {:?}, i: {}", ls, i);
+        let (p0, i) = if i % 8 == 0 { (None, i) } else {
+            let (p0, i): (u32, _) = ReadFrom::<T>::read(b, i)?;
+            (Some(p0), i)
+        };
+        debug!("Record::read p0: {:?}, i: {}", p0, i);
         let (p1, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
         debug!("Record::read p1: {:?}, i: {}", p1, i);
         let (nlo, i): (NumLiveOuts, _) = ReadFrom::<T>::read(b, i)?;
         debug!("Record::read nlo: {:?}, i: {}", nlo, i);
         let (los, i): (Vec<LiveOut>, _) = ReadMany::<T>::read_many(b, i, &nlo)?;
         debug!("Record::read los: {:?}, i: {}", los, i);
-        assert!(i % 4 == 0);
         let (p2, i) = if i % 8 == 0 {
             (None, i) } else {
             let (p2, i): (u32, _) = ReadFrom::<T>::read(b, i)?;
@@ -362,6 +595,7 @@ impl<T:ByteOrder> ReadFrom<T> for Record {
             reserved: rs,
             num_locations: nl,
             locations: ls,
+            padding_0: p0,
             padding_1: p1,
             num_live_outs: nlo,
             live_outs: los,
@@ -370,11 +604,22 @@ impl<T:ByteOrder> ReadFrom<T> for Record {
     }
 }
 
-impl<T:ByteOrder> ReadFrom<T> for Location {
-    fn read(b: &[u8], i: usize) -> ReadResult<Self> {
-        let (vt, i): (u8, _) = ReadFrom::<T>::read(b, i)?;
-        let (rs, i): (u8, _) = ReadFrom::<T>::read(b, i)?;
-        let (dr, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
+impl<T:ByteOrder> ReadVersioned<T> for Location {
+    type Count = NumLocations;
+    fn locate(err: ReadError, index: usize) -> ReadError { err.in_location(index) }
+    fn read_versioned(b: &[u8], start: usize, v: u8) -> ReadResult<Self> {
+        let (vt, i): (u8, _) = ReadFrom::<T>::read(b, start)?;
+        let (rs, sz, dr, rs2, i) = if v < 3 {
+            let (sz, i): (u8, _) = ReadFrom::<T>::read(b, i)?;
+            let (dr, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
+            (0, sz as u16, dr, 0, i)
+        } else {
+            let (rs, i): (u8, _) = ReadFrom::<T>::read(b, i)?;
+            let (sz, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
+            let (dr, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
+            let (rs2, i): (u16, _) = ReadFrom::<T>::read(b, i)?;
+            (rs, sz, dr, rs2, i)
+        };
         let (os, i): (i32, _) = ReadFrom::<T>::read(b, i)?;
 
         let variant = match vt {
@@ -383,11 +628,13 @@ impl<T:ByteOrder> ReadFrom<T> for Location {
             0x3 => LocationVariant::Indirect { dwarf_regnum: dr, offset: os },
             0x4 => LocationVariant::Constant { value : os },
             0x5 => LocationVariant::ConstIndex { offset: os },
-            other => return Err(LocationError::InvalidVariant(other).into()),
+            other => return Err(ReadError::new(ErrorKind::InvalidLocationType(other), start)),
         };
 
         Ok((Location {
             reserved: rs,
+            size: sz,
+            reserved_2: rs2,
             variant: variant,
         }, i))
     }
@@ -426,13 +673,15 @@ pub struct Header {
 pub struct StackSize {
     function_address: u64,
     stack_size: u64,
+    record_count: Option<u64>, // version 2 and later
 }
 
 impl fmt::Debug for StackSize {
     fn fmt(&self, w: &mut fmt::Formatter) -> fmt::Result {
-        write!(w, "StackSize {{ function_address 0x{:x}, stack_size: {} }}",
+        write!(w, "StackSize {{ function_address 0x{:x}, stack_size: {}, record_count: {:?} }}",
                self.function_address,
-               self.stack_size)
+               self.stack_size,
+               self.record_count)
     }
 }
 
@@ -448,6 +697,7 @@ pub struct Record {
     reserved: u16,
     num_locations: NumLocations,
     locations: Vec<Location>,
+    padding_0: Option<u32>, // version 3, only if required to align to 8 byte
     padding_1: u16,
     num_live_outs: NumLiveOuts,
     live_outs: Vec<LiveOut>,
@@ -457,6 +707,8 @@ pub struct Record {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Location {
     reserved: u8,
+    size: u16,
+    reserved_2: u16, // version 3
     variant: LocationVariant,
 }
 
@@ -486,6 +738,46 @@ impl StackMap {
     pub fn stack_sizes(&self) -> &[StackSize] { &self.stack_sizes[..] }
     pub fn large_constants(&self) -> &[LargeConstant] { &self.large_constants[..] }
     pub fn records(&self) -> &[Record] { &self.records[..] }
+
+    /// Groups the records by the function containing them.
+    ///
+    /// Records are emitted function by function, in the order of the
+    /// stack size records, each of which counts the records of its
+    /// function. Returns `None` for version 1 maps, which lack the
+    /// counts, or if the counts do not add up to the number of records.
+    pub fn functions<'a>(&'a self) -> Option<Vec<Function<'a>>> {
+        let mut functions = Vec::with_capacity(self.stack_sizes.len());
+        let mut start = 0;
+        for ss in self.stack_sizes.iter() {
+            let count = match ss.record_count {
+                Some(count) => count,
+                None => return None,
+            };
+            if count > (self.records.len() - start) as u64 {
+                return None;
+            }
+            let end = start + count as usize;
+            functions.push(Function { stack_size: ss, records: &self.records[start..end] });
+            start = end;
+        }
+        if start != self.records.len() {
+            return None;
+        }
+        Some(functions)
+    }
+}
+
+/// A function with stack map records, as returned by `StackMap::functions`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Function<'a> {
+    stack_size: &'a StackSize,
+    records: &'a [Record],
+}
+
+impl<'a> Function<'a> {
+    pub fn address(&self) -> u64 { self.stack_size.function_address }
+    pub fn stack_size(&self) -> u64 { self.stack_size.stack_size }
+    pub fn records(&self) -> &'a [Record] { self.records }
 }
 
 impl Header {
@@ -495,6 +787,8 @@ impl Header {
 impl StackSize {
     pub fn function_address(&self) -> u64 { self.function_address }
     pub fn stack_size(&self) -> u64 { self.stack_size }
+    /// Number of records for the function; absent from version 1 maps
+    pub fn record_count(&self) -> Option<u64> { self.record_count }
 }
 
 impl Record {
@@ -506,6 +800,8 @@ impl Record {
 
 impl Location {
     pub fn variant(&self) -> &LocationVariant { &self.variant }
+    /// Size in bytes of the value
+    pub fn size(&self) -> u16 { self.size }
 }
 
 impl LiveOut {
@@ -0,0 +1,56 @@
+#!/usr/bin/env python3
+"""Re-encodes a version 3 stack map section in the version 1 and 2 layouts.
+
+Current llc only emits version 3, so the older fixtures are synthetic,
+derived from stackmaps-v3.bin rather than produced by an old llc:
+
+    python3 downgrade.py stackmaps-v3.bin
+
+writes stackmaps-v2.bin and stackmaps-v1.bin. Following LLVM's description
+of those versions, the byte after a location's type holds its size.
+"""
+import struct
+import sys
+
+
+def align8(out):
+    while len(out) % 8:
+        out += b"\0"
+
+
+def downgrade(data, version):
+    _, _, _, nf, nc, nr = struct.unpack_from("<BBHIII", data, 0)
+    out = bytearray(struct.pack("<BBHIII", version, 0, 0, nf, nc, nr))
+    i = 16
+    for _ in range(nf):
+        addr, size, count = struct.unpack_from("<QQQ", data, i)
+        i += 24
+        out += struct.pack("<QQ", addr, size)
+        if version == 2:
+            out += struct.pack("<Q", count)
+    out += data[i:i + 8 * nc]
+    i += 8 * nc
+    for _ in range(nr):
+        pid, off, flags, nl = struct.unpack_from("<QIHH", data, i)
+        i += 16
+        out += struct.pack("<QIHH", pid, off, flags, nl)
+        for _ in range(nl):
+            ty, _, size, reg, _, offset = struct.unpack_from("<BBHHHi", data, i)
+            i += 12
+            out += struct.pack("<BBHi", ty, size, reg, offset)
+        i = (i + 7) & ~7
+        _, nlo = struct.unpack_from("<HH", data, i)
+        i += 4
+        out += struct.pack("<HH", 0, nlo)
+        out += data[i:i + 4 * nlo]
+        i += 4 * nlo
+        i = (i + 7) & ~7
+        align8(out)
+    return bytes(out)
+
+
+if __name__ == "__main__":
+    v3 = open(sys.argv[1], "rb").read()
+    for version in (2, 1):
+        with open("stackmaps-v%d.bin" % version, "wb") as f:
+            f.write(downgrade(v3, version))
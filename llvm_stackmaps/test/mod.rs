@@ -0,0 +1,585 @@
+//! Golden tests on `.llvm_stackmaps` sections for `stackmaps.ll`.
+//!
+//! Only `stackmaps-v3.bin` is compiler output, from llc 14. The version 1
+//! and 2 sections are synthetic: `downgrade.py` re-encodes the version 3
+//! section in the older layouts as LLVM documented them, because no
+//! available llc emits those versions. They test the reader and writer
+//! against that documented layout, not against the output of an old llc;
+//! `check_layout` spells out the offsets of that layout by hand so the
+//! tests do not rest on `downgrade.py` alone. `stackmaps.ll` describes how
+//! to replace them with llc output.
+
+extern crate quickcheck;
+extern crate rand;
+
+use byteorder::{BigEndian, LittleEndian};
+
+use self::quickcheck::{quickcheck, Arbitrary, Gen};
+use self::rand::Rng;
+
+use super::*;
+use super::errors::*;
+
+fn location(size: u16, variant: LocationVariant) -> Location {
+    Location::new(size, variant)
+}
+
+fn live_out(dwarf_regnum: u16) -> LiveOut {
+    LiveOut::new(dwarf_regnum, 8)
+}
+
+fn record(patchpoint_id: u64,
+          instruction_offset: u32,
+          locations: Vec<Location>,
+          live_outs: Vec<LiveOut>,
+          version: u8) -> Record {
+    // Version 3 pads the 12-byte locations to 8 bytes; the padding after
+    // the live-outs then depends on their count as in the older versions
+    let padding_0 = if version == 3 && locations.len() % 2 == 1 { Some(0) } else { None };
+    let padding_2 = if live_outs.len() % 2 == 0 { Some(0) } else { None };
+    Record {
+        patchpoint_id: patchpoint_id,
+        instruction_offset: instruction_offset,
+        reserved: 0,
+        num_locations: NumLocations(locations.len() as u16),
+        locations: locations,
+        padding_0: padding_0,
+        padding_1: 0,
+        num_live_outs: NumLiveOuts(live_outs.len() as u16),
+        live_outs: live_outs,
+        padding_2: padding_2,
+    }
+}
+
+fn expected(version: u8) -> StackMap {
+    let record_count = if version == 1 { None } else { Some(1) };
+    let stack_size = |function_address, stack_size| StackSize {
+        function_address: function_address,
+        stack_size: stack_size,
+        record_count: record_count,
+    };
+    StackMap {
+        header: Header { version: version, reserved_1: 0, reserved_2: 0 },
+        stack_sizes: vec![stack_size(0x401010, 8),
+                          stack_size(0x401020, 24),
+                          stack_size(0x401050, 24)],
+        large_constants: vec![LargeConstant { value: 1 << 32 }],
+        records: vec![
+            record(1, 8, vec![
+                location(8, LocationVariant::Register { dwarf_regnum: 0 }),
+                location(8, LocationVariant::Constant { value: 7 }),
+                location(8, LocationVariant::ConstIndex { offset: 0 }),
+                location(8, LocationVariant::Constant { value: -1 }),
+            ], vec![], version),
+            record(2, 18, vec![
+                location(8, LocationVariant::Direct { dwarf_regnum: 6, offset: -16 }),
+                location(8, LocationVariant::Register { dwarf_regnum: 3 }),
+            ], vec![], version),
+            record(3, 14, vec![
+                location(8, LocationVariant::Register { dwarf_regnum: 5 }),
+            ], vec![live_out(3), live_out(7), live_out(14)], version),
+        ],
+    }
+}
+
+/// Checks the first record of a synthetic section at the offsets LLVM
+/// documented for its version, independently of `downgrade.py`: the header
+/// and a stack size record of `function_size` bytes for each of the three
+/// functions, the large constant, then a 16-byte record header followed
+/// by 8-byte locations.
+fn check_layout(bytes: &[u8], version: u8, function_size: usize) {
+    assert_eq!(&bytes[..16], &[version, 0, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0]);
+    let constants = 16 + 3 * function_size;
+    assert_eq!(&bytes[constants..constants + 8], &[0, 0, 0, 0, 1, 0, 0, 0]);
+    let record = constants + 8;
+    // ID 1 at instruction offset 8 with four locations
+    assert_eq!(&bytes[record..record + 16], &[1, 0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 0, 0, 4, 0]);
+    // Register 0 of size 8, then the constant 7
+    assert_eq!(&bytes[record + 16..record + 32], &[1, 8, 0, 0, 0, 0, 0, 0, 4, 8, 0, 0, 7, 0, 0, 0]);
+}
+
+#[test]
+fn read_version_1() {
+    let bytes = include_bytes!("stackmaps-v1.bin");
+    check_layout(bytes, 1, 16);
+    let map = StackMap::read_from::<LittleEndian>(bytes).unwrap();
+    assert_eq!(map, expected(1));
+}
+
+#[test]
+fn read_version_2() {
+    let bytes = include_bytes!("stackmaps-v2.bin");
+    check_layout(bytes, 2, 24);
+    let map = StackMap::read_from::<LittleEndian>(bytes).unwrap();
+    assert_eq!(map, expected(2));
+}
+
+#[test]
+fn read_version_3() {
+    let map = StackMap::read_from::<LittleEndian>(include_bytes!("stackmaps-v3.bin")).unwrap();
+    assert_eq!(map, expected(3));
+    assert_eq!(map.stack_sizes()[1].record_count(), Some(1));
+    assert_eq!(map.records()[1].locations()[0].size(), 8);
+}
+
+#[test]
+fn reject_unknown_version() {
+    let mut bytes = include_bytes!("stackmaps-v3.bin").to_vec();
+    bytes[0] = 4;
+    let err = StackMap::read_from::<LittleEndian>(&bytes).unwrap_err();
+    assert_eq!(err, ReadError::new(ErrorKind::Header(HeaderError::UnknownVersion(4)), 0));
+}
+
+#[test]
+fn errors_carry_position() {
+    // The second location of the second record
+    let mut bytes = include_bytes!("stackmaps-v3.bin").to_vec();
+    bytes[0xc4] = 9;
+    let err = StackMap::read_from::<LittleEndian>(&bytes).unwrap_err();
+    assert_eq!(err, ReadError {
+        kind: ErrorKind::InvalidLocationType(9),
+        offset: 0xc4,
+        record: Some(1),
+        location: Some(1),
+    });
+    assert_eq!(err.to_string(), "invalid location type 0x9 at offset 0xc4 (record 1, location 1)");
+    assert_eq!(StackMapRef::<LittleEndian>::new(&bytes).unwrap_err(), err);
+
+    let bytes = include_bytes!("stackmaps-v3.bin");
+    let err = StackMap::read_from::<LittleEndian>(&bytes[..0xca]).unwrap_err();
+    assert_eq!(err.to_string(), "section ends within u16 at offset 0xca (record 1, location 1)");
+}
+
+#[test]
+fn return_address_lookup() {
+    let map = StackMap::read_from::<LittleEndian>(include_bytes!("stackmaps-v3.bin")).unwrap();
+    // Each function of the fixture has a single record
+    let index = ReturnAddressIndex::new(&map, |i, _| {
+        Some(map.stack_sizes()[i].function_address() + 0x1000)
+    });
+    assert_eq!(index.len(), 3);
+    assert_eq!(index.lookup(0x402010 + 8), Some(&map.records()[0]));
+    assert_eq!(index.lookup(0x402020 + 18), Some(&map.records()[1]));
+    assert_eq!(index.lookup(0x402050 + 14), Some(&map.records()[2]));
+    assert_eq!(index.lookup(0x401010 + 8), None);
+    assert_eq!(index.lookup(0x402050), None);
+    let addresses: Vec<u64> = index.iter().map(|(addr, _)| addr).collect();
+    assert_eq!(addresses, vec![0x402018, 0x402032, 0x40205e]);
+    assert_eq!(index.lookup_in_function(0x402050, 0x40205e), Some(&map.records()[2]));
+    assert_eq!(index.lookup_in_function(0x402020, 0x40205e), None);
+
+    // Records without a known function are left out
+    let index = ReturnAddressIndex::new(&map, |_, r| {
+        if r.patchpoint_id() == 2 { None } else { Some(0) }
+    });
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.lookup(18), None);
+    assert_eq!(index.lookup(14).map(|r| r.patchpoint_id()), Some(3));
+
+    // Records whose return address overflows are left out
+    let index = ReturnAddressIndex::new(&map, |_, _| Some(u64::max_value() - 10));
+    assert_eq!(index.len(), 1);
+    assert_eq!(index.lookup(u64::max_value() - 2).map(|r| r.patchpoint_id()), Some(1));
+}
+
+#[test]
+fn group_records_by_function() {
+    let map = StackMap::read_from::<LittleEndian>(include_bytes!("stackmaps-v3.bin")).unwrap();
+    let functions = map.functions().unwrap();
+    assert_eq!(functions.len(), 3);
+    assert_eq!(functions[1].address(), 0x401020);
+    assert_eq!(functions[1].stack_size(), 24);
+    assert_eq!(functions[1].records(), &map.records()[1..2]);
+
+    let index = ReturnAddressIndex::for_functions(&map, 0x1000).unwrap();
+    assert_eq!(index.lookup(0x402050 + 14), Some(&map.records()[2]));
+
+    let v1 = StackMap::read_from::<LittleEndian>(include_bytes!("stackmaps-v1.bin")).unwrap();
+    assert!(v1.functions().is_none());
+    assert!(ReturnAddressIndex::for_functions(&v1, 0).is_none());
+
+    // Counts that do not match the records are rejected
+    let mut bad = map.clone();
+    bad.stack_sizes[2].record_count = Some(2);
+    assert!(bad.functions().is_none());
+    bad.stack_sizes[2].record_count = Some(0);
+    assert!(bad.functions().is_none());
+}
+
+struct Frame {
+    registers: Vec<(u16, u64)>,
+    memory: Vec<(u64, u64)>,
+}
+
+impl RegisterProvider for Frame {
+    fn register(&self, dwarf_regnum: u16) -> Option<u64> {
+        self.registers.iter().find(|&&(r, _)| r == dwarf_regnum).map(|&(_, v)| v)
+    }
+}
+
+impl MemoryReader for Frame {
+    fn read(&self, address: u64, size: usize) -> Option<u64> {
+        assert_eq!(size, 8);
+        self.memory.iter().find(|&&(a, _)| a == address).map(|&(_, v)| v)
+    }
+}
+
+#[test]
+fn evaluate_locations() {
+    let map = StackMap::read_from::<LittleEndian>(include_bytes!("stackmaps-v3.bin")).unwrap();
+    let frame = Frame {
+        registers: vec![(0, 0x1122334455667788), (3, 42), (6, 0x7ffe0100)],
+        memory: vec![(0x7ffe00f0, 0xfeed)],
+    };
+    let values: Vec<_> = map.records()[0].locations().iter()
+        .map(|l| l.evaluate(&map, &frame, &frame).unwrap())
+        .collect();
+    assert_eq!(values, vec![LocationValue::Value(0x1122334455667788),
+                            LocationValue::Value(7),
+                            LocationValue::Value(1 << 32),
+                            LocationValue::Value(!0)]);
+
+    let locations = map.records()[1].locations();
+    assert_eq!(locations[0].evaluate(&map, &frame, &frame), Ok(LocationValue::StackSlot(0x7ffe00f0)));
+    assert_eq!(locations[1].evaluate(&map, &frame, &frame), Ok(LocationValue::Value(42)));
+
+    // A patchpoint argument held in a register the frame does not know
+    assert_eq!(map.records()[2].locations()[0].evaluate(&map, &frame, &frame),
+               Err(EvaluateError::MissingRegister(5)));
+
+    let spill = location(8, LocationVariant::Indirect { dwarf_regnum: 6, offset: -16 });
+    assert_eq!(spill.evaluate(&map, &frame, &frame),
+               Ok(LocationValue::Spilled { address: 0x7ffe00f0, value: 0xfeed }));
+    let spill = location(8, LocationVariant::Indirect { dwarf_regnum: 6, offset: -8 });
+    assert_eq!(spill.evaluate(&map, &frame, &frame),
+               Err(EvaluateError::UnreadableMemory(0x7ffe00f8)));
+
+    // Sub-word values only keep their low bytes
+    let narrow = location(4, LocationVariant::Register { dwarf_regnum: 0 });
+    assert_eq!(narrow.evaluate(&map, &frame, &frame), Ok(LocationValue::Value(0x55667788)));
+    let vector = location(16, LocationVariant::Register { dwarf_regnum: 17 });
+    assert_eq!(vector.evaluate(&map, &frame, &frame), Err(EvaluateError::UnsupportedSize(16)));
+    let missing = location(8, LocationVariant::ConstIndex { offset: 1 });
+    assert_eq!(missing.evaluate(&map, &frame, &frame), Err(EvaluateError::InvalidConstantIndex(1)));
+}
+
+#[test]
+fn write_reproduces_sections() {
+    for &bytes in [&include_bytes!("stackmaps-v1.bin")[..],
+                   &include_bytes!("stackmaps-v2.bin")[..],
+                   &include_bytes!("stackmaps-v3.bin")[..]].iter() {
+        let map = StackMap::read_from::<LittleEndian>(bytes).unwrap();
+        assert_eq!(&map.write::<LittleEndian>()[..], bytes);
+    }
+}
+
+#[test]
+fn build_matches_fixtures() {
+    for version in 1..4 {
+        let mut b = StackMapBuilder::new(version);
+        let large = b.large_constant(1 << 32);
+        b.function(0x401010, 8)
+            .record(1, 8, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 0 }),
+                               Location::new(8, LocationVariant::Constant { value: 7 }),
+                               Location::new(8, large),
+                               Location::new(8, LocationVariant::Constant { value: -1 })],
+                    vec![]).unwrap();
+        b.function(0x401020, 24)
+            .record(2, 18, vec![Location::new(8, LocationVariant::Direct { dwarf_regnum: 6, offset: -16 }),
+                                Location::new(8, LocationVariant::Register { dwarf_regnum: 3 })],
+                    vec![]).unwrap();
+        b.function(0x401050, 24)
+            .record(3, 14, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 5 })],
+                    vec![LiveOut::new(3, 8), LiveOut::new(7, 8), LiveOut::new(14, 8)]).unwrap();
+        assert_eq!(b.build().unwrap(), expected(version));
+    }
+    assert_eq!(StackMapBuilder::new(4).build().unwrap_err(),
+               BuildError::Header(HeaderError::UnknownVersion(4)));
+}
+
+#[test]
+fn builder_rejects_invalid_records() {
+    let mut b = StackMapBuilder::new(3);
+    let err = b.record(1, 8, vec![], vec![]).unwrap_err();
+    assert_eq!(err, BuildError::NoFunction { record: 0 });
+    assert_eq!(err.to_string(), "record 0 added before any function");
+
+    let large = Location::new(256, LocationVariant::Register { dwarf_regnum: 0 });
+    for version in 1..3 {
+        let mut b = StackMapBuilder::new(version);
+        b.function(0x1000, 8).record(1, 8, vec![], vec![]).unwrap();
+        assert_eq!(b.record(2, 8, vec![large.clone()], vec![]).unwrap_err(),
+                   BuildError::LocationTooLarge { record: 1, location: 0, size: 256 });
+    }
+    let mut b = StackMapBuilder::new(3);
+    b.function(0x1000, 8);
+    assert!(b.record(1, 8, vec![large], vec![]).is_ok());
+}
+
+#[derive(Clone, Debug)]
+struct ArbitraryMap(StackMap);
+
+impl Arbitrary for ArbitraryMap {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let version = g.gen_range(1, 4);
+        let mut b = StackMapBuilder::new(version);
+        for _ in 0..g.gen_range(0, 4) {
+            b.function(g.gen(), g.gen());
+            for _ in 0..g.gen_range(0, 4) {
+                let mut locations = Vec::new();
+                for _ in 0..g.gen_range(0, 6) {
+                    let variant = match g.gen_range(0, 5) {
+                        0 => LocationVariant::Register { dwarf_regnum: g.gen() },
+                        1 => LocationVariant::Direct { dwarf_regnum: g.gen(), offset: g.gen() },
+                        2 => LocationVariant::Indirect { dwarf_regnum: g.gen(), offset: g.gen() },
+                        3 => LocationVariant::Constant { value: g.gen() },
+                        _ => b.large_constant(g.gen()),
+                    };
+                    let size = if version < 3 { g.gen::<u8>() as u16 } else { g.gen() };
+                    locations.push(Location::new(size, variant));
+                }
+                let mut live_outs = Vec::new();
+                for _ in 0..g.gen_range(0, 4) {
+                    live_outs.push(LiveOut::new(g.gen(), g.gen()));
+                }
+                b.record(g.gen(), g.gen(), locations, live_outs).unwrap();
+            }
+        }
+        ArbitraryMap(b.build().unwrap())
+    }
+}
+
+#[test]
+fn write_round_trips() {
+    fn prop(map: ArbitraryMap) -> bool {
+        let map = map.0;
+        StackMap::read_from::<LittleEndian>(&map.write::<LittleEndian>()).unwrap() == map &&
+            StackMap::read_from::<BigEndian>(&map.write::<BigEndian>()).unwrap() == map
+    }
+    quickcheck(prop as fn(ArbitraryMap) -> bool);
+}
+
+#[test]
+fn view_matches_read() {
+    for version in 1..4 {
+        let bytes = expected(version).write::<LittleEndian>();
+        let map = StackMap::read_from::<LittleEndian>(&bytes).unwrap();
+        let view = StackMapRef::<LittleEndian>::new(&bytes).unwrap();
+        assert_eq!(view.header(), map.header());
+        assert_eq!(view.stack_sizes().collect::<Vec<_>>(), map.stack_sizes());
+        assert_eq!(view.large_constant(0), Some(1 << 32));
+        assert_eq!(view.large_constant(1), None);
+        assert_eq!(view.num_records(), 3);
+        let r = view.record(2).unwrap();
+        assert_eq!(r.patchpoint_id(), 3);
+        assert_eq!(r.instruction_offset(), 14);
+        assert_eq!(r.location(0), Some(location(8, LocationVariant::Register { dwarf_regnum: 5 })));
+        assert_eq!(r.location(1), None);
+        assert_eq!(r.live_outs().collect::<Vec<_>>(), map.records()[2].live_outs());
+        assert_eq!(view.records().map(|r| r.to_record()).collect::<Vec<_>>(), map.records());
+        assert_eq!(view.to_stack_map(), map);
+    }
+}
+
+#[test]
+fn view_rejects_truncated() {
+    let bytes = include_bytes!("stackmaps-v3.bin");
+    assert!(StackMapRef::<LittleEndian>::new(&bytes[..]).is_ok());
+    for &len in [0, 20, 40, bytes.len() - 8].iter() {
+        assert!(StackMapRef::<LittleEndian>::new(&bytes[..len]).is_err());
+    }
+    // A header claiming u32::MAX records must fail without allocating for them.
+    let huge = [3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+    assert!(StackMapRef::<LittleEndian>::new(&huge[..]).is_err());
+}
+
+#[test]
+fn view_round_trips() {
+    fn prop(map: ArbitraryMap) -> bool {
+        let map = map.0;
+        let le = map.write::<LittleEndian>();
+        let be = map.write::<BigEndian>();
+        StackMapRef::<LittleEndian>::new(&le).unwrap().to_stack_map() == map &&
+            StackMapRef::<BigEndian>::new(&be).unwrap().to_stack_map() == map
+    }
+    quickcheck(prop as fn(ArbitraryMap) -> bool);
+}
+
+#[test]
+fn validate_fixtures() {
+    for version in 1..4 {
+        assert_eq!(expected(version).validate_for(&Target::x86_64()), vec![]);
+    }
+}
+
+#[test]
+fn validate_reports_problems() {
+    let mut b = StackMapBuilder::new(3);
+    b.function(0x1000, 16)
+        .record(7, 0x20, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 200 }),
+                              Location::new(8, LocationVariant::ConstIndex { offset: 0 })],
+                vec![]).unwrap()
+        .record(7, 0x40, vec![], vec![LiveOut::new(3, 8)]).unwrap();
+    let mut map = b.build().unwrap();
+    map.header.reserved_1 = 1;
+    map.records[1].live_outs[0].reserved = 2;
+
+    let mut target = Target::x86_64();
+    target.function_sizes.insert(0x1000, 0x30);
+    let diags = map.validate_for(&target);
+    let at = |problem, record, location, live_out| Diagnostic {
+        problem: problem, record: record, location: location, live_out: live_out,
+    };
+    assert_eq!(diags, vec![
+        at(Problem::NonzeroReserved { field: "Header.reserved_1", value: 1 }, None, None, None),
+        at(Problem::InvalidRegister(200), Some(0), Some(0), None),
+        at(Problem::ConstantIndexOutOfRange(0), Some(0), Some(1), None),
+        at(Problem::DuplicatePatchpointId { id: 7, first: 0 }, Some(1), None, None),
+        at(Problem::OffsetBeyondFunction { offset: 0x40, function_size: 0x30 }, Some(1), None, None),
+        at(Problem::NonzeroReserved { field: "LiveOut.reserved", value: 2 }, Some(1), None, Some(0)),
+    ]);
+    assert_eq!(diags[2].to_string(), "record 0: location 1: large constant 0 does not exist");
+
+    // Without a target only the target-independent problems are found
+    assert_eq!(map.validate().len(), 4);
+
+    map.stack_sizes[0].record_count = Some(3);
+    assert_eq!(map.validate()[1],
+               at(Problem::RecordCountMismatch { counted: 3, records: 2 }, None, None, None));
+    map.stack_sizes.push(StackSize {
+        function_address: 0x2000,
+        stack_size: 8,
+        record_count: Some(u64::max_value()),
+    });
+    assert_eq!(map.validate()[1], at(Problem::RecordCountOverflow, None, None, None));
+
+    // Registers are checked against the architecture's table, which has
+    // no DWARF register 70 on x86-64, where AArch64 has v6
+    map.records[1].live_outs[0].dwarf_regnum = 70;
+    assert!(map.validate_for(&Target::x86_64()).contains(
+        &at(Problem::InvalidRegister(70), Some(1), None, Some(0))));
+    assert!(!map.validate_for(&Target::aarch64()).contains(
+        &at(Problem::InvalidRegister(70), Some(1), None, Some(0))));
+}
+
+#[test]
+fn merge_pools_constants() {
+    let mut a = StackMapBuilder::new(3);
+    let one = a.large_constant(1 << 40);
+    a.function(0x1000, 8).record(1, 4, vec![Location::new(8, one)], vec![]).unwrap();
+    let mut b = StackMapBuilder::new(3);
+    let two = b.large_constant(1 << 41);
+    let one = b.large_constant(1 << 40);
+    b.function(0x2000, 16)
+        .record(2, 8, vec![Location::new(8, one), Location::new(8, two)], vec![]).unwrap();
+    let (a, b) = (a.build().unwrap(), b.build().unwrap());
+
+    let merged = StackMap::merge(&[a.clone(), b.clone()]).unwrap();
+    assert_eq!(merged.stack_sizes().len(), 2);
+    assert_eq!(merged.large_constants(),
+               &[LargeConstant { value: 1 << 40 }, LargeConstant { value: 1 << 41 }][..]);
+    assert_eq!(merged.records()[1].locations(),
+               &[Location::new(8, LocationVariant::ConstIndex { offset: 0 }),
+                 Location::new(8, LocationVariant::ConstIndex { offset: 1 })][..]);
+    assert_eq!(merged.validate(), vec![]);
+    assert_eq!(StackMap::merge(&[a, expected(2)]),
+               Err(MergeError::VersionMismatch { map: 1, version: 2, expected: 3 }));
+    assert_eq!(StackMap::merge(&[]), Err(MergeError::NoMaps));
+}
+
+#[test]
+fn merge_rejects_dangling_constants() {
+    // Once pooled, index 0 of the second map would refer to the first
+    // map's constant
+    let mut a = StackMapBuilder::new(3);
+    let one = a.large_constant(111);
+    a.function(0x1000, 8).record(1, 4, vec![Location::new(8, one)], vec![]).unwrap();
+    let mut b = StackMapBuilder::new(3);
+    b.function(0x2000, 16)
+        .record(2, 8, vec![], vec![]).unwrap()
+        .record(3, 12, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 0 }),
+                            Location::new(8, LocationVariant::ConstIndex { offset: 0 })],
+                vec![]).unwrap();
+    let (a, b) = (a.build().unwrap(), b.build().unwrap());
+
+    let err = StackMap::merge(&[a, b]).unwrap_err();
+    assert_eq!(err, MergeError::DanglingConstant { map: 1, record: 1, location: 1 });
+    assert_eq!(err.to_string(), "location 1 of record 1 of stack map 1 refers to no large constant");
+}
+
+#[test]
+fn diff_records_by_function() {
+    let old = expected(3);
+    let mut b = StackMapBuilder::new(3);
+    // The large constant moves in the pool, which is not a change
+    b.large_constant(5);
+    let large = b.large_constant(1 << 32);
+    b.function(0x401010, 8)
+        .record(1, 8, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 0 }),
+                           Location::new(8, LocationVariant::Constant { value: 7 }),
+                           Location::new(8, large),
+                           Location::new(8, LocationVariant::Constant { value: -1 })],
+                vec![]).unwrap();
+    b.function(0x401020, 24)
+        .record(2, 20, vec![Location::new(8, LocationVariant::Direct { dwarf_regnum: 6, offset: -16 }),
+                            Location::new(8, LocationVariant::Register { dwarf_regnum: 3 })],
+                vec![]).unwrap();
+    b.function(0x401060, 8)
+        .record(4, 2, vec![], vec![]).unwrap();
+    let new = b.build().unwrap();
+
+    let diffs = old.diff(&new).unwrap();
+    assert_eq!(diffs.len(), 3);
+    assert_eq!(diffs[0].function, 0x401020);
+    assert_eq!(diffs[0].changed, vec![(&old.records()[1], &new.records()[1])]);
+    assert_eq!((diffs[1].function, diffs[1].removed.len(), diffs[1].added.len()), (0x401050, 1, 0));
+    assert_eq!((diffs[2].function, diffs[2].removed.len(), diffs[2].added.len()), (0x401060, 0, 1));
+    assert_eq!(old.diff(&old).unwrap(), vec![]);
+    assert_eq!(expected(1).diff(&old), Err(MergeError::Ungrouped { map: 0 }));
+
+    // Keys must be one per function and distinct
+    assert_eq!(old.diff_by_key(&["f", "g"], &new, &["f", "g", "h"]),
+               Err(MergeError::KeyCountMismatch { map: 0, keys: 2, functions: 3 }));
+    assert_eq!(old.diff_by_key(&["f", "g", "h"], &new, &["f", "g", "f"]),
+               Err(MergeError::DuplicateKey { map: 1, function: 2, first: 0 }));
+}
+
+#[test]
+fn live_registers() {
+    let map = expected(3);
+    let live = map.records()[2].live_registers();
+    assert_eq!(live.iter().collect::<Vec<_>>(), vec![(3, 8), (7, 8), (14, 8)]);
+    assert_eq!(live.words(), &[(1 << 3) | (1 << 7) | (1 << 14)][..]);
+    assert_eq!(live.size(7), Some(8));
+    assert!(!live.contains(5));
+    assert!(map.records()[0].live_registers().is_empty());
+
+    let mut b = StackMapBuilder::new(3);
+    b.function(0x1000, 16)
+        .record(1, 0x10, vec![], vec![LiveOut::new(3, 8), LiveOut::new(17, 16)]).unwrap()
+        .record(2, 0x20, vec![], vec![LiveOut::new(17, 32), LiveOut::new(70, 8)]).unwrap();
+    let map = b.build().unwrap();
+    let live = map.functions().unwrap()[0].live_registers();
+    assert_eq!(live.iter().collect::<Vec<_>>(), vec![(3, 8), (17, 32), (70, 8)]);
+    assert_eq!(live.len(), 3);
+    assert_eq!(live.words(), &[(1 << 3) | (1 << 17), 1 << 6][..]);
+}
+
+#[test]
+fn validate_roots_in_live_outs() {
+    let mut b = StackMapBuilder::new(3);
+    b.function(0x1000, 16)
+        .record(1, 0x10, vec![Location::new(8, LocationVariant::Register { dwarf_regnum: 3 }),
+                              Location::new(8, LocationVariant::Register { dwarf_regnum: 12 })],
+                vec![LiveOut::new(12, 8), LiveOut::new(13, 8)]).unwrap();
+    let map = b.build().unwrap();
+    let diags = map.validate();
+    assert_eq!(diags, vec![Diagnostic {
+        problem: Problem::RootInLiveOut(12),
+        record: Some(0),
+        location: Some(1),
+        live_out: Some(0),
+    }]);
+    assert_eq!(diags[0].to_string(),
+               "record 0: location 1: live-out 0: GC root in DWARF register 12 is also live-out");
+}
@@ -0,0 +1,39 @@
+; The next build of stackmaps.ll, for the stackmap-dump --diff golden test:
+; `constants` records another value, `patch` uses a new patchpoint ID.
+;
+;   llc -O1 -filetype=obj stackmaps-next.ll -o stackmaps-next.o
+target triple = "x86_64-unknown-linux-gnu"
+
+declare void @llvm.experimental.stackmap(i64, i32, ...)
+declare void @llvm.experimental.patchpoint.void(i64, i32, i8*, i32, ...)
+define void @callee(i64 %x) noinline {
+  ret void
+}
+
+; Register, constant and large constant locations
+define i64 @constants(i64 %a, i64 %b) {
+entry:
+  %sum = add i64 %a, %b
+  call void (i64, i32, ...) @llvm.experimental.stackmap(i64 1, i32 0, i64 %sum, i64 7, i64 4294967296, i32 -1, i64 %a)
+  ret i64 %sum
+}
+
+; Direct (alloca) and indirect (spilled) locations
+define void @frame(i64 %a) {
+entry:
+  %slot = alloca i64
+  store i64 %a, i64* %slot
+  call void @callee(i64 %a)
+  call void (i64, i32, ...) @llvm.experimental.stackmap(i64 2, i32 0, i64* %slot, i64 %a)
+  call void @callee(i64 %a)
+  ret void
+}
+
+; A patchpoint, whose record lists the registers live after the call
+define i64 @patch(i64 %a, i64 %b) {
+entry:
+  %c = add i64 %a, %b
+  call void (i64, i32, i8*, i32, ...) @llvm.experimental.patchpoint.void(i64 4, i32 16, i8* null, i32 0, i64 %a)
+  %d = mul i64 %c, %b
+  ret i64 %d
+}
@@ -0,0 +1,48 @@
+; Fixture for the llvm_stackmaps and stackmap-dump golden tests.
+;
+;   llc -O1 -filetype=obj stackmaps.ll -o stackmaps.o
+;   gcc -static -nostdlib -no-pie -Wl,-e,constants -o stackmaps stackmaps.o
+;   objcopy -O binary --only-section=.llvm_stackmaps stackmaps stackmaps-v3.bin
+;   python3 downgrade.py stackmaps-v3.bin
+;
+; The last step derives the synthetic version 1 and 2 fixtures, which no
+; available llc can emit. llc 3.8 or older emits version 1 and llc 4 to 8
+; emit version 2; running the first three steps with those, writing
+; stackmaps-v1.bin and stackmaps-v2.bin, replaces the synthetic fixtures
+; with compiler output. Their code generation may differ from llc 14, so
+; the offsets in the tests' expected maps must be checked as well.
+target triple = "x86_64-unknown-linux-gnu"
+
+declare void @llvm.experimental.stackmap(i64, i32, ...)
+declare void @llvm.experimental.patchpoint.void(i64, i32, i8*, i32, ...)
+define void @callee(i64 %x) noinline {
+  ret void
+}
+
+; Register, constant and large constant locations
+define i64 @constants(i64 %a, i64 %b) {
+entry:
+  %sum = add i64 %a, %b
+  call void (i64, i32, ...) @llvm.experimental.stackmap(i64 1, i32 0, i64 %sum, i64 7, i64 4294967296, i32 -1)
+  ret i64 %sum
+}
+
+; Direct (alloca) and indirect (spilled) locations
+define void @frame(i64 %a) {
+entry:
+  %slot = alloca i64
+  store i64 %a, i64* %slot
+  call void @callee(i64 %a)
+  call void (i64, i32, ...) @llvm.experimental.stackmap(i64 2, i32 0, i64* %slot, i64 %a)
+  call void @callee(i64 %a)
+  ret void
+}
+
+; A patchpoint, whose record lists the registers live after the call
+define i64 @patch(i64 %a, i64 %b) {
+entry:
+  %c = add i64 %a, %b
+  call void (i64, i32, i8*, i32, ...) @llvm.experimental.patchpoint.void(i64 3, i32 16, i8* null, i32 0, i64 %a)
+  %d = mul i64 %c, %b
+  ret i64 %d
+}
@@ -0,0 +1,231 @@
+//! Consistency checks on decoded stack maps.
+//!
+//! Reading a stack map only checks that it can be decoded. `validate`
+//! looks for the inconsistencies a corrupt or mis-generated map shows
+//! and reports each one, rather than stopping at the first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use unravel::registers::Arch;
+
+use super::{LocationVariant, StackMap};
+
+/// What is known about the code a stack map was generated for. Checks
+/// needing information that is absent are skipped.
+#[derive(Clone, Debug, Default)]
+pub struct Target {
+    /// The architecture, whose register table tells which DWARF register
+    /// numbers are valid
+    pub arch: Option<Arch>,
+    /// Code size in bytes of each function, by function address (e.g.
+    /// from the symbol table)
+    pub function_sizes: BTreeMap<u64, u64>,
+}
+
+impl Target {
+    pub fn new(arch: Arch) -> Self {
+        Target { arch: Some(arch), function_sizes: BTreeMap::new() }
+    }
+
+    pub fn x86_64() -> Self {
+        Target::new(Arch::X86_64)
+    }
+
+    pub fn aarch64() -> Self {
+        Target::new(Arch::AArch64)
+    }
+}
+
+/// An inconsistency found by `StackMap::validate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// A reserved or padding field is not zero
+    NonzeroReserved { field: &'static str, value: u64 },
+    /// A `ConstIndex` location refers to a large constant that does not exist
+    ConstantIndexOutOfRange(i32),
+    /// The patchpoint ID was already used by the record with this index
+    DuplicatePatchpointId { id: u64, first: usize },
+    /// The record's instruction offset is past the end of its function
+    OffsetBeyondFunction { offset: u32, function_size: u64 },
+    /// The DWARF register number is not in the target's register table
+    InvalidRegister(u16),
+    /// The record counts of the functions do not add up to the number
+    /// of records
+    RecordCountMismatch { counted: u64, records: usize },
+    /// The record counts of the functions add up to more than a `u64`
+    RecordCountOverflow,
+    /// The register of a `Register` location, a GC root, is also a
+    /// live-out, so code patched over the call that preserves it would
+    /// undo the collector's update of the root
+    RootInLiveOut(u16),
+}
+
+/// A `Problem` and the part of the map it was found in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub problem: Problem,
+    /// Index of the record, if the problem is in one
+    pub record: Option<usize>,
+    /// Index of the location within the record, if the problem is in one
+    pub location: Option<usize>,
+    /// Index of the live-out within the record, if the problem is in one
+    pub live_out: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(problem: Problem) -> Self {
+        Diagnostic { problem: problem, record: None, location: None, live_out: None }
+    }
+
+    fn in_record(problem: Problem, record: usize) -> Self {
+        Diagnostic { record: Some(record), ..Diagnostic::new(problem) }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::NonzeroReserved { field, value } =>
+                write!(f, "reserved field {} is {:#x}", field, value),
+            Problem::ConstantIndexOutOfRange(i) =>
+                write!(f, "large constant {} does not exist", i),
+            Problem::DuplicatePatchpointId { id, first } =>
+                write!(f, "patchpoint ID {} is also used by record {}", id, first),
+            Problem::OffsetBeyondFunction { offset, function_size } =>
+                write!(f, "instruction offset {:#x} is beyond the function's {:#x} bytes",
+                       offset, function_size),
+            Problem::InvalidRegister(r) =>
+                write!(f, "DWARF register {} does not exist on the target", r),
+            Problem::RecordCountMismatch { counted, records } =>
+                write!(f, "functions count {} records, but the map has {}", counted, records),
+            Problem::RecordCountOverflow =>
+                write!(f, "function record counts overflow"),
+            Problem::RootInLiveOut(r) =>
+                write!(f, "GC root in DWARF register {} is also live-out", r),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(r) = self.record {
+            write!(f, "record {}: ", r)?;
+        }
+        if let Some(l) = self.location {
+            write!(f, "location {}: ", l)?;
+        }
+        if let Some(l) = self.live_out {
+            write!(f, "live-out {}: ", l)?;
+        }
+        write!(f, "{}", self.problem)
+    }
+}
+
+impl StackMap {
+    /// Checks the map for inconsistencies that do not depend on the
+    /// target, returning them in the order of the map.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.validate_for(&Target::default())
+    }
+
+    /// Checks the map for inconsistencies, including those only visible
+    /// with what `target` tells about the code.
+    pub fn validate_for(&self, target: &Target) -> Vec<Diagnostic> {
+        let mut diags = Vec::new();
+        let reserved = |field, value: u64| if value != 0 {
+            Some(Problem::NonzeroReserved { field: field, value: value })
+        } else {
+            None
+        };
+        let register = |regnum: u16| match target.arch {
+            Some(arch) if arch.register(regnum).is_none() =>
+                Some(Problem::InvalidRegister(regnum)),
+            _ => None,
+        };
+
+        let header = &self.header;
+        diags.extend(reserved("Header.reserved_1", header.reserved_1 as u64).map(Diagnostic::new));
+        diags.extend(reserved("Header.reserved_2", header.reserved_2 as u64).map(Diagnostic::new));
+
+        if header.version >= 2 {
+            let counted = self.stack_sizes.iter().fold(Some(0u64), |sum, ss| {
+                sum.and_then(|sum| sum.checked_add(ss.record_count.unwrap_or(0)))
+            });
+            match counted {
+                None => diags.push(Diagnostic::new(Problem::RecordCountOverflow)),
+                Some(counted) if counted != self.records.len() as u64 => {
+                    diags.push(Diagnostic::new(Problem::RecordCountMismatch {
+                        counted: counted,
+                        records: self.records.len(),
+                    }));
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Function sizes apply to the records of each function
+        let mut function_sizes = Vec::with_capacity(self.records.len());
+        if let Some(functions) = self.functions() {
+            for f in functions.iter() {
+                let size = target.function_sizes.get(&f.address()).cloned();
+                function_sizes.extend(f.records().iter().map(|_| size));
+            }
+        }
+
+        let mut first_with_id = HashMap::new();
+        for (i, r) in self.records.iter().enumerate() {
+            match first_with_id.get(&r.patchpoint_id).cloned() {
+                Some(first) => diags.push(Diagnostic::in_record(
+                    Problem::DuplicatePatchpointId { id: r.patchpoint_id, first: first }, i)),
+                None => { first_with_id.insert(r.patchpoint_id, i); }
+            }
+            if let Some(&Some(size)) = function_sizes.get(i) {
+                if r.instruction_offset as u64 > size {
+                    diags.push(Diagnostic::in_record(Problem::OffsetBeyondFunction {
+                        offset: r.instruction_offset,
+                        function_size: size,
+                    }, i));
+                }
+            }
+            let fields = [("Record.reserved", r.reserved as u64),
+                          ("Record.padding_0", r.padding_0.unwrap_or(0) as u64),
+                          ("Record.padding_1", r.padding_1 as u64),
+                          ("Record.padding_2", r.padding_2.unwrap_or(0) as u64)];
+            for &(field, value) in fields.iter() {
+                diags.extend(reserved(field, value).map(|p| Diagnostic::in_record(p, i)));
+            }
+
+            for (j, l) in r.locations.iter().enumerate() {
+                let at = |p| Diagnostic { location: Some(j), ..Diagnostic::in_record(p, i) };
+                diags.extend(reserved("Location.reserved", l.reserved as u64).map(&at));
+                diags.extend(reserved("Location.reserved_2", l.reserved_2 as u64).map(&at));
+                if let LocationVariant::Register { dwarf_regnum } = l.variant {
+                    if let Some(k) = r.live_outs.iter().position(|lo| lo.dwarf_regnum == dwarf_regnum) {
+                        diags.push(Diagnostic { live_out: Some(k), ..at(Problem::RootInLiveOut(dwarf_regnum)) });
+                    }
+                }
+                match l.variant {
+                    LocationVariant::Register { dwarf_regnum } |
+                    LocationVariant::Direct { dwarf_regnum, .. } |
+                    LocationVariant::Indirect { dwarf_regnum, .. } => {
+                        diags.extend(register(dwarf_regnum).map(&at));
+                    }
+                    LocationVariant::ConstIndex { offset } => {
+                        if offset < 0 || offset as usize >= self.large_constants.len() {
+                            diags.push(at(Problem::ConstantIndexOutOfRange(offset)));
+                        }
+                    }
+                    LocationVariant::Constant { .. } => {}
+                }
+            }
+
+            for (j, lo) in r.live_outs.iter().enumerate() {
+                let at = |p| Diagnostic { live_out: Some(j), ..Diagnostic::in_record(p, i) };
+                diags.extend(reserved("LiveOut.reserved", lo.reserved as u64).map(&at));
+                diags.extend(register(lo.dwarf_regnum).map(&at));
+            }
+        }
+        diags
+    }
+}
@@ -0,0 +1,295 @@
+//! A borrowed, lazily decoded view of a stack map section.
+//!
+//! `StackMap::read_from` decodes everything up front into nested vectors.
+//! `StackMapRef` instead checks the whole section once, recording where
+//! each record starts, and decodes functions, constants, records,
+//! locations and live-outs from the section bytes only when asked for.
+
+use byteorder::ByteOrder;
+
+use std::cmp;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{Header, LiveOut, Location, NumLiveOuts, NumLocations, ReadFrom, ReadResult,
+            ReadVersioned, Record, StackMap, StackSize, SUPPORTED_VERSIONS};
+use super::errors::*;
+
+const HEADER_SIZE: usize = 16;
+const RECORD_HEADER_SIZE: usize = 16;
+const LIVE_OUT_SIZE: usize = 4;
+
+fn align8(i: usize) -> usize { (i + 7) & !7 }
+
+fn stack_size_size(version: u8) -> usize { if version == 1 { 16 } else { 24 } }
+
+fn location_size(version: u8) -> usize { if version < 3 { 8 } else { 12 } }
+
+/// Checks that `bytes` holds `count` elements of `size` bytes from
+/// `start`, returning the offset after them.
+fn check_array(bytes: &[u8], start: usize, size: usize, count: usize, want: &'static str)
+               -> Result<usize, ReadError> {
+    let end = start + size * count;
+    if bytes.len() < end {
+        let at = start + (bytes.len().saturating_sub(start)) / size * size;
+        return Err(ReadError::new(ErrorKind::Truncated { want: want }, at));
+    }
+    Ok(end)
+}
+
+/// Decodes a value at an offset `StackMapRef::new` has already checked.
+fn decoded<X>(r: ReadResult<X>) -> X {
+    r.ok().expect("stack map was validated by StackMapRef::new").0
+}
+
+/// A stack map section decoded on demand, in byte order `T`.
+pub struct StackMapRef<'a, T: ByteOrder> {
+    bytes: &'a [u8],
+    header: Header,
+    num_functions: usize,
+    num_constants: usize,
+    /// Offset of each record in `bytes`
+    record_offsets: Vec<usize>,
+    _order: PhantomData<T>,
+}
+
+impl<'a, T: ByteOrder> StackMapRef<'a, T> {
+    /// Checks that `bytes` holds a well-formed stack map and returns a
+    /// view of it.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, ReadError> {
+        let (header, i): (Header, _) = ReadFrom::<T>::read(bytes, 0)?;
+        if !SUPPORTED_VERSIONS.contains(&header.version) {
+            return Err(ReadError::new(HeaderError::UnknownVersion(header.version).into(), 0));
+        }
+        let v = header.version;
+        let (nf, i): (u32, _) = ReadFrom::<T>::read(bytes, i)?;
+        let (nc, i): (u32, _) = ReadFrom::<T>::read(bytes, i)?;
+        let (nr, _): (u32, _) = ReadFrom::<T>::read(bytes, i)?;
+
+        let constants_start = check_array(bytes, HEADER_SIZE, stack_size_size(v), nf as usize,
+                                          "StackSize")?;
+        let records_start = check_array(bytes, constants_start, 8, nc as usize, "LargeConstant")?;
+
+        // Every record takes at least 16 bytes, so a count larger than the
+        // section could hold is not trusted for the allocation.
+        let max_records = (bytes.len() - records_start) / 16;
+        let mut record_offsets = Vec::with_capacity(cmp::min(nr as usize, max_records));
+        let mut i = records_start;
+        for index in 0..nr as usize {
+            record_offsets.push(i);
+            i = Self::check_record(bytes, i, v).map_err(|err| err.in_record(index))?;
+        }
+        Ok(StackMapRef {
+            bytes: bytes,
+            header: header,
+            num_functions: nf as usize,
+            num_constants: nc as usize,
+            record_offsets: record_offsets,
+            _order: PhantomData,
+        })
+    }
+
+    /// Checks the record at `i`, returning the offset of the next one.
+    fn check_record(b: &[u8], i: usize, v: u8) -> Result<usize, ReadError> {
+        let (nl, _): (NumLocations, _) = ReadFrom::<T>::read(b, i + 14)?;
+        let mut j = i + RECORD_HEADER_SIZE;
+        for index in 0..nl.0 as usize {
+            let (_, next): (Location, _) = ReadVersioned::<T>::read_versioned(b, j, v)
+                .map_err(|err| err.in_location(index))?;
+            j = next;
+        }
+        // Only version 3 locations can leave the live-outs misaligned
+        j = align8(j);
+        let (nlo, _): (NumLiveOuts, _) = ReadFrom::<T>::read(b, j + 2)?;
+        let end = check_array(b, j + 4, LIVE_OUT_SIZE, nlo.0 as usize, "LiveOut")?;
+        if b.len() < align8(end) {
+            return Err(ReadError::new(ErrorKind::Truncated { want: "u32" }, end));
+        }
+        Ok(align8(end))
+    }
+
+    pub fn header(&self) -> &Header { &self.header }
+
+    pub fn num_functions(&self) -> usize { self.num_functions }
+
+    /// Decodes the stack size record of the `i`th function.
+    pub fn stack_size(&self, i: usize) -> Option<StackSize> {
+        if i >= self.num_functions { return None; }
+        let v = self.header.version;
+        let offset = HEADER_SIZE + i * stack_size_size(v);
+        Some(decoded(ReadVersioned::<T>::read_versioned(self.bytes, offset, v)))
+    }
+
+    pub fn stack_sizes<'b>(&'b self) -> StackSizes<'a, 'b, T> {
+        StackSizes { map: self, next: 0 }
+    }
+
+    pub fn num_large_constants(&self) -> usize { self.num_constants }
+
+    pub fn large_constant(&self, i: usize) -> Option<u64> {
+        if i >= self.num_constants { return None; }
+        let offset = HEADER_SIZE + self.num_functions * stack_size_size(self.header.version) + i * 8;
+        Some(decoded(ReadFrom::<T>::read(self.bytes, offset)))
+    }
+
+    pub fn num_records(&self) -> usize { self.record_offsets.len() }
+
+    pub fn record(&self, i: usize) -> Option<RecordRef<'a, T>> {
+        self.record_offsets.get(i).map(|&offset| RecordRef {
+            bytes: self.bytes,
+            offset: offset,
+            version: self.header.version,
+            _order: PhantomData,
+        })
+    }
+
+    pub fn records<'b>(&'b self) -> Records<'a, 'b, T> {
+        Records { map: self, next: 0 }
+    }
+
+    /// Decodes the whole map.
+    pub fn to_stack_map(&self) -> StackMap {
+        StackMap {
+            header: self.header.clone(),
+            stack_sizes: self.stack_sizes().collect(),
+            large_constants: (0..self.num_constants)
+                .map(|i| super::LargeConstant { value: self.large_constant(i).unwrap() })
+                .collect(),
+            records: self.records().map(|r| r.to_record()).collect(),
+        }
+    }
+}
+
+// The byte order types implement nothing, so these are not derived
+
+impl<'a, T: ByteOrder> Clone for StackMapRef<'a, T> {
+    fn clone(&self) -> Self {
+        StackMapRef {
+            bytes: self.bytes,
+            header: self.header.clone(),
+            num_functions: self.num_functions,
+            num_constants: self.num_constants,
+            record_offsets: self.record_offsets.clone(),
+            _order: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: ByteOrder> fmt::Debug for StackMapRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StackMapRef {{ header: {:?}, num_functions: {}, num_constants: {}, num_records: {} }}",
+               self.header, self.num_functions, self.num_constants, self.record_offsets.len())
+    }
+}
+
+/// A record of a `StackMapRef`, decoded on demand.
+pub struct RecordRef<'a, T: ByteOrder> {
+    bytes: &'a [u8],
+    offset: usize,
+    version: u8,
+    _order: PhantomData<T>,
+}
+
+impl<'a, T: ByteOrder> Copy for RecordRef<'a, T> {}
+
+impl<'a, T: ByteOrder> Clone for RecordRef<'a, T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<'a, T: ByteOrder> fmt::Debug for RecordRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RecordRef {{ offset: {}, patchpoint_id: {} }}",
+               self.offset, self.patchpoint_id())
+    }
+}
+
+impl<'a, T: ByteOrder> RecordRef<'a, T> {
+    fn at<X: ReadFrom<T>>(&self, offset: usize) -> X {
+        decoded(ReadFrom::<T>::read(self.bytes, self.offset + offset))
+    }
+
+    /// Offset of the record in the section
+    pub fn offset(&self) -> usize { self.offset }
+
+    pub fn patchpoint_id(&self) -> u64 { self.at(0) }
+
+    pub fn instruction_offset(&self) -> u32 { self.at(8) }
+
+    pub fn num_locations(&self) -> usize { self.at::<u16>(14) as usize }
+
+    /// Decodes the `i`th location.
+    pub fn location(&self, i: usize) -> Option<Location> {
+        if i >= self.num_locations() { return None; }
+        let offset = self.offset + RECORD_HEADER_SIZE + i * location_size(self.version);
+        Some(decoded(ReadVersioned::<T>::read_versioned(self.bytes, offset, self.version)))
+    }
+
+    pub fn locations(&self) -> Locations<'a, T> {
+        Locations { record: *self, next: 0 }
+    }
+
+    /// Offset of the padding and live-out count, relative to the record
+    fn live_outs_header(&self) -> usize {
+        let end = self.offset + RECORD_HEADER_SIZE + self.num_locations() * location_size(self.version);
+        align8(end) - self.offset
+    }
+
+    pub fn num_live_outs(&self) -> usize {
+        self.at::<u16>(self.live_outs_header() + 2) as usize
+    }
+
+    pub fn live_out(&self, i: usize) -> Option<LiveOut> {
+        if i >= self.num_live_outs() { return None; }
+        Some(self.at(self.live_outs_header() + 4 + i * LIVE_OUT_SIZE))
+    }
+
+    pub fn live_outs(&self) -> LiveOuts<'a, T> {
+        LiveOuts { record: *self, next: 0 }
+    }
+
+    /// Decodes the whole record.
+    pub fn to_record(&self) -> Record {
+        decoded(ReadVersioned::<T>::read_versioned(self.bytes, self.offset, self.version))
+    }
+}
+
+macro_rules! view_iterator {
+    ($(#[$doc:meta])* struct $Iter:ident<$($l:tt),*> { $field:ident: $Field:ty }
+     => $get:ident: $Item:ty) => {
+        $(#[$doc])*
+        pub struct $Iter<$($l,)* T: ByteOrder + 'a> {
+            $field: $Field,
+            next: usize,
+        }
+
+        impl<$($l,)* T: ByteOrder> Clone for $Iter<$($l,)* T> {
+            fn clone(&self) -> Self { $Iter { $field: self.$field, next: self.next } }
+        }
+
+        impl<$($l,)* T: ByteOrder> Iterator for $Iter<$($l,)* T> {
+            type Item = $Item;
+            fn next(&mut self) -> Option<$Item> {
+                let item = self.$field.$get(self.next);
+                if item.is_some() { self.next += 1; }
+                item
+            }
+        }
+    }
+}
+
+view_iterator! {
+    /// Iterator over the stack size records of a `StackMapRef`
+    struct StackSizes<'a, 'b> { map: &'b StackMapRef<'a, T> } => stack_size: StackSize
+}
+view_iterator! {
+    /// Iterator over the records of a `StackMapRef`
+    struct Records<'a, 'b> { map: &'b StackMapRef<'a, T> } => record: RecordRef<'a, T>
+}
+view_iterator! {
+    /// Iterator over the locations of a `RecordRef`
+    struct Locations<'a> { record: RecordRef<'a, T> } => location: Location
+}
+view_iterator! {
+    /// Iterator over the live-outs of a `RecordRef`
+    struct LiveOuts<'a> { record: RecordRef<'a, T> } => live_out: LiveOut
+}
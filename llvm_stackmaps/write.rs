@@ -0,0 +1,213 @@
+//! Construction and serialization of stack maps.
+//!
+//! `StackMapBuilder` assembles a `StackMap` function by function, filling
+//! in the counts and padding fields the way LLVM emits them, and
+//! `StackMap::write` lays it out in the format of its header's version.
+//! This lets the scanner be tested on stack maps without a compiler that
+//! emits them.
+
+use byteorder::ByteOrder;
+
+use std::marker::PhantomData;
+
+use super::{Header, LargeConstant, LiveOut, Location, LocationVariant, NumLiveOuts,
+            NumLocations, Record, StackMap, StackSize, SUPPORTED_VERSIONS};
+use super::errors::{BuildError, HeaderError};
+
+impl Location {
+    /// A location of a value of `size` bytes.
+    pub fn new(size: u16, variant: LocationVariant) -> Self {
+        Location { reserved: 0, size: size, reserved_2: 0, variant: variant }
+    }
+}
+
+impl LiveOut {
+    pub fn new(dwarf_regnum: u16, size_in_bytes: u8) -> Self {
+        LiveOut { dwarf_regnum: dwarf_regnum, reserved: 0, size_in_bytes: size_in_bytes }
+    }
+}
+
+/// Builds a `StackMap` of a given format version.
+///
+/// Records belong to the function most recently added with `function`.
+#[derive(Clone, Debug)]
+pub struct StackMapBuilder {
+    version: u8,
+    stack_sizes: Vec<StackSize>,
+    large_constants: Vec<LargeConstant>,
+    records: Vec<Record>,
+}
+
+impl StackMapBuilder {
+    pub fn new(version: u8) -> Self {
+        StackMapBuilder {
+            version: version,
+            stack_sizes: Vec::new(),
+            large_constants: Vec::new(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Starts the records of the function at `address`.
+    pub fn function(&mut self, address: u64, stack_size: u64) -> &mut Self {
+        let record_count = if self.version == 1 { None } else { Some(0) };
+        self.stack_sizes.push(StackSize {
+            function_address: address,
+            stack_size: stack_size,
+            record_count: record_count,
+        });
+        self
+    }
+
+    /// Returns a location variant referring to the large constant `value`,
+    /// adding it to the constant pool unless it is already there.
+    pub fn large_constant(&mut self, value: u64) -> LocationVariant {
+        let index = match self.large_constants.iter().position(|c| c.value == value) {
+            Some(index) => index,
+            None => {
+                self.large_constants.push(LargeConstant { value: value });
+                self.large_constants.len() - 1
+            }
+        };
+        LocationVariant::ConstIndex { offset: index as i32 }
+    }
+
+    /// Adds a record to the current function.
+    ///
+    /// Fails if no function has been started, or if a location is larger
+    /// than versions 1 and 2 can describe in their single size byte.
+    pub fn record(&mut self,
+                  patchpoint_id: u64,
+                  instruction_offset: u32,
+                  locations: Vec<Location>,
+                  live_outs: Vec<LiveOut>) -> Result<&mut Self, BuildError> {
+        let index = self.records.len();
+        if self.stack_sizes.is_empty() {
+            return Err(BuildError::NoFunction { record: index });
+        }
+        if self.version < 3 {
+            if let Some(i) = locations.iter().position(|l| l.size > u8::max_value() as u16) {
+                return Err(BuildError::LocationTooLarge {
+                    record: index,
+                    location: i,
+                    size: locations[i].size,
+                });
+            }
+        }
+        // Records start 8-byte aligned; version 3 realigns after the
+        // 12-byte locations, and every record ends aligned
+        let padding_0 = if self.version >= 3 && locations.len() % 2 == 1 { Some(0) } else { None };
+        let padding_2 = if live_outs.len() % 2 == 0 { Some(0) } else { None };
+        if let Some(ss) = self.stack_sizes.last_mut() {
+            ss.record_count = ss.record_count.map(|c| c + 1);
+        }
+        self.records.push(Record {
+            patchpoint_id: patchpoint_id,
+            instruction_offset: instruction_offset,
+            reserved: 0,
+            num_locations: NumLocations(locations.len() as u16),
+            locations: locations,
+            padding_0: padding_0,
+            padding_1: 0,
+            num_live_outs: NumLiveOuts(live_outs.len() as u16),
+            live_outs: live_outs,
+            padding_2: padding_2,
+        });
+        Ok(self)
+    }
+
+    pub fn build(self) -> Result<StackMap, BuildError> {
+        if !SUPPORTED_VERSIONS.contains(&self.version) {
+            return Err(HeaderError::UnknownVersion(self.version).into());
+        }
+        Ok(StackMap {
+            header: Header { version: self.version, reserved_1: 0, reserved_2: 0 },
+            stack_sizes: self.stack_sizes,
+            large_constants: self.large_constants,
+            records: self.records,
+        })
+    }
+}
+
+struct Out<T: ByteOrder> {
+    bytes: Vec<u8>,
+    _order: PhantomData<T>,
+}
+
+impl<T: ByteOrder> Out<T> {
+    fn u8(&mut self, x: u8) { self.bytes.push(x); }
+    fn u16(&mut self, x: u16) { let mut b = [0; 2]; T::write_u16(&mut b, x); self.bytes.extend(&b); }
+    fn u32(&mut self, x: u32) { let mut b = [0; 4]; T::write_u32(&mut b, x); self.bytes.extend(&b); }
+    fn i32(&mut self, x: i32) { let mut b = [0; 4]; T::write_i32(&mut b, x); self.bytes.extend(&b); }
+    fn u64(&mut self, x: u64) { let mut b = [0; 8]; T::write_u64(&mut b, x); self.bytes.extend(&b); }
+
+    /// Pads to 8 bytes with the 4-byte `padding`, if needed.
+    fn align8(&mut self, padding: Option<u32>) {
+        if self.bytes.len() % 8 != 0 {
+            self.u32(padding.unwrap_or(0));
+        }
+    }
+}
+
+impl StackMap {
+    /// Serializes the map in the layout of its header's version.
+    ///
+    /// Versions 1 and 2 store location sizes in a single byte, so larger
+    /// sizes, which `StackMapBuilder` rejects, are truncated.
+    pub fn write<T: ByteOrder>(&self) -> Vec<u8> {
+        let mut out = Out::<T> { bytes: Vec::new(), _order: PhantomData };
+        let v = self.header.version;
+        out.u8(v);
+        out.u8(self.header.reserved_1);
+        out.u16(self.header.reserved_2);
+        out.u32(self.stack_sizes.len() as u32);
+        out.u32(self.large_constants.len() as u32);
+        out.u32(self.records.len() as u32);
+        for ss in self.stack_sizes.iter() {
+            out.u64(ss.function_address);
+            out.u64(ss.stack_size);
+            if v >= 2 {
+                out.u64(ss.record_count.unwrap_or(0));
+            }
+        }
+        for c in self.large_constants.iter() {
+            out.u64(c.value);
+        }
+        for r in self.records.iter() {
+            out.u64(r.patchpoint_id);
+            out.u32(r.instruction_offset);
+            out.u16(r.reserved);
+            out.u16(r.locations.len() as u16);
+            for l in r.locations.iter() {
+                let (tag, regnum, offset) = match l.variant {
+                    LocationVariant::Register { dwarf_regnum } => (1, dwarf_regnum, 0),
+                    LocationVariant::Direct { dwarf_regnum, offset } => (2, dwarf_regnum, offset),
+                    LocationVariant::Indirect { dwarf_regnum, offset } => (3, dwarf_regnum, offset),
+                    LocationVariant::Constant { value } => (4, 0, value),
+                    LocationVariant::ConstIndex { offset } => (5, 0, offset),
+                };
+                out.u8(tag);
+                if v < 3 {
+                    out.u8(l.size as u8);
+                    out.u16(regnum);
+                } else {
+                    out.u8(l.reserved);
+                    out.u16(l.size);
+                    out.u16(regnum);
+                    out.u16(l.reserved_2);
+                }
+                out.i32(offset);
+            }
+            out.align8(r.padding_0);
+            out.u16(r.padding_1);
+            out.u16(r.live_outs.len() as u16);
+            for lo in r.live_outs.iter() {
+                out.u16(lo.dwarf_regnum);
+                out.u8(lo.reserved);
+                out.u8(lo.size_in_bytes);
+            }
+            out.align8(r.padding_2);
+        }
+        out.bytes
+    }
+}
@@ -31,4 +31,30 @@ impl Cursor {
     pub fn pc(&self) -> u64 {
         self.registers.pc as u64
     }
+
+    /// Value of the register with ARM DWARF number `dwarf_regnum` (see
+    /// `registers::Arch::Arm`).
+    pub fn register(&self, dwarf_regnum: u16) -> Option<u64> {
+        let r = &self.registers;
+        let value = match dwarf_regnum {
+            0 => r.r0,
+            1 => r.r1,
+            2 => r.r2,
+            3 => r.r3,
+            4 => r.r4,
+            5 => r.r5,
+            6 => r.r6,
+            7 => r.r7,
+            8 => r.r8,
+            9 => r.r9,
+            10 => r.r10,
+            11 => r.r11,
+            12 => r.ip,
+            13 => r.sp,
+            14 => r.lr,
+            15 => r.pc,
+            _ => return None,
+        };
+        Some(value as u64)
+    }
 }
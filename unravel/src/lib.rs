@@ -1,6 +1,7 @@
 mod arm;
 mod x86;
 pub mod dwarf;
+pub mod registers;
 
 #[cfg(test)]
 mod test;
@@ -35,4 +36,13 @@ impl Cursor {
             Cursor::X86(ref x86) => x86.pc(),
         }
     }
+
+    /// Value of the register with DWARF number `dwarf_regnum`, if the
+    /// cursor knows it
+    pub fn register(&self, dwarf_regnum: u16) -> Option<u64> {
+        match *self {
+            Cursor::ARM(ref arm) => arm.register(dwarf_regnum),
+            Cursor::X86(ref x86) => x86.register(dwarf_regnum),
+        }
+    }
 }
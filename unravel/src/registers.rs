@@ -0,0 +1,303 @@
+//! Register numbering of the supported architectures.
+//!
+//! DWARF call frame information and LLVM stack maps name registers by
+//! their DWARF number. libunwind, the `ucontext_t` a signal handler
+//! receives, and the register sets of core dumps and ptrace each number
+//! them differently, so reading a register named in DWARF goes through
+//! the tables here.
+
+/// An architecture whose register numbering is known
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Arch {
+    X86_64,
+    I386,
+    AArch64,
+    Arm,
+}
+
+/// A register and its number in each numbering
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Register {
+    /// DWARF register number
+    pub dwarf: u16,
+    /// Name of the register, as used by the assembler and by the
+    /// register names of `elf::coredump`
+    pub name: &'static str,
+    /// libunwind register number (`unw_regnum_t`), if libunwind can
+    /// read the register. Vector registers are read with `unw_get_fpreg`.
+    pub libunwind: Option<i32>,
+    /// Index of the register among the general-purpose registers of the
+    /// `mcontext_t` in a `ucontext_t`, if they include it
+    pub ucontext: Option<usize>,
+    /// Index of the register in the `elf_gregset_t` of core dumps and
+    /// of `PTRACE_GETREGSET`, if it includes it
+    pub gregset: Option<usize>,
+}
+
+/// libunwind's number for the instruction pointer (`UNW_REG_IP`), on
+/// machines where it has no number of its own
+const UNW_REG_IP: i32 = -1;
+
+macro_rules! registers {
+    ($($dwarf:expr => $name:expr, $libunwind:expr, $ucontext:expr, $gregset:expr;)*) => {
+        &[$(Register {
+            dwarf: $dwarf,
+            name: $name,
+            libunwind: $libunwind,
+            ucontext: $ucontext,
+            gregset: $gregset,
+        }),*]
+    }
+}
+
+/// x86-64 psABI numbering. libunwind follows DWARF; the `ucontext`
+/// indices are glibc's `REG_*` constants.
+const X86_64: &'static [Register] = registers! {
+    0 => "rax", Some(0), Some(13), Some(10);
+    1 => "rdx", Some(1), Some(12), Some(12);
+    2 => "rcx", Some(2), Some(14), Some(11);
+    3 => "rbx", Some(3), Some(11), Some(5);
+    4 => "rsi", Some(4), Some(9), Some(13);
+    5 => "rdi", Some(5), Some(8), Some(14);
+    6 => "rbp", Some(6), Some(10), Some(4);
+    7 => "rsp", Some(7), Some(15), Some(19);
+    8 => "r8", Some(8), Some(0), Some(9);
+    9 => "r9", Some(9), Some(1), Some(8);
+    10 => "r10", Some(10), Some(2), Some(7);
+    11 => "r11", Some(11), Some(3), Some(6);
+    12 => "r12", Some(12), Some(4), Some(3);
+    13 => "r13", Some(13), Some(5), Some(2);
+    14 => "r14", Some(14), Some(6), Some(1);
+    15 => "r15", Some(15), Some(7), Some(0);
+    16 => "rip", Some(UNW_REG_IP), Some(16), Some(16);
+    17 => "xmm0", None, None, None;
+    18 => "xmm1", None, None, None;
+    19 => "xmm2", None, None, None;
+    20 => "xmm3", None, None, None;
+    21 => "xmm4", None, None, None;
+    22 => "xmm5", None, None, None;
+    23 => "xmm6", None, None, None;
+    24 => "xmm7", None, None, None;
+    25 => "xmm8", None, None, None;
+    26 => "xmm9", None, None, None;
+    27 => "xmm10", None, None, None;
+    28 => "xmm11", None, None, None;
+    29 => "xmm12", None, None, None;
+    30 => "xmm13", None, None, None;
+    31 => "xmm14", None, None, None;
+    32 => "xmm15", None, None, None;
+    49 => "eflags", None, Some(17), Some(18);
+};
+
+/// i386 System V numbering. libunwind swaps esp and ebp relative to
+/// DWARF; the `ucontext` indices are glibc's `REG_*` constants.
+const I386: &'static [Register] = registers! {
+    0 => "eax", Some(0), Some(11), Some(6);
+    1 => "ecx", Some(1), Some(10), Some(1);
+    2 => "edx", Some(2), Some(9), Some(2);
+    3 => "ebx", Some(3), Some(8), Some(0);
+    4 => "esp", Some(5), Some(7), Some(15);
+    5 => "ebp", Some(4), Some(6), Some(5);
+    6 => "esi", Some(6), Some(5), Some(3);
+    7 => "edi", Some(7), Some(4), Some(4);
+    8 => "eip", Some(UNW_REG_IP), Some(14), Some(12);
+    9 => "eflags", None, Some(16), Some(14);
+    21 => "xmm0", None, None, None;
+    22 => "xmm1", None, None, None;
+    23 => "xmm2", None, None, None;
+    24 => "xmm3", None, None, None;
+    25 => "xmm4", None, None, None;
+    26 => "xmm5", None, None, None;
+    27 => "xmm6", None, None, None;
+    28 => "xmm7", None, None, None;
+};
+
+/// AArch64 numbering. libunwind follows DWARF; the `ucontext` indices
+/// count from `regs[0]` of the `sigcontext`, which `sp` and `pc` follow.
+const AARCH64: &'static [Register] = registers! {
+    0 => "x0", Some(0), Some(0), Some(0);
+    1 => "x1", Some(1), Some(1), Some(1);
+    2 => "x2", Some(2), Some(2), Some(2);
+    3 => "x3", Some(3), Some(3), Some(3);
+    4 => "x4", Some(4), Some(4), Some(4);
+    5 => "x5", Some(5), Some(5), Some(5);
+    6 => "x6", Some(6), Some(6), Some(6);
+    7 => "x7", Some(7), Some(7), Some(7);
+    8 => "x8", Some(8), Some(8), Some(8);
+    9 => "x9", Some(9), Some(9), Some(9);
+    10 => "x10", Some(10), Some(10), Some(10);
+    11 => "x11", Some(11), Some(11), Some(11);
+    12 => "x12", Some(12), Some(12), Some(12);
+    13 => "x13", Some(13), Some(13), Some(13);
+    14 => "x14", Some(14), Some(14), Some(14);
+    15 => "x15", Some(15), Some(15), Some(15);
+    16 => "x16", Some(16), Some(16), Some(16);
+    17 => "x17", Some(17), Some(17), Some(17);
+    18 => "x18", Some(18), Some(18), Some(18);
+    19 => "x19", Some(19), Some(19), Some(19);
+    20 => "x20", Some(20), Some(20), Some(20);
+    21 => "x21", Some(21), Some(21), Some(21);
+    22 => "x22", Some(22), Some(22), Some(22);
+    23 => "x23", Some(23), Some(23), Some(23);
+    24 => "x24", Some(24), Some(24), Some(24);
+    25 => "x25", Some(25), Some(25), Some(25);
+    26 => "x26", Some(26), Some(26), Some(26);
+    27 => "x27", Some(27), Some(27), Some(27);
+    28 => "x28", Some(28), Some(28), Some(28);
+    29 => "x29", Some(29), Some(29), Some(29);
+    30 => "x30", Some(30), Some(30), Some(30);
+    31 => "sp", Some(31), Some(31), Some(31);
+    64 => "v0", Some(64), None, None;
+    65 => "v1", Some(65), None, None;
+    66 => "v2", Some(66), None, None;
+    67 => "v3", Some(67), None, None;
+    68 => "v4", Some(68), None, None;
+    69 => "v5", Some(69), None, None;
+    70 => "v6", Some(70), None, None;
+    71 => "v7", Some(71), None, None;
+    72 => "v8", Some(72), None, None;
+    73 => "v9", Some(73), None, None;
+    74 => "v10", Some(74), None, None;
+    75 => "v11", Some(75), None, None;
+    76 => "v12", Some(76), None, None;
+    77 => "v13", Some(77), None, None;
+    78 => "v14", Some(78), None, None;
+    79 => "v15", Some(79), None, None;
+    80 => "v16", Some(80), None, None;
+    81 => "v17", Some(81), None, None;
+    82 => "v18", Some(82), None, None;
+    83 => "v19", Some(83), None, None;
+    84 => "v20", Some(84), None, None;
+    85 => "v21", Some(85), None, None;
+    86 => "v22", Some(86), None, None;
+    87 => "v23", Some(87), None, None;
+    88 => "v24", Some(88), None, None;
+    89 => "v25", Some(89), None, None;
+    90 => "v26", Some(90), None, None;
+    91 => "v27", Some(91), None, None;
+    92 => "v28", Some(92), None, None;
+    93 => "v29", Some(93), None, None;
+    94 => "v30", Some(94), None, None;
+    95 => "v31", Some(95), None, None;
+};
+
+/// ARM numbering. libunwind follows DWARF; the `ucontext` indices count
+/// from `arm_r0` of the `sigcontext`, which `arm_cpsr` ends.
+const ARM: &'static [Register] = registers! {
+    0 => "r0", Some(0), Some(0), Some(0);
+    1 => "r1", Some(1), Some(1), Some(1);
+    2 => "r2", Some(2), Some(2), Some(2);
+    3 => "r3", Some(3), Some(3), Some(3);
+    4 => "r4", Some(4), Some(4), Some(4);
+    5 => "r5", Some(5), Some(5), Some(5);
+    6 => "r6", Some(6), Some(6), Some(6);
+    7 => "r7", Some(7), Some(7), Some(7);
+    8 => "r8", Some(8), Some(8), Some(8);
+    9 => "r9", Some(9), Some(9), Some(9);
+    10 => "r10", Some(10), Some(10), Some(10);
+    11 => "r11", Some(11), Some(11), Some(11);
+    12 => "r12", Some(12), Some(12), Some(12);
+    13 => "sp", Some(13), Some(13), Some(13);
+    14 => "lr", Some(14), Some(14), Some(14);
+    15 => "pc", Some(15), Some(15), Some(15);
+    256 => "d0", Some(256), None, None;
+    257 => "d1", Some(257), None, None;
+    258 => "d2", Some(258), None, None;
+    259 => "d3", Some(259), None, None;
+    260 => "d4", Some(260), None, None;
+    261 => "d5", Some(261), None, None;
+    262 => "d6", Some(262), None, None;
+    263 => "d7", Some(263), None, None;
+    264 => "d8", Some(264), None, None;
+    265 => "d9", Some(265), None, None;
+    266 => "d10", Some(266), None, None;
+    267 => "d11", Some(267), None, None;
+    268 => "d12", Some(268), None, None;
+    269 => "d13", Some(269), None, None;
+    270 => "d14", Some(270), None, None;
+    271 => "d15", Some(271), None, None;
+    272 => "d16", Some(272), None, None;
+    273 => "d17", Some(273), None, None;
+    274 => "d18", Some(274), None, None;
+    275 => "d19", Some(275), None, None;
+    276 => "d20", Some(276), None, None;
+    277 => "d21", Some(277), None, None;
+    278 => "d22", Some(278), None, None;
+    279 => "d23", Some(279), None, None;
+    280 => "d24", Some(280), None, None;
+    281 => "d25", Some(281), None, None;
+    282 => "d26", Some(282), None, None;
+    283 => "d27", Some(283), None, None;
+    284 => "d28", Some(284), None, None;
+    285 => "d29", Some(285), None, None;
+    286 => "d30", Some(286), None, None;
+    287 => "d31", Some(287), None, None;
+};
+
+impl Arch {
+    /// The architecture of an ELF file's `e_machine`
+    pub fn from_elf_machine(machine: u16) -> Option<Arch> {
+        match machine {
+            62 => Some(Arch::X86_64),
+            3 => Some(Arch::I386),
+            183 => Some(Arch::AArch64),
+            40 => Some(Arch::Arm),
+            _ => None,
+        }
+    }
+
+    /// The architecture this code runs on, if it is a supported one
+    pub fn native() -> Option<Arch> {
+        if cfg!(target_arch = "x86_64") {
+            Some(Arch::X86_64)
+        } else if cfg!(target_arch = "x86") {
+            Some(Arch::I386)
+        } else if cfg!(target_arch = "aarch64") {
+            Some(Arch::AArch64)
+        } else if cfg!(target_arch = "arm") {
+            Some(Arch::Arm)
+        } else {
+            None
+        }
+    }
+
+    /// The known registers, in DWARF order
+    pub fn registers(self) -> &'static [Register] {
+        match self {
+            Arch::X86_64 => X86_64,
+            Arch::I386 => I386,
+            Arch::AArch64 => AARCH64,
+            Arch::Arm => ARM,
+        }
+    }
+
+    /// The register with DWARF number `dwarf`
+    pub fn register(self, dwarf: u16) -> Option<&'static Register> {
+        self.registers().iter().find(|r| r.dwarf == dwarf)
+    }
+
+    /// The register called `name`
+    pub fn register_by_name(self, name: &str) -> Option<&'static Register> {
+        self.registers().iter().find(|r| r.name == name)
+    }
+
+    /// Name of the register with DWARF number `dwarf`
+    pub fn register_name(self, dwarf: u16) -> Option<&'static str> {
+        self.register(dwarf).map(|r| r.name)
+    }
+
+    /// libunwind's number for the register with DWARF number `dwarf`
+    pub fn libunwind_regnum(self, dwarf: u16) -> Option<i32> {
+        self.register(dwarf).and_then(|r| r.libunwind)
+    }
+
+    /// `ucontext_t` index of the register with DWARF number `dwarf`
+    pub fn ucontext_index(self, dwarf: u16) -> Option<usize> {
+        self.register(dwarf).and_then(|r| r.ucontext)
+    }
+
+    /// `elf_gregset_t` index of the register with DWARF number `dwarf`
+    pub fn gregset_index(self, dwarf: u16) -> Option<usize> {
+        self.register(dwarf).and_then(|r| r.gregset)
+    }
+}
@@ -1,4 +1,41 @@
+use super::registers::Arch;
+
 #[test]
 fn it_works() {
 
 }
+
+#[test]
+fn tables_are_consistent() {
+    for &arch in [Arch::X86_64, Arch::I386, Arch::AArch64, Arch::Arm].iter() {
+        let regs = arch.registers();
+        for (i, r) in regs.iter().enumerate() {
+            if i > 0 {
+                assert!(regs[i - 1].dwarf < r.dwarf, "{:?} {} out of order", arch, r.name);
+            }
+            assert_eq!(arch.register_by_name(r.name), Some(r));
+            let same_libunwind = regs.iter().filter(|s| r.libunwind.is_some() && s.libunwind == r.libunwind);
+            assert!(same_libunwind.count() <= 1, "{:?} {} libunwind", arch, r.name);
+            let same_ucontext = regs.iter().filter(|s| r.ucontext.is_some() && s.ucontext == r.ucontext);
+            assert!(same_ucontext.count() <= 1, "{:?} {} ucontext", arch, r.name);
+            let same_gregset = regs.iter().filter(|s| r.gregset.is_some() && s.gregset == r.gregset);
+            assert!(same_gregset.count() <= 1, "{:?} {} gregset", arch, r.name);
+        }
+    }
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(Arch::X86_64.register_name(6), Some("rbp"));
+    assert_eq!(Arch::X86_64.libunwind_regnum(6), Some(6));
+    assert_eq!(Arch::X86_64.ucontext_index(6), Some(10));
+    assert_eq!(Arch::I386.register_name(4), Some("esp"));
+    assert_eq!(Arch::I386.libunwind_regnum(4), Some(5));
+    assert_eq!(Arch::I386.ucontext_index(8), Some(14));
+    assert_eq!(Arch::I386.gregset_index(8), Some(12));
+    assert_eq!(Arch::X86_64.gregset_index(7), Some(19));
+    assert_eq!(Arch::AArch64.register_name(31), Some("sp"));
+    assert_eq!(Arch::Arm.register_name(14), Some("lr"));
+    assert_eq!(Arch::X86_64.register_name(40), None);
+    assert_eq!(Arch::from_elf_machine(62), Some(Arch::X86_64));
+}
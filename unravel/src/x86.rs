@@ -24,4 +24,23 @@ impl Cursor {
     pub fn pc(&self) -> u64 {
         self.registers.eip as u64
     }
+
+    /// Value of the register with i386 DWARF number `dwarf_regnum` (see
+    /// `registers::Arch::I386`).
+    pub fn register(&self, dwarf_regnum: u16) -> Option<u64> {
+        let r = &self.registers;
+        let value = match dwarf_regnum {
+            0 => r.eax,
+            1 => r.ecx,
+            2 => r.edx,
+            3 => r.ebx,
+            4 => r.esp,
+            5 => r.ebp,
+            6 => r.esi,
+            7 => r.edi,
+            8 => r.eip,
+            _ => return None,
+        };
+        Some(value as u64)
+    }
 }
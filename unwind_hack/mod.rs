@@ -4,33 +4,47 @@
 #![allow(non_camel_case_types)]
 #![allow(dead_code)]
 
-use libc::{self, c_char, c_int, size_t};
-use std::mem;
+use libc::{c_char, c_int, size_t};
+use std::cmp;
+use std::error;
 use std::ffi::CStr;
+use std::fmt;
+use std::marker::PhantomData;
 
-#[repr(i32)]
-#[derive(Debug)]
+// Remote unwinding needs the address spaces of the nongnu libunwind,
+// which the LLVM fork lacks; build with `--cfg libunwind_nongnu` to
+// link it for the remote cursors of `ptrace`.
+#[cfg(all(libunwind_nongnu, target_arch = "x86_64"))]
+pub mod ptrace;
+
+#[cfg(test)]
+mod test;
+
+/// An error reported by libunwind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UnwindError {
     /// unspecified (general) error
-    Unspec       = -6540,
+    Unspec,
     /// out of memory
-    NoMem        = -6541,
+    NoMem,
     /// bad register number
-    BadReg       = -6542,
+    BadReg,
     /// attempt to write read-only register
-    ReadOnlyReg  = -6543,
+    ReadOnlyReg,
     /// stop unwinding
-    StopUnwind   = -6544,
+    StopUnwind,
     /// invalid IP
-    InvalidIP    = -6545,
+    InvalidIP,
     /// bad frame
-    BadFrame     = -6546,
+    BadFrame,
     /// unsupported operation or bad value
-    Invalid      = -6547,
+    Invalid,
     /// unwind info has unsupported version
-    BadVersion   = -6548,
+    BadVersion,
     /// no unwind info found
-    NoInfo       = -6549,
+    NoInfo,
+    /// an error code not among the above
+    Other(i32),
 }
 #[repr(i32)]
 #[derive(Debug)]
@@ -38,25 +52,25 @@ pub enum ErrorCode {
     /// no error
     SUCCESS      = 0,
     /// unspecified (general) error
-    UNSPEC       = UnwindError::Unspec as i32,
+    UNSPEC       = -6540,
     /// out of memory
-    NOMEM        = UnwindError::NoMem as i32,
+    NOMEM        = -6541,
     /// bad register number
-    BADREG       = UnwindError::BadReg as i32,
+    BADREG       = -6542,
     /// attempt to write read-only register
-    READONLYREG  = UnwindError::ReadOnlyReg as i32,
+    READONLYREG  = -6543,
     /// stop unwinding
-    STOPUNWIND   = UnwindError::StopUnwind as i32,
+    STOPUNWIND   = -6544,
     /// invalid IP
-    INVALIDIP    = UnwindError::InvalidIP as i32,
+    INVALIDIP    = -6545,
     /// bad frame
-    BADFRAME     = UnwindError::BadFrame as i32,
+    BADFRAME     = -6546,
     /// unsupported operation or bad value
-    INVAL        = UnwindError::Invalid as i32,
+    INVAL        = -6547,
     /// unwind info has unsupported version
-    BADVERSION   = UnwindError::BadVersion as i32,
+    BADVERSION   = -6548,
     /// no unwind info found
-    NOINFO       = UnwindError::NoInfo as i32,
+    NOINFO       = -6549,
 }
 pub const UNW_ESUCCESS: ErrorCode = ErrorCode::SUCCESS;
 pub const UNW_EUNSPEC: ErrorCode = ErrorCode::UNSPEC;
@@ -71,6 +85,8 @@ pub const UNW_EBADVERSION: ErrorCode = ErrorCode::BADVERSION;
 pub const UNW_ENOINFO: ErrorCode = ErrorCode::NOINFO;
 
 const UNW_CONTEXT_LEN: usize = 128;
+/// Longest procedure name `UnwindCursor::get_proc_name` makes room for
+const MAX_PROC_NAME_LEN: usize = 1 << 16;
 
 #[repr(C)]
 pub struct unw_context_t { data: [u64; UNW_CONTEXT_LEN] }
@@ -97,6 +113,7 @@ pub type unw_word_t = u64;
 pub type unw_fpreg_t = f64;
 
 #[repr(C)]
+#[derive(Default)]
 pub struct unw_proc_info_t {
     /// start address of function
     start_ip: unw_word_t,
@@ -137,6 +154,40 @@ extern {
     static unw_local_addr_space: unw_addr_space_t;
 }
 
+/// The functions of the libunwind that initialized a cursor, which the
+/// cursor must be used with
+struct Library {
+    step: unsafe extern "C" fn(*mut unw_cursor_t) -> c_int,
+    get_reg: unsafe extern "C" fn(*const unw_cursor_t, unw_regnum_t, *mut unw_word_t) -> c_int,
+    get_fpreg: unsafe extern "C" fn(*const unw_cursor_t, unw_regnum_t, *mut unw_fpreg_t) -> c_int,
+    resume: unsafe extern "C" fn(*const unw_cursor_t) -> c_int,
+    get_proc_info: unsafe extern "C" fn(*const unw_cursor_t, *mut unw_proc_info_t) -> c_int,
+    is_signal_frame: unsafe extern "C" fn(*const unw_cursor_t) -> c_int,
+    get_proc_name: unsafe extern "C" fn(*const unw_cursor_t, *mut c_char, size_t,
+                                        *mut unw_word_t) -> c_int,
+    /// The library's number for a register numbered as in the LLVM fork
+    regnum: fn(unw_regnum_t) -> unw_regnum_t,
+    /// The error for a negative code the library returned
+    error: fn(c_int) -> UnwindError,
+}
+
+fn same_regnum(regnum: unw_regnum_t) -> unw_regnum_t {
+    regnum
+}
+
+/// The LLVM fork, which unwinds the calling process
+static LLVM: Library = Library {
+    step: unw_step,
+    get_reg: unw_get_reg,
+    get_fpreg: unw_get_fpreg,
+    resume: unw_resume,
+    get_proc_info: unw_get_proc_info,
+    is_signal_frame: unw_is_signal_frame,
+    get_proc_name: unw_get_proc_name,
+    regnum: same_regnum,
+    error: UnwindError::from_code,
+};
+
 macro_rules! enum_as_consts {
     ($Enum:ident, $($UNW_NAME:ident = $Variant:ident),*) => {
         $( pub const $UNW_NAME: unw_regnum_t = $Enum::$Variant as unw_regnum_t; )*
@@ -591,133 +642,313 @@ enum_as_consts!(OR1KReg,
                 UNW_OR1K_R30 = R30,
                 UNW_OR1K_R31 = R31);
 
+macro_rules! error_codes {
+    ($($Variant:ident = $CODE:ident: $description:expr),*) => {
+        impl UnwindError {
+            /// The error for the negative code a function of the LLVM fork
+            /// returned
+            pub fn from_code(code: c_int) -> UnwindError {
+                match code {
+                    $(x if x == ErrorCode::$CODE as c_int => UnwindError::$Variant,)*
+                    x => UnwindError::Other(x),
+                }
+            }
+
+            /// The code the LLVM fork reports the error with
+            pub fn code(&self) -> c_int {
+                match *self {
+                    $(UnwindError::$Variant => ErrorCode::$CODE as c_int,)*
+                    UnwindError::Other(x) => x,
+                }
+            }
+        }
+
+        impl fmt::Display for UnwindError {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match *self {
+                    $(UnwindError::$Variant => write!(f, $description),)*
+                    UnwindError::Other(x) => write!(f, "libunwind error {}", x),
+                }
+            }
+        }
+
+        impl error::Error for UnwindError {
+            fn description(&self) -> &str {
+                match *self {
+                    $(UnwindError::$Variant => $description,)*
+                    UnwindError::Other(_) => "libunwind error",
+                }
+            }
+        }
+    }
+}
+
+error_codes!(Unspec = UNSPEC: "unspecified libunwind error",
+             NoMem = NOMEM: "out of memory",
+             BadReg = BADREG: "bad register number",
+             ReadOnlyReg = READONLYREG: "attempt to write read-only register",
+             StopUnwind = STOPUNWIND: "stop unwinding",
+             InvalidIP = INVALIDIP: "invalid instruction pointer",
+             BadFrame = BADFRAME: "bad frame",
+             Invalid = INVAL: "unsupported operation or bad value",
+             BadVersion = BADVERSION: "unwind info has unsupported version",
+             NoInfo = NOINFO: "no unwind info found");
+
+/// The registers of a thread, captured by `UnwindContext::with`, from
+/// which its stack can be unwound.
 pub struct UnwindContext {
     repr: unw_context_t,
 }
 
-pub struct UnwindCursor {
+/// A position on a stack. The cursor borrows the `UnwindContext` or
+/// `ptrace::TracedProcess` it unwinds, which libunwind may read from
+/// while unwinding.
+pub struct UnwindCursor<'a> {
     repr: unw_cursor_t,
+    library: &'static Library,
+    context: PhantomData<&'a ()>,
 }
 
 impl UnwindContext {
-    pub fn new() -> UnwindContext {
-        unsafe {
-            let mut c = UnwindContext { repr: mem::uninitialized() };
-            if 0 != unw_getcontext(&mut c.repr as *mut _) {
-                panic!("got error from unw_getcontext");
-            }
-            return c;
+    /// Captures the registers of the calling thread and passes them to
+    /// `f`. The context describes the frame of `with` itself, which
+    /// lives until `f` returns, and `f` only borrows it, so it cannot be
+    /// unwound after that frame is gone.
+    #[inline(never)]
+    pub fn with<F, R>(f: F) -> Result<R, UnwindError>
+        where F: FnOnce(&UnwindContext) -> R
+    {
+        let mut c = UnwindContext { repr: unw_context_t { data: [0; UNW_CONTEXT_LEN] } };
+        match unsafe { unw_getcontext(&mut c.repr as *mut _) } {
+            0 => Ok(f(&c)),
+            code => Err(UnwindError::from_code(code)),
         }
     }
-    pub fn cursor(&self) -> UnwindCursor {
-        unsafe {
-            let mut c = UnwindCursor { repr: mem::uninitialized() };
-            if 0 != unw_init_local(&mut c.repr as *mut _, &self.repr as *const _) {
-                panic!("got error from unw_init_local");
-            }
-            return c;
+
+    /// A cursor on the frame of the function that called `with`
+    pub fn cursor(&self) -> Result<UnwindCursor, UnwindError> {
+        let mut c = UnwindCursor::blank(&LLVM);
+        match unsafe { unw_init_local(&mut c.repr as *mut _, &self.repr as *const _) } {
+            0 => {}
+            code => return Err(UnwindError::from_code(code)),
+        }
+        // The context was captured in the frame of `with`
+        match c.step() {
+            Ok(true) => Ok(c),
+            Ok(false) => Err(UnwindError::BadFrame),
+            Err(e) => Err(e),
         }
     }
 }
 
-macro_rules! match_err {
-    ($val:ident, $($Variant:ident),*; $default:expr) => {
-        match $val {
-            $(x if x == UnwindError::$Variant as c_int => UnwindError::$Variant),*,
-            _ => $default,
+impl<'a> Clone for UnwindCursor<'a> {
+    /// libunwind cursors are plain data, so a copy unwinds independently
+    /// of the original.
+    fn clone(&self) -> Self {
+        UnwindCursor {
+            repr: unw_cursor_t { data: self.repr.data },
+            library: self.library,
+            context: PhantomData,
         }
     }
 }
 
-macro_rules! match_errs {
-    ($val:ident, $default:expr) => {
-        match_err!($val, Unspec, NoMem, BadReg, ReadOnlyReg, StopUnwind,
-                   InvalidIP, BadFrame, Invalid, BadVersion, NoInfo;
-                   $default)
+impl<'a> UnwindCursor<'a> {
+    /// A cursor for `library` to initialize
+    fn blank(library: &'static Library) -> Self {
+        UnwindCursor {
+            repr: unw_cursor_t { data: [0; UNW_CURSOR_LEN] },
+            library: library,
+            context: PhantomData,
+        }
     }
-}
 
-impl UnwindCursor {
-    fn ffi_ret<C>(&self, context: &'static str, c: C) -> Result<c_int, UnwindError>
+    fn ffi_ret<C>(&self, c: C) -> Result<c_int, UnwindError>
         where C: FnOnce(*const unw_cursor_t) -> c_int
     {
         match c(&self.repr as *const _) {
             x if x >= 0 => Ok(x),
-            x => Err(match_errs!(x, panic!("got error from {}", context))),
+            x => Err((self.library.error)(x)),
         }
     }
 
-    fn ffi<T, C>(&self, context: &'static str, c: C) -> Result<T, UnwindError>
-        where C: FnOnce(*const unw_cursor_t, *mut T) -> c_int
+    fn ffi<T, C>(&self, c: C) -> Result<T, UnwindError>
+        where T: Default, C: FnOnce(*const unw_cursor_t, *mut T) -> c_int
     {
-        unsafe {
-            let mut t: T = mem::uninitialized();
-            match self.ffi_ret(context, |cursor| c(cursor, &mut t as *mut _)) {
-                Ok(_) => Ok(t),
-                Err(e) => { mem::forget(t); Err(e) }
-            }
-        }
+        let mut t = T::default();
+        self.ffi_ret(|cursor| c(cursor, &mut t as *mut _)).map(|_| t)
     }
-    
-    pub fn step(self) -> Result<Option<UnwindCursor>, UnwindError> {
-        self.ffi_ret("unw_step", |cursor| unsafe { unw_step(cursor as *mut _) })
-            .map(|x| if x == 0 { None } else { Some(self) })
+
+    /// Moves the cursor to the caller's frame. Returns `false`, leaving
+    /// the cursor in place, if the current frame is the outermost one.
+    pub fn step(&mut self) -> Result<bool, UnwindError> {
+        let cursor = &mut self.repr as *mut _;
+        let step = self.library.step;
+        self.ffi_ret(|_| unsafe { step(cursor) })
+            .map(|x| x != 0)
+    }
+
+    /// The frames from the cursor's to the outermost one
+    pub fn frames(self) -> Frames<'a> {
+        Frames { cursor: self, started: false, done: false }
     }
 
     pub fn get_reg(&self, reg: unw_regnum_t) -> Result<unw_word_t, UnwindError> {
-        self.ffi("unw_get_reg", |cursor, p_word| unsafe { unw_get_reg(cursor, reg, p_word) })
+        let (get_reg, reg) = (self.library.get_reg, (self.library.regnum)(reg));
+        self.ffi(|cursor, p_word| unsafe { get_reg(cursor, reg, p_word) })
     }
 
     pub fn get_fpreg(&self, reg: unw_regnum_t) -> Result<unw_fpreg_t, UnwindError> {
-        self.ffi("unw_get_fpreg", |cursor, p_fpreg| unsafe { unw_get_fpreg(cursor, reg, p_fpreg) })
+        let (get_fpreg, reg) = (self.library.get_fpreg, (self.library.regnum)(reg));
+        self.ffi(|cursor, p_fpreg| unsafe { get_fpreg(cursor, reg, p_fpreg) })
     }
 
-    pub fn resume(&self) -> Result<(), UnwindError> {
-        self.ffi("unw_resume", |cursor, _p_unit| unsafe { unw_resume(cursor) })
+    /// Resumes execution in the cursor's frame, with its registers. Only
+    /// returns if that fails.
+    ///
+    /// # Safety
+    ///
+    /// The frames above the cursor's are abandoned without running their
+    /// destructors: the caller must ensure none of them owns a value
+    /// whose drop is relied upon, such as a lock guard. The cursor must
+    /// be for a frame of the current thread, and its registers, which
+    /// may have been changed to arbitrary values, must describe a state
+    /// the frame's code can continue from.
+    pub unsafe fn resume(&self) -> UnwindError {
+        match self.ffi_ret(|cursor| (self.library.resume)(cursor)) {
+            Ok(_) => UnwindError::Unspec,
+            Err(e) => e,
+        }
     }
 
-    pub fn get_proc_info(&self) -> Result<unw_proc_info_t, UnwindError> {
-        //! Warning: I keep getting core dumps from within a call to
-        //! strncp (within `unw_get_proc_info) when I try to use this
-        //! function. Presumably it has some extra preconditions on
-        //! p_pip (or cursor?) but I cannot figure out from the
-        //! documentation what they might be.
-        //!
-        //! In any case, I believe I can extract the infomation I need
-        //! via `fn get_proc_name` and `fn get_reg(IP)`
-        self.ffi("unw_get_proc_info", |cursor, p_pip| unsafe { unw_get_proc_info(cursor, p_pip) })
+    /// The procedure containing the cursor's instruction pointer, as
+    /// described by its unwind information
+    pub fn get_proc_info(&self) -> Result<ProcInfo, UnwindError> {
+        let get_proc_info = self.library.get_proc_info;
+        self.ffi(|cursor, p_pip| unsafe { get_proc_info(cursor, p_pip) })
+            .map(|pi: unw_proc_info_t| ProcInfo {
+                start_ip: pi.start_ip,
+                end_ip: pi.end_ip,
+                lsda: pi.lsda,
+                handler: pi.handler,
+                unwind_info: pi.unwind_info,
+            })
     }
 
     // (This is apparently not offered by the LLVM libunwind fork?)
     #[cfg(not_now)]
     pub fn get_save_loc(&self, i: c_int) -> Result<unw_save_loc_t, UnwindError> {
-        self.ffi("unw_get_save_loc", |cursor, p_loc| unsafe { unw_get_save_loc(cursor, i, p_loc) })
+        self.ffi(|cursor, p_loc| unsafe { unw_get_save_loc(cursor, i, p_loc) })
     }
 
     pub fn is_signal_frame(&self) -> Result<bool, UnwindError> {
-        self.ffi_ret("unw_is_signal_frame", |cursor| unsafe { unw_is_signal_frame(cursor) })
+        let is_signal_frame = self.library.is_signal_frame;
+        self.ffi_ret(|cursor| unsafe { is_signal_frame(cursor) })
             .map(|ret| ret > 0)
     }
 
+    /// Writes the name of the procedure containing the cursor's
+    /// instruction pointer to `bufp`, replacing its contents, and returns
+    /// the offset of the instruction pointer in the procedure. The buffer
+    /// grows until the name fits: nongnu reports a truncated name as
+    /// `NoMem`, while the LLVM fork silently truncates it to fill the
+    /// buffer, so a name filling the buffer is retried too.
     pub fn get_proc_name(&self, bufp: &mut Vec<u8>) -> Result<unw_word_t, UnwindError> {
-        unsafe {
-            let mut off: unw_word_t = mem::uninitialized();
-            let r: *const unw_cursor_t = &self.repr;
-            let p: *mut u8 = bufp.as_mut_ptr();
-            let p: *mut c_char = p as *mut c_char;
-            let c: size_t = bufp.capacity();
-            let offp: *mut unw_word_t = &mut off as *mut unw_word_t;
-            // println!("calling unw_get_proc_name r: {:?} p: {:?} c: {:?} offp", r, p, c);
-            let ret: c_int = unw_get_proc_name(r, p, c, offp);
-            if ret != 0 {
-                return Err(match_errs!(ret, panic!("got error from unw_get_proc_name")));
+        let mut len = cmp::max(bufp.capacity(), 64);
+        loop {
+            bufp.clear();
+            bufp.resize(len, 0);
+            let p = bufp.as_mut_ptr() as *mut c_char;
+            let mut off: unw_word_t = 0;
+            let get_proc_name = self.library.get_proc_name;
+            match self.ffi_ret(|cursor| unsafe { get_proc_name(cursor, p, len, &mut off) }) {
+                // The name was truncated to fit
+                Err(UnwindError::NoMem) if len < MAX_PROC_NAME_LEN => len *= 2,
+                Err(e) => { bufp.clear(); return Err(e); }
+                Ok(_) => {
+                    let end = bufp.iter().position(|&b| b == 0).unwrap_or(len);
+                    if end + 1 >= len && len < MAX_PROC_NAME_LEN {
+                        len *= 2;
+                        continue;
+                    }
+                    bufp.truncate(end);
+                    return Ok(off);
+                }
             }
-            let len = libc::strlen(p);
-            bufp.set_len(len);
-            Ok(off)
         }
     }
+}
+
+/// A procedure (function), as described by its unwind information
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProcInfo {
+    /// Address of the first instruction
+    pub start_ip: unw_word_t,
+    /// Address after the last instruction
+    pub end_ip: unw_word_t,
+    /// Address of the language-specific data area, or zero
+    pub lsda: unw_word_t,
+    /// Address of the personality routine, or zero
+    pub handler: unw_word_t,
+    /// Address of the DWARF unwind information, or zero
+    pub unwind_info: unw_word_t,
+}
+
+/// A frame of the stack, as reached by `Frames`
+pub struct Frame<'a> {
+    /// Instruction pointer: the return address, for frames other than
+    /// the first
+    pub ip: unw_word_t,
+    /// Stack pointer
+    pub sp: unw_word_t,
+    cursor: UnwindCursor<'a>,
+}
+
+impl<'a> Frame<'a> {
+    /// A cursor on the frame, to read its other registers
+    pub fn cursor(&self) -> &UnwindCursor<'a> {
+        &self.cursor
+    }
+}
 
+/// Iterator over the frames of a stack, returned by
+/// `UnwindCursor::frames`. A failure to unwind ends the iteration after
+/// yielding the error.
+pub struct Frames<'a> {
+    cursor: UnwindCursor<'a>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> Frames<'a> {
+    fn frame(&self) -> Result<Frame<'a>, UnwindError> {
+        Ok(Frame {
+            ip: self.cursor.get_reg(UNW_REG_IP)?,
+            sp: self.cursor.get_reg(UNW_REG_SP)?,
+            cursor: self.cursor.clone(),
+        })
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<Frame<'a>, UnwindError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.started {
+            match self.cursor.step() {
+                Ok(true) => {}
+                Ok(false) => { self.done = true; return None; }
+                Err(e) => { self.done = true; return Some(Err(e)); }
+            }
+        }
+        self.started = true;
+        let frame = self.frame();
+        self.done = frame.is_err();
+        Some(frame)
+    }
 }
 
 pub fn is_fpreg(regnum: unw_regnum_t) -> bool {
@@ -0,0 +1,527 @@
+//! Unwinding of another process, stopped under ptrace.
+//!
+//! libunwind reaches the state of a remote process through the
+//! accessors of an address space. `TracedProcess` implements them with
+//! ptrace for memory and registers, and finds the unwind tables and
+//! symbols of the code in the ELF files `/proc/<pid>/maps` lists.
+//!
+//! Address spaces are an interface of the HP/nongnu libunwind, which the
+//! LLVM fork the rest of `unwind_hack` binds lacks. Remote cursors are
+//! therefore initialized and used with the functions of nongnu's generic
+//! x86-64 library, `libunwind-x86_64`, under its `_Ux86_64_` names, with
+//! its own error codes and register numbers.
+
+use libc::{self, c_char, c_int, c_void, pid_t, size_t};
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::mem;
+use std::ptr;
+use std::rc::Rc;
+
+use byteorder::{ByteOrder, NativeEndian};
+use elf;
+use elf::types;
+use unravel::registers::Arch;
+
+use super::{unw_addr_space_t, unw_cursor_t, unw_fpreg_t, unw_proc_info_t, unw_regnum_t,
+            unw_word_t, Library, UnwindCursor, UnwindError, UNW_REG_IP, UNW_REG_SP};
+
+/// Callbacks through which libunwind reads the process an address space
+/// describes. Each returns 0 or a negated nongnu error code (see
+/// `code`), and gets the `arg` given to `unw_init_remote`.
+#[repr(C)]
+pub struct unw_accessors_t {
+    /// Finds the procedure containing `ip`, and its unwind information
+    /// if `need_unwind_info` is nonzero
+    pub find_proc_info: extern "C" fn(unw_addr_space_t, unw_word_t, *mut unw_proc_info_t, c_int, *mut c_void) -> c_int,
+    /// Releases the unwind information `find_proc_info` allocated
+    pub put_unwind_info: extern "C" fn(unw_addr_space_t, *mut unw_proc_info_t, *mut c_void),
+    /// Finds the list of dynamically registered unwind information
+    pub get_dyn_info_list_addr: extern "C" fn(unw_addr_space_t, *mut unw_word_t, *mut c_void) -> c_int,
+    /// Reads or, if the `c_int` is nonzero, writes a word of memory
+    pub access_mem: extern "C" fn(unw_addr_space_t, unw_word_t, *mut unw_word_t, c_int, *mut c_void) -> c_int,
+    /// Reads or writes a register of the innermost frame
+    pub access_reg: extern "C" fn(unw_addr_space_t, unw_regnum_t, *mut unw_word_t, c_int, *mut c_void) -> c_int,
+    /// Reads or writes a floating-point register of the innermost frame
+    pub access_fpreg: extern "C" fn(unw_addr_space_t, unw_regnum_t, *mut unw_fpreg_t, c_int, *mut c_void) -> c_int,
+    /// Resumes execution at the cursor
+    pub resume: extern "C" fn(unw_addr_space_t, *mut unw_cursor_t, *mut c_void) -> c_int,
+    /// Finds the name of the procedure containing an address, and the
+    /// address's offset in it
+    pub get_proc_name: extern "C" fn(unw_addr_space_t, unw_word_t, *mut c_char, size_t, *mut unw_word_t, *mut c_void) -> c_int,
+}
+
+/// `format` of a `unw_dyn_info_t` describing a `.eh_frame_hdr` search
+/// table in the memory of the remote process
+pub const UNW_INFO_FORMAT_REMOTE_TABLE: i32 = 2;
+
+/// A binary search table of FDEs, as in `.eh_frame_hdr`
+#[repr(C)]
+pub struct unw_dyn_remote_table_info_t {
+    /// address of the name of the table's object, or zero
+    pub name_ptr: unw_word_t,
+    /// base for the table's data-relative addresses
+    pub segbase: unw_word_t,
+    /// size of the table in words
+    pub table_len: unw_word_t,
+    /// address of the table
+    pub table_data: unw_word_t,
+}
+
+/// Unwind information for a range of code
+#[repr(C)]
+pub struct unw_dyn_info_t {
+    next: *mut unw_dyn_info_t,
+    prev: *mut unw_dyn_info_t,
+    /// first address covered
+    pub start_ip: unw_word_t,
+    /// address after the last one covered
+    pub end_ip: unw_word_t,
+    /// global pointer, where the machine has one
+    pub gp: unw_word_t,
+    pub format: i32,
+    pad: i32,
+    /// bias added to the addresses of the table
+    pub load_offset: unw_word_t,
+    pub rti: unw_dyn_remote_table_info_t,
+}
+
+
+#[link(name="unwind-x86_64")]
+extern {
+    #[link_name="_Ux86_64_create_addr_space"]
+    fn unw_create_addr_space(a: *const unw_accessors_t, byteorder: c_int) -> unw_addr_space_t;
+    #[link_name="_Ux86_64_destroy_addr_space"]
+    fn unw_destroy_addr_space(a: unw_addr_space_t);
+    #[link_name="_Ux86_64_init_remote"]
+    fn unw_init_remote(c: *mut unw_cursor_t, a: unw_addr_space_t, arg: *mut c_void) -> c_int;
+    /// Helper for `find_proc_info` accessors: finds `ip` in the search
+    /// table `di` describes
+    #[link_name="_Ux86_64_dwarf_search_unwind_table"]
+    fn dwarf_search_unwind_table(a: unw_addr_space_t, ip: unw_word_t, di: *mut unw_dyn_info_t,
+                                 pip: *mut unw_proc_info_t, need_unwind_info: c_int,
+                                 arg: *mut c_void) -> c_int;
+
+    #[link_name="_Ux86_64_step"]
+    fn unw_step(cp: *mut unw_cursor_t) -> c_int;
+    #[link_name="_Ux86_64_get_reg"]
+    fn unw_get_reg(cp: *const unw_cursor_t, reg: unw_regnum_t, valp: *mut unw_word_t) -> c_int;
+    #[link_name="_Ux86_64_get_fpreg"]
+    fn unw_get_fpreg(cp: *const unw_cursor_t, reg: unw_regnum_t, valp: *mut unw_fpreg_t) -> c_int;
+    #[link_name="_Ux86_64_resume"]
+    fn unw_resume(cp: *const unw_cursor_t) -> c_int;
+    #[link_name="_Ux86_64_get_proc_info"]
+    fn unw_get_proc_info(cp: *const unw_cursor_t, pip: *mut unw_proc_info_t) -> c_int;
+    #[link_name="_Ux86_64_is_signal_frame"]
+    fn unw_is_signal_frame(cp: *const unw_cursor_t) -> c_int;
+    #[link_name="_Ux86_64_get_proc_name"]
+    fn unw_get_proc_name(cp: *const unw_cursor_t, bufp: *mut c_char, len: size_t, offp: *mut unw_word_t) -> c_int;
+}
+
+/// nongnu's x86-64 registers follow the DWARF numbering, with the
+/// instruction pointer as register 16
+const NONGNU_REG_IP: unw_regnum_t = 16;
+const NONGNU_REG_SP: unw_regnum_t = 7;
+
+/// The errors of nongnu's error codes 1 to 10, which its functions return
+/// negated
+const NONGNU_ERRORS: [UnwindError; 10] = [
+    UnwindError::Unspec,
+    UnwindError::NoMem,
+    UnwindError::BadReg,
+    UnwindError::ReadOnlyReg,
+    UnwindError::StopUnwind,
+    UnwindError::InvalidIP,
+    UnwindError::BadFrame,
+    UnwindError::Invalid,
+    UnwindError::BadVersion,
+    UnwindError::NoInfo,
+];
+
+/// The error for the negative code a nongnu function returned
+fn error(code: c_int) -> UnwindError {
+    if -(NONGNU_ERRORS.len() as c_int) <= code && code < 0 {
+        NONGNU_ERRORS[(-code - 1) as usize]
+    } else {
+        UnwindError::Other(code)
+    }
+}
+
+/// The negative code nongnu reports `e` with
+fn code(e: UnwindError) -> c_int {
+    match NONGNU_ERRORS.iter().position(|&x| x == e) {
+        Some(i) => -(i as c_int + 1),
+        None => e.code(),
+    }
+}
+
+/// nongnu's number for a register numbered as in the LLVM fork
+fn regnum(regnum: unw_regnum_t) -> unw_regnum_t {
+    match regnum {
+        UNW_REG_IP => NONGNU_REG_IP,
+        UNW_REG_SP => NONGNU_REG_SP,
+        regnum => regnum,
+    }
+}
+
+static NONGNU: Library = Library {
+    step: unw_step,
+    get_reg: unw_get_reg,
+    get_fpreg: unw_get_fpreg,
+    resume: unw_resume,
+    get_proc_info: unw_get_proc_info,
+    is_signal_frame: unw_is_signal_frame,
+    get_proc_name: unw_get_proc_name,
+    regnum: regnum,
+    error: error,
+};
+
+/// `NT_PRSTATUS`, the general-purpose register set of `PTRACE_GETREGSET`
+const NT_PRSTATUS: usize = 1;
+
+/// A range of the address space mapped from a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Mapping {
+    start: u64,
+    end: u64,
+    /// Offset in the file of the mapping's first byte
+    offset: u64,
+    path: String,
+}
+
+impl Mapping {
+    /// Parses a line of `/proc/<pid>/maps`, e.g.
+    /// "00400000-00452000 r-xp 00000000 08:02 173521 /usr/bin/dbus-daemon".
+    /// Anonymous mappings have no path and are skipped.
+    fn parse(line: &str) -> Option<Mapping> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || !fields[5].starts_with('/') {
+            return None;
+        }
+        let mut range = fields[0].splitn(2, '-').map(|n| u64::from_str_radix(n, 16));
+        match (range.next(), range.next(), u64::from_str_radix(fields[2], 16)) {
+            (Some(Ok(start)), Some(Ok(end)), Ok(offset)) => Some(Mapping {
+                start: start,
+                end: end,
+                offset: offset,
+                path: fields[5].to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// What is known about an ELF file mapped into the process
+struct Module {
+    /// Difference between the run-time and link-time addresses
+    bias: u64,
+    /// Link-time address and contents of `.eh_frame_hdr`
+    eh_frame_hdr: Option<(u64, Vec<u8>)>,
+    /// Function symbols as (link-time address, size, name), by address
+    functions: Vec<(u64, u64, String)>,
+}
+
+impl Module {
+    /// Loads the file `mapping` maps, if it is an ELF file.
+    fn load(mapping: &Mapping) -> Option<Module> {
+        let file = match elf::File::open_path(&mapping.path) {
+            Ok(file) => file,
+            Err(_) => return None,
+        };
+        // The loadable segment holding the mapped bytes gives the bias. A
+        // segment is mapped from the start of the page it begins in, which
+        // may hold the end of the previous segment too.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let segment = file.phdrs.iter().rev().find(|ph| {
+            ph.progtype == types::PT_LOAD && ph.offset & !(page - 1) <= mapping.offset
+                && mapping.offset < ph.offset + ph.filesz
+        });
+        let segment = match segment {
+            Some(segment) => segment,
+            None => return None,
+        };
+        let bias = mapping.start
+            .wrapping_sub(mapping.offset)
+            .wrapping_add(segment.offset)
+            .wrapping_sub(segment.vaddr);
+        let eh_frame_hdr = file.phdrs.iter()
+            .find(|ph| ph.progtype == types::PT_GNU_EH_FRAME)
+            .and_then(|ph| file.segment_data(ph).map(|data| (ph.vaddr, data.to_vec())));
+
+        let mut symbols = file.symbols().unwrap_or_default();
+        symbols.extend(file.dynamic_symbols().unwrap_or_default());
+        let mut functions: Vec<_> = symbols.into_iter()
+            .filter(|sym| sym.sym_type() == types::STT_FUNC && sym.shndx != types::SHN_UNDEF)
+            .map(|sym| (sym.value, sym.size, sym.name))
+            .collect();
+        functions.sort();
+        functions.dedup_by_key(|f| f.0);
+        Some(Module { bias: bias, eh_frame_hdr: eh_frame_hdr, functions: functions })
+    }
+
+    /// Describes the search table of `.eh_frame_hdr` for the code of
+    /// `mapping`, or returns `None` if the module has none or it is not
+    /// in the usual encoding.
+    fn search_table(&self, mapping: &Mapping) -> Option<unw_dyn_info_t> {
+        let (vaddr, hdr) = match self.eh_frame_hdr {
+            Some((vaddr, ref hdr)) => (vaddr, hdr),
+            None => return None,
+        };
+        // version, eh_frame_ptr encoding, fde_count encoding, table encoding
+        if hdr.len() < 4 || hdr[0] != 1 {
+            return None;
+        }
+        let eh_frame_ptr_size = match hdr[1] & 0x0f {
+            0x03 | 0x0b => 4, // udata4, sdata4
+            0x04 | 0x0c => 8, // udata8, sdata8
+            _ => return None,
+        };
+        // fde_count is a udata4, and the table entries are datarel sdata4
+        if hdr[2] != 0x03 || hdr[3] != 0x3b {
+            return None;
+        }
+        let count_at = 4 + eh_frame_ptr_size;
+        if hdr.len() < count_at + 4 {
+            return None;
+        }
+        let fde_count = NativeEndian::read_u32(&hdr[count_at..]) as u64;
+        let segbase = self.bias.wrapping_add(vaddr);
+        Some(unw_dyn_info_t {
+            next: ptr::null_mut(),
+            prev: ptr::null_mut(),
+            start_ip: mapping.start,
+            end_ip: mapping.end,
+            gp: 0,
+            format: UNW_INFO_FORMAT_REMOTE_TABLE,
+            pad: 0,
+            load_offset: 0,
+            rti: unw_dyn_remote_table_info_t {
+                name_ptr: 0,
+                segbase: segbase,
+                // Each entry is two 4-byte addresses
+                table_len: fde_count * 8 / mem::size_of::<unw_word_t>() as u64,
+                table_data: segbase + count_at as u64 + 4,
+            },
+        })
+    }
+
+    /// The function containing the run-time `address`, and the offset of
+    /// the address in it
+    fn function(&self, address: u64) -> Option<(&str, u64)> {
+        let address = address.wrapping_sub(self.bias);
+        let i = match self.functions.binary_search_by_key(&address, |f| f.0) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        let (start, size, ref name) = self.functions[i];
+        if address < start + cmp::max(size, 1) {
+            Some((name, address - start))
+        } else {
+            None
+        }
+    }
+}
+
+/// A process stopped under ptrace by the calling thread, whose stack can
+/// be unwound with the cursor API.
+///
+/// The registers and mappings are read when the `TracedProcess` is
+/// created, so it describes the process only while it stays stopped.
+pub struct TracedProcess {
+    pid: pid_t,
+    /// General-purpose registers, in the order of `elf_gregset_t`
+    registers: Vec<u64>,
+    mappings: Vec<Mapping>,
+    /// Modules loaded so far, by path; `None` if the file is unusable
+    modules: RefCell<HashMap<String, Option<Rc<Module>>>>,
+    address_space: unw_addr_space_t,
+}
+
+static ACCESSORS: unw_accessors_t = unw_accessors_t {
+    find_proc_info: find_proc_info,
+    put_unwind_info: put_unwind_info,
+    get_dyn_info_list_addr: get_dyn_info_list_addr,
+    access_mem: access_mem,
+    access_reg: access_reg,
+    access_fpreg: access_fpreg,
+    resume: resume,
+    get_proc_name: get_proc_name,
+};
+
+impl TracedProcess {
+    /// Reads the state of `pid`, which must be a ptrace-stopped tracee of
+    /// the calling thread running the same architecture.
+    pub fn new(pid: pid_t) -> Result<TracedProcess, UnwindError> {
+        let mut words: [libc::c_ulong; 64] = [0; 64];
+        let mut iov = libc::iovec {
+            iov_base: words.as_mut_ptr() as *mut c_void,
+            iov_len: mem::size_of_val(&words),
+        };
+        let ret = unsafe {
+            libc::ptrace(libc::PTRACE_GETREGSET, pid, NT_PRSTATUS as *mut c_void,
+                         &mut iov as *mut _ as *mut c_void)
+        };
+        if ret < 0 {
+            return Err(UnwindError::BadFrame);
+        }
+        let count = iov.iov_len / mem::size_of::<libc::c_ulong>();
+        let registers = words[..count].iter().map(|&w| w as u64).collect();
+
+        let maps = fs::File::open(format!("/proc/{}/maps", pid))
+            .map_err(|_| UnwindError::NoInfo)?;
+        let mappings = BufReader::new(maps).lines()
+            .filter_map(|line| line.ok().and_then(|line| Mapping::parse(&line)))
+            .collect();
+
+        let address_space = unsafe { unw_create_addr_space(&ACCESSORS, 0) };
+        if address_space.is_null() {
+            return Err(UnwindError::NoMem);
+        }
+        Ok(TracedProcess {
+            pid: pid,
+            registers: registers,
+            mappings: mappings,
+            modules: RefCell::new(HashMap::new()),
+            address_space: address_space,
+        })
+    }
+
+    /// A cursor on the innermost frame of the stopped thread
+    pub fn cursor(&self) -> Result<UnwindCursor, UnwindError> {
+        let mut c = UnwindCursor::blank(&NONGNU);
+        let arg = self as *const TracedProcess as *mut c_void;
+        match unsafe { unw_init_remote(&mut c.repr, self.address_space, arg) } {
+            0 => Ok(c),
+            code => Err(error(code)),
+        }
+    }
+
+    /// The module mapped at `address`, with its mapping
+    fn module(&self, address: u64) -> Option<(&Mapping, Rc<Module>)> {
+        let mapping = match self.mappings.iter().find(|m| m.start <= address && address < m.end) {
+            Some(mapping) => mapping,
+            None => return None,
+        };
+        let mut modules = self.modules.borrow_mut();
+        let module = modules.entry(mapping.path.clone())
+            .or_insert_with(|| Module::load(mapping).map(Rc::new));
+        module.clone().map(|module| (mapping, module))
+    }
+
+    /// Reads the word at `address` of the process memory.
+    fn read_word(&self, address: u64) -> Option<unw_word_t> {
+        unsafe {
+            *libc::__errno_location() = 0;
+            let word = libc::ptrace(libc::PTRACE_PEEKDATA, self.pid, address as *mut c_void,
+                                    ptr::null_mut::<c_void>());
+            if word == -1 && *libc::__errno_location() != 0 {
+                None
+            } else {
+                Some(word as unw_word_t)
+            }
+        }
+    }
+
+    /// The `elf_gregset_t` index of the register nongnu numbers `regnum`
+    fn register_index(&self, regnum: unw_regnum_t) -> Option<usize> {
+        if regnum < 0 {
+            return None;
+        }
+        Arch::X86_64.register(regnum as u16).and_then(|r| r.gregset)
+    }
+}
+
+impl Drop for TracedProcess {
+    fn drop(&mut self) {
+        unsafe { unw_destroy_addr_space(self.address_space) }
+    }
+}
+
+fn process<'a>(arg: *mut c_void) -> &'a TracedProcess {
+    unsafe { &*(arg as *const TracedProcess) }
+}
+
+extern "C" fn find_proc_info(a: unw_addr_space_t, ip: unw_word_t, pip: *mut unw_proc_info_t,
+                             need_unwind_info: c_int, arg: *mut c_void) -> c_int {
+    let mut table = match process(arg).module(ip) {
+        Some((mapping, module)) => match module.search_table(mapping) {
+            Some(table) => table,
+            None => return code(UnwindError::NoInfo),
+        },
+        None => return code(UnwindError::NoInfo),
+    };
+    unsafe { dwarf_search_unwind_table(a, ip, &mut table, pip, need_unwind_info, arg) }
+}
+
+extern "C" fn put_unwind_info(_: unw_addr_space_t, pip: *mut unw_proc_info_t, _: *mut c_void) {
+    // `dwarf_search_unwind_table` mallocs the unwind information
+    unsafe {
+        libc::free((*pip).unwind_info as usize as *mut c_void);
+        (*pip).unwind_info = 0;
+    }
+}
+
+extern "C" fn get_dyn_info_list_addr(_: unw_addr_space_t, _: *mut unw_word_t,
+                                     _: *mut c_void) -> c_int {
+    code(UnwindError::NoInfo)
+}
+
+extern "C" fn access_mem(_: unw_addr_space_t, address: unw_word_t, valp: *mut unw_word_t,
+                         write: c_int, arg: *mut c_void) -> c_int {
+    if write != 0 {
+        return code(UnwindError::ReadOnlyReg);
+    }
+    match process(arg).read_word(address as u64) {
+        Some(word) => { unsafe { *valp = word; } 0 }
+        None => code(UnwindError::Invalid),
+    }
+}
+
+extern "C" fn access_reg(_: unw_addr_space_t, regnum: unw_regnum_t, valp: *mut unw_word_t,
+                         write: c_int, arg: *mut c_void) -> c_int {
+    if write != 0 {
+        return code(UnwindError::ReadOnlyReg);
+    }
+    let process = process(arg);
+    match process.register_index(regnum).and_then(|i| process.registers.get(i)) {
+        Some(&value) => { unsafe { *valp = value as unw_word_t; } 0 }
+        None => code(UnwindError::BadReg),
+    }
+}
+
+extern "C" fn access_fpreg(_: unw_addr_space_t, _: unw_regnum_t, _: *mut unw_fpreg_t,
+                           _: c_int, _: *mut c_void) -> c_int {
+    code(UnwindError::BadReg)
+}
+
+extern "C" fn resume(_: unw_addr_space_t, _: *mut unw_cursor_t, _: *mut c_void) -> c_int {
+    code(UnwindError::Invalid)
+}
+
+extern "C" fn get_proc_name(_: unw_addr_space_t, address: unw_word_t, buf: *mut c_char,
+                            len: size_t, offp: *mut unw_word_t, arg: *mut c_void) -> c_int {
+    let process = process(arg);
+    let module = match process.module(address as u64) {
+        Some((_, module)) => module,
+        None => return code(UnwindError::NoInfo),
+    };
+    let (name, offset) = match module.function(address as u64) {
+        Some(function) => function,
+        None => return code(UnwindError::NoInfo),
+    };
+    if len == 0 {
+        return code(UnwindError::NoMem);
+    }
+    // Copy as much of the name as fits, NUL-terminated
+    let n = cmp::min(name.len(), len - 1);
+    unsafe {
+        ptr::copy_nonoverlapping(name.as_ptr() as *const c_char, buf, n);
+        *buf.offset(n as isize) = 0;
+        *offp = offset as unw_word_t;
+    }
+    if n < name.len() { code(UnwindError::NoMem) } else { 0 }
+}
@@ -0,0 +1,209 @@
+//! Tests unwinding the stack of the test itself.
+
+use super::*;
+use super::{same_regnum, unw_get_fpreg, unw_get_proc_info, unw_get_reg, unw_is_signal_frame,
+            unw_resume, unw_step, Library};
+use libc::{self, c_char, c_int, c_void, size_t};
+use std::error;
+
+/// The name of each frame's function, from the caller of this one
+/// outwards, with the frame's stack pointer.
+#[inline(never)]
+fn walk() -> Vec<(String, unw_word_t)> {
+    UnwindContext::with(|context| {
+        context.cursor().unwrap().frames().map(|frame| {
+            let frame = frame.unwrap();
+            let mut buf = Vec::with_capacity(256);
+            let name = match frame.cursor().get_proc_name(&mut buf) {
+                Ok(_) => String::from_utf8(buf).unwrap(),
+                Err(_) => String::new(),
+            };
+            (name, frame.sp)
+        }).collect()
+    }).unwrap()
+}
+
+#[test]
+fn frames_reach_caller() {
+    let frames = walk();
+    assert!(frames[0].0.contains("4walk"), "{:?}", frames);
+    assert!(frames[1].0.contains("frames_reach_caller"), "{:?}", frames);
+    // The stack grows down, so callers' frames are at higher addresses
+    assert!(frames.windows(2).all(|w| w[0].1 <= w[1].1), "{:?}", frames);
+}
+
+#[test]
+fn cloned_cursor_steps_independently() {
+    UnwindContext::with(|context| {
+        let mut cursor = context.cursor().unwrap();
+        let copy = cursor.clone();
+        let ip = copy.get_reg(UNW_REG_IP).unwrap();
+        assert_eq!(cursor.step(), Ok(true));
+        assert!(cursor.get_reg(UNW_REG_IP).unwrap() != ip);
+        assert_eq!(copy.get_reg(UNW_REG_IP).unwrap(), ip);
+    }).unwrap();
+}
+
+/// The name of this function, read into a buffer with no room for it.
+#[inline(never)]
+fn a_function_with_a_name_longer_than_the_buffer_get_proc_name_starts_with() -> Vec<u8> {
+    UnwindContext::with(|context| {
+        let mut buf = Vec::new();
+        context.cursor().unwrap().get_proc_name(&mut buf).unwrap();
+        buf
+    }).unwrap()
+}
+
+#[test]
+fn proc_name_grows_buffer() {
+    let name = a_function_with_a_name_longer_than_the_buffer_get_proc_name_starts_with();
+    let name = String::from_utf8(name).unwrap();
+    assert!(name.contains("a_function_with_a_name_longer_than_the_buffer"), "{}", name);
+    assert!(!name.contains('\0'));
+}
+
+/// A name longer than the buffers `get_proc_name` starts with
+const LONG_NAME: &'static [u8] = &[b'x'; 300];
+
+/// Copies `LONG_NAME` as the LLVM fork does, truncating it to fit the
+/// buffer without reporting an error.
+unsafe extern "C" fn snprintf_proc_name(_: *const unw_cursor_t, bufp: *mut c_char, len: size_t,
+                                        offp: *mut unw_word_t) -> c_int {
+    let n = ::std::cmp::min(LONG_NAME.len(), len - 1);
+    ::std::ptr::copy_nonoverlapping(LONG_NAME.as_ptr() as *const c_char, bufp, n);
+    *bufp.offset(n as isize) = 0;
+    *offp = 8;
+    0
+}
+
+static TRUNCATING: Library = Library {
+    step: unw_step,
+    get_reg: unw_get_reg,
+    get_fpreg: unw_get_fpreg,
+    resume: unw_resume,
+    get_proc_info: unw_get_proc_info,
+    is_signal_frame: unw_is_signal_frame,
+    get_proc_name: snprintf_proc_name,
+    regnum: same_regnum,
+    error: UnwindError::from_code,
+};
+
+#[test]
+fn proc_name_retries_silent_truncation() {
+    let cursor = UnwindCursor::blank(&TRUNCATING);
+    let mut buf = Vec::new();
+    assert_eq!(cursor.get_proc_name(&mut buf), Ok(8));
+    assert_eq!(&buf[..], LONG_NAME);
+}
+
+#[test]
+fn error_codes() {
+    for code in -6549..-6539 {
+        let e = UnwindError::from_code(code);
+        assert!(e != UnwindError::Other(code));
+        assert_eq!(e.code(), code);
+    }
+    assert_eq!(UnwindError::from_code(UNW_ENOINFO as c_int), UnwindError::NoInfo);
+    assert_eq!(UnwindError::from_code(-1), UnwindError::Other(-1));
+    assert_eq!(UnwindError::Other(-1).code(), -1);
+    assert_eq!(UnwindError::BadReg.to_string(), "bad register number");
+    assert_eq!(error::Error::description(&UnwindError::NoInfo), "no unwind info found");
+}
+
+/// The instruction pointer and procedure of this function's frame and
+/// of its caller's.
+#[inline(never)]
+fn walk_info() -> Vec<(unw_word_t, ProcInfo)> {
+    UnwindContext::with(|context| {
+        context.cursor().unwrap().frames().take(2).map(|frame| {
+            let frame = frame.unwrap();
+            (frame.ip, frame.cursor().get_proc_info().unwrap())
+        }).collect()
+    }).unwrap()
+}
+
+#[test]
+fn proc_info_bounds_frames() {
+    let frames = walk_info();
+    assert_eq!(frames[0].1.start_ip, walk_info as *const () as unw_word_t);
+    assert_eq!(frames[1].1.start_ip, proc_info_bounds_frames as *const () as unw_word_t);
+    for &(ip, ref info) in frames.iter() {
+        assert!(info.start_ip < ip && ip < info.end_ip, "{:#x} {:?}", ip, info);
+    }
+}
+
+/// Called from generated code, which has no unwind information: reads
+/// the procedure information of the caller's frame into `result`.
+extern "C" fn proc_info_of_caller(result: *mut Result<ProcInfo, UnwindError>) {
+    let info = UnwindContext::with(|context| {
+        let mut cursor = context.cursor().unwrap();
+        assert_eq!(cursor.step(), Ok(true));
+        cursor.get_proc_info()
+    }).unwrap();
+    unsafe { *result = info; }
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn proc_info_without_unwind_info() {
+    // sub $8, %rsp; mov %rdi, %rax; mov %rsi, %rdi; call *%rax;
+    // add $8, %rsp; ret
+    let code: [u8; 18] = [0x48, 0x83, 0xec, 0x08, 0x48, 0x89, 0xf8, 0x48, 0x89, 0xf7,
+                          0xff, 0xd0, 0x48, 0x83, 0xc4, 0x08, 0xc3, 0xcc];
+    let mut result = Err(UnwindError::Unspec);
+    unsafe {
+        let page = libc::mmap(0 as *mut c_void, 4096, libc::PROT_READ | libc::PROT_WRITE,
+                              libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1, 0);
+        assert!(page != libc::MAP_FAILED);
+        ::std::ptr::copy_nonoverlapping(code.as_ptr(), page as *mut u8, code.len());
+        assert_eq!(libc::mprotect(page, 4096, libc::PROT_READ | libc::PROT_EXEC), 0);
+        let stub: extern "C" fn(extern "C" fn(*mut Result<ProcInfo, UnwindError>),
+                                *mut Result<ProcInfo, UnwindError>) =
+            ::std::mem::transmute(page);
+        stub(proc_info_of_caller, &mut result);
+        libc::munmap(page, 4096);
+    }
+    // The generated code has no unwind information to describe it
+    assert_eq!(result, Err(UnwindError::NoInfo));
+}
+
+/// Stops the calling process for its tracer, in a frame of its own.
+#[inline(never)]
+fn stop_for_tracer() {
+    unsafe { libc::raise(libc::SIGSTOP); }
+}
+
+#[test]
+#[cfg(all(libunwind_nongnu, target_arch = "x86_64"))]
+fn traced_child_frames() {
+    let pid = unsafe { libc::fork() };
+    assert!(pid >= 0);
+    if pid == 0 {
+        unsafe {
+            if libc::ptrace(libc::PTRACE_TRACEME, 0, 0 as *mut c_void, 0 as *mut c_void) == 0 {
+                stop_for_tracer();
+            }
+            libc::_exit(1);
+        }
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+    assert!(libc::WIFSTOPPED(status), "{:#x}", status);
+
+    let names: Vec<String> = {
+        let process = ptrace::TracedProcess::new(pid).unwrap();
+        let cursor = process.cursor().unwrap();
+        cursor.frames().take(32).filter_map(|frame| {
+            let mut buf = Vec::with_capacity(256);
+            frame.ok().and_then(|f| f.cursor().get_proc_name(&mut buf).ok())
+                .map(|_| String::from_utf8_lossy(&buf).into_owned())
+        }).collect()
+    };
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+        libc::waitpid(pid, &mut status, 0);
+    }
+    let child = names.iter().position(|n| n.contains("stop_for_tracer"));
+    let caller = names.iter().position(|n| n.contains("traced_child_frames"));
+    assert!(child.is_some() && child < caller, "{:?}", names);
+}